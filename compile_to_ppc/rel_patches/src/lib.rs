@@ -56,6 +56,10 @@ unsafe extern "C" fn update_main_menu_text(frame: *mut CGuiFrame, widget_name: *
 
     for name in &[b"textpane_identifier\0".as_ptr(), b"textpane_identifierb\0".as_ptr()] {
         let widget = CGuiFrame::find_widget(frame, *name);
+        // The patcher doesn't add the text panes when there's no message to show
+        if widget.is_null() {
+            continue;
+        }
         let text_support = CGuiTextPane::text_support_mut(widget as *mut CGuiTextPane);
         CGuiTextSupport::set_text(text_support, &s);
     }
@@ -288,9 +288,16 @@ macro_rules! decl_instrs {
 
                 if let Ok(_) = input.parse::<Token![.]>() {
                     let e = if let Ok(_) = input.parse::<kw::float>() {
-                        let lit = input.parse::<LitFloat>()?;
-                        let f = lit.base10_parse::<f32>()?;
-                        parse_quote_spanned! {lit.span()=> #f.to_bits() }
+                        if input.peek(token::Brace) {
+                            let content;
+                            let _ = braced!(content in input);
+                            let expr: Expr = content.parse()?;
+                            parse_quote_spanned! {expr.span()=> (#expr as f32).to_bits() }
+                        } else {
+                            let lit = input.parse::<LitFloat>()?;
+                            let f = lit.base10_parse::<f32>()?;
+                            parse_quote_spanned! {lit.span()=> #f.to_bits() }
+                        }
 
                     } else if let Ok(_) = input.parse::<kw::long>() {
                         input.parse()?
@@ -67,6 +67,16 @@ fn test_ppcasm()
         0x3f, 0x00, 0x00, 0x00,
     ] as &[u8]);
 
+    let values = [0.1f32, 0.5];
+    let asm = ppcasm!(0x0, {
+        .float { values[0] };
+        .float { values[1] };
+    });
+    assert_eq!(&asm.encoded_bytes()[..], &[
+        0x3d, 0xcc, 0xcc, 0xcd,
+        0x3f, 0x00, 0x00, 0x00,
+    ]);
+
     let asm = ppcasm!(0x0, {
         .asciiz b"%03d/%03d";
     });
@@ -7,14 +7,15 @@ use clap::{
 };
 
 use randomprime::{
-    door_meta::Weights, extract_flaahgra_music_files, parse_layout, patches, reader_writer, structs
+    door_meta::{CustomDoorDef, DoorType, Weights}, extract_flaahgra_music_files, parse_layout, patches, reader_writer, structs
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{File, OpenOptions},
     fs,
     panic,
+    path::{Path, PathBuf},
     process::Command,
 };
 
@@ -87,6 +88,10 @@ fn default_as_false() -> bool {
     false
 }
 
+fn default_as_true() -> bool {
+    true
+}
+
 fn default_as_empty_str_vec() -> Vec<String> {
     Vec::new()
 }
@@ -109,27 +114,68 @@ fn default_as_empty_add_items_vec() -> Vec<patches::AdditionalItem> {
 }
 
 
-fn default_empty_string() -> String {
-    "".to_string()
+fn default_as_empty_door_opener_vec() -> Vec<patches::DoorOpener> {
+    Vec::new()
+}
+
+
+fn default_as_empty_custom_door_vec() -> Vec<CustomDoorConfig> {
+    Vec::new()
 }
 
-fn default_u64_123456789() -> u64 {
-    123456789
+fn default_as_empty_pickup_scan_overrides() -> HashMap<usize, String> {
+    HashMap::new()
+}
+
+fn default_as_zero_u8() -> u8 {
+    0
+}
+
+fn default_suit_damage_reduction() -> [f32; 4] {
+    [0.0, 0.1, 0.2, 0.5]
+}
+
+fn default_empty_string() -> String {
+    "".to_string()
 }
 
 #[derive(Deserialize)]
 struct PatchConfig {
+    #[serde(default = "default_as_false")]
     skip_frigate: bool,
+
+    #[serde(default = "default_empty_string")]
+    frigate_mode: String,
+
     skip_crater: bool,
     fix_flaaghra_music: bool,
     trilogy_iso: Option<String>,
     varia_heat_protection: bool,
+
+    // Overrides the damage per second of every heated room (added superheated rooms default to 10)
+    #[serde(default)]
+    heat_damage_per_sec: Option<f32>,
+
     stagger_suit_damage: bool,
+
+    // Damage reduction with 0 to 3 suits when stagger_suit_damage is on
+    #[serde(default = "default_suit_damage_reduction")]
+    suit_damage_reduction: [f32; 4],
+
     skip_hudmemos: bool,
+
+    // Don't play the item acquisition jingle
+    #[serde(default = "default_as_false")]
+    mute_item_fanfare: bool,
+
     powerbomb_lockpick: bool,
     enable_one_way_doors: bool,
     patch_map: bool,
     obfuscate_items:bool,
+
+    #[serde(default = "default_as_false")]
+    obfuscate_scans: bool,
+
     artifact_hints:String,
     auto_enabled_elevators:bool,
     
@@ -142,6 +188,64 @@ struct PatchConfig {
     #[serde(default = "default_as_false")]
     tiny_elvetator_samus: bool,
 
+    #[serde(default = "default_as_false")]
+    fast_elevators: bool,
+
+    #[serde(default = "default_as_false")]
+    randomize_elevators: bool,
+
+    #[serde(default = "default_as_false")]
+    boss_item_independence: bool,
+
+    #[serde(default = "default_as_true")]
+    fix_elite_research: bool,
+
+    #[serde(default = "default_as_true")]
+    fix_mines_security_station: bool,
+
+    #[serde(default = "default_as_false")]
+    disable_rel_patches: bool,
+
+    #[serde(default = "default_as_true")]
+    skip_ridley_shorelines_cinematic: bool,
+
+    // Reroute the in-room cinematics of the rooms known to be safe (see KNOWN_CUTSCENE_ROOMS)
+    #[serde(default = "default_as_false")]
+    skip_known_cutscenes: bool,
+
+    // Show every room on the map from the start, as if each map station had been used
+    #[serde(default = "default_as_false")]
+    reveal_full_map: bool,
+
+    #[serde(default = "default_as_false")]
+    phazon_suit_scan_follows_item: bool,
+
+    #[serde(default = "default_as_false")]
+    scan_known_destructibles: bool,
+
+    #[serde(default = "default_as_false")]
+    skip_artifact_temple_intro: bool,
+
+    #[serde(default = "default_as_false")]
+    print_disc_info: bool,
+
+    // Write the position of every pickup location in the input ISO as JSON, next to the output
+    #[serde(default = "default_as_false")]
+    pickup_positions: bool,
+
+    #[serde(default = "default_as_false")]
+    validate_only: bool,
+
+    #[serde(default = "default_as_empty_str_vec")]
+    target_languages: Vec<String>,
+
+    #[serde(default = "default_as_false")]
+    print_writer_regions: bool,
+
+    // Write a JSON spoiler log next to the output, e.g. "seed.spoiler.json" for "seed.iso"
+    #[serde(default = "default_as_false")]
+    spoiler_log: bool,
+
     #[serde(default = "default_as_false")]
     remove_missile_locks: bool,
 
@@ -162,11 +266,52 @@ struct PatchConfig {
 
     #[serde(default = "default_as_false")]
     quickplay: bool,
+
+    #[serde(default = "default_as_false")]
+    check_door_logic: bool,
+
+    #[serde(default = "default_as_false")]
+    remove_unopenable_doors: bool,
+
+    #[serde(default = "default_as_zero_u8")]
+    min_colored_doors_per_area: u8,
+
+    #[serde(default = "default_as_false")]
+    show_seed_ingame: bool,
+
+    // Name the game in the disc banner after the seed
+    #[serde(default = "default_as_false")]
+    show_seed_in_banner: bool,
+
+    #[serde(default = "default_as_false")]
+    show_starting_items: bool,
+
+    // Always start with the scan visor (implied when the frigate is skipped)
+    #[serde(default = "default_as_false")]
+    force_scan_visor: bool,
+
+    // Practice seeds: standing in a save station warps back to the starting room
+    #[serde(default = "default_as_false")]
+    warp_to_start: bool,
+
+    #[serde(default = "default_as_false")]
+    gravity_chamber_item_accessible: bool,
+}
+
+#[derive(Deserialize)]
+struct CustomDoorConfig {
+    name: String,
+    cmdl_path: String,
+    vertical_cmdl_path: Option<String>, // required to use the door on vertical docks
+    txtr_path: String,
+    vulnerability: String, // name of the built-in door type whose weaknesses are copied
 }
 
 #[derive(Deserialize)]
 struct Config {
     input_iso: String,
+    // "iso", "gcz" or "ciso", detected from the file when left out
+    input_iso_format: Option<patches::IsoFormat>,
     output_iso: String,
     layout_string: String,
 
@@ -196,6 +341,23 @@ struct Config {
 
     #[serde(default = "default_as_empty_add_items_vec")]
     additional_items: Vec<patches::AdditionalItem>,
+
+    // Objects that open a door when they fire, for "solid" doors
+    #[serde(default = "default_as_empty_door_opener_vec")]
+    door_openers: Vec<patches::DoorOpener>,
+
+    // Replaces the scan text of a pickup, keyed by its index in the layout
+    #[serde(default = "default_as_empty_pickup_scan_overrides")]
+    pickup_scan_overrides: HashMap<usize, String>,
+
+    // 1 in n missiles are shiny (1024 if unset, 0 for never, 1 for always)
+    #[serde(default)]
+    shiny_missile_odds: Option<u32>,
+
+    // Rooms ("World:Room Name") or single locations ("World:Room Name:index") whose pickups are
+    // always Nothing
+    #[serde(default)]
+    excluded_pickups: HashSet<String>,
     
     #[serde(default = "default_empty_string")]
     new_save_spawn_room: String,
@@ -205,18 +367,40 @@ struct Config {
 
     seed: u64,
     door_weights: Weights,
+    // Door weights must add up to exactly 100 for every world, rather than being scaled to their sum
+    #[serde(default = "default_as_false")]
+    strict_door_weights: bool,
     patch_settings: PatchConfig,
     
-    #[serde(default = "default_u64_123456789")]
-    starting_pickups: u64,
+    // Each of these is either the legacy bitfield or an object with named items
+    #[serde(default)]
+    starting_pickups: Option<patches::StartingItemsConfig>,
 
-    #[serde(default = "default_u64_123456789")]
-    new_save_starting_items: u64,
+    #[serde(default)]
+    new_save_starting_items: Option<patches::StartingItemsConfig>,
 
-    #[serde(default = "default_u64_123456789")]
-    frigate_done_starting_items: u64,
+    #[serde(default)]
+    frigate_done_starting_items: Option<patches::StartingItemsConfig>,
     
     excluded_doors: [HashMap<String,Vec<String>>;7],
+
+    #[serde(default = "default_as_empty_custom_door_vec")]
+    custom_door_types: Vec<CustomDoorConfig>,
+
+    #[serde(default = "default_as_empty_str_vec")]
+    protected_assets: Vec<String>, // e.g. "0x88ED4593.TXTR"
+
+    // Files to add as assets, keyed like protected_assets. They replace any asset with the same id.
+    #[serde(default)]
+    extra_assets: HashMap<String, String>,
+
+    // AABBs (min xyz, max xyz) of pickup models that aren't built in, keyed by CMDL id, e.g. "0xDEAF1234"
+    #[serde(default)]
+    custom_pickup_aabbs: HashMap<String, [f32; 6]>,
+
+    // DSP files to swap in for Audio/*.dsp files on the disc, keyed by file name
+    #[serde(default)]
+    replace_audio: HashMap<String, PathBuf>,
 }
 
 #[derive(Deserialize)]
@@ -230,6 +414,19 @@ struct ConfigBanner
     description: Option<String>,
 }
 
+// Parses an asset written as "<hex id>.<FOURCC>", e.g. "0x88ED4593.TXTR"
+fn parse_asset_name(asset: &str) -> Result<(u32, reader_writer::FourCC), String>
+{
+    let (id, fourcc) = asset.split_at(asset.find('.').unwrap_or(asset.len()));
+    let id = u32::from_str_radix(id.trim_start_matches("0x"), 16)
+        .map_err(|e| e.to_string())?;
+    let fourcc = fourcc.trim_start_matches('.').as_bytes();
+    if fourcc.len() != 4 {
+        return Err("expected <id>.<FOURCC>".to_string());
+    }
+    Ok((id, reader_writer::FourCC::from_bytes(&[fourcc[0], fourcc[1], fourcc[2], fourcc[3]])))
+}
+
 fn get_config() -> Result<patches::ParsedConfig, String>
 {
     /*let matches = App::new("randomprime ISO patcher")
@@ -342,20 +539,33 @@ fn get_config() -> Result<patches::ParsedConfig, String>
     let input_iso_mmap = unsafe { memmap::Mmap::map(&input_iso_file) }
                 .map_err(|e| format!("Failed to open input iso: {}", e))?;
 
-    let output_iso_path = config.output_iso;
-    let out_iso = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&output_iso_path)
-        .map_err(|e| format!("Failed to open output file: {}", e))?;
-
-    let iso_format = if output_iso_path.ends_with(".gcz") {
-        patches::IsoFormat::Gcz
-    } else if output_iso_path.ends_with(".ciso") {
-        patches::IsoFormat::Ciso
+    let (iso_format, output_iso_path) = patches::parse_output_spec(&config.output_iso)?;
+    // Nothing is written when only validating, so don't create or wipe the output
+    let out_iso = if config.patch_settings.validate_only {
+        None
     } else {
-        patches::IsoFormat::Iso
+        Some(OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&output_iso_path)
+            .map_err(|e| format!("Failed to open output file: {}", e))?)
+    };
+
+    let spoiler_log = if config.patch_settings.spoiler_log && !config.patch_settings.validate_only {
+        let spoiler_log_path = Path::new(&output_iso_path).with_extension("spoiler.json");
+        Some(File::create(&spoiler_log_path)
+            .map_err(|e| format!("Failed to open spoiler log file: {}", e))?)
+    } else {
+        None
+    };
+
+    let pickup_positions_file = if config.patch_settings.pickup_positions && !config.patch_settings.validate_only {
+        let pickup_positions_path = Path::new(&output_iso_path).with_extension("pickup_positions.json");
+        Some(File::create(&pickup_positions_path)
+            .map_err(|e| format!("Failed to open pickup positions file: {}", e))?)
+    } else {
+        None
     };
 
     let layout_string = String::from(&config.layout_string);
@@ -368,11 +578,78 @@ fn get_config() -> Result<patches::ParsedConfig, String>
         patches::ArtifactHintBehavior::Default
     } else if artifact_hints == "none" {
         patches::ArtifactHintBehavior::None
+    } else if artifact_hints == "no_hints" {
+        patches::ArtifactHintBehavior::NoHints
     } else { // e.g. "all"
         patches::ArtifactHintBehavior::All
         
     };
 
+    let frigate_mode = String::from(&config.patch_settings.frigate_mode).to_lowercase();
+    let frigate_mode = if frigate_mode == "full" {
+        patches::FrigateMode::Full
+    } else if frigate_mode == "skip" {
+        patches::FrigateMode::Skip
+    } else if frigate_mode == "short" {
+        patches::FrigateMode::Short
+    } else if !frigate_mode.is_empty() {
+        Err(format!("Unknown frigate mode '{}', expected 'full', 'skip' or 'short'", frigate_mode))?
+    } else if config.patch_settings.skip_frigate { // not set, fall back to the old flag
+        patches::FrigateMode::Skip
+    } else {
+        patches::FrigateMode::Full
+    };
+
+    let mut target_languages = Vec::new();
+    for language in config.patch_settings.target_languages.iter() {
+        target_languages.push(patches::Language::from_string(language.to_string())
+            .ok_or_else(|| format!("Unknown language '{}'", language))?);
+    }
+    if target_languages.is_empty() {
+        target_languages.push(patches::Language::English);
+    }
+
+    let mut custom_door_types = Vec::new();
+    for door in config.custom_door_types.iter() {
+        let vulnerability = DoorType::from_string(door.vulnerability.to_string())
+            .ok_or_else(|| format!("Unknown door type '{}' for custom door '{}'", door.vulnerability, door.name))?
+            .vulnerability();
+        custom_door_types.push(CustomDoorDef {
+            name: door.name.clone(),
+            cmdl_bytes: fs::read(&door.cmdl_path)
+                .map_err(|e| format!("Failed to read CMDL for custom door '{}': {}", door.name, e))?,
+            vertical_cmdl_bytes: door.vertical_cmdl_path.as_ref()
+                .map(|path| fs::read(path)
+                    .map_err(|e| format!("Failed to read vertical CMDL for custom door '{}': {}", door.name, e)))
+                .transpose()?,
+            txtr_bytes: fs::read(&door.txtr_path)
+                .map_err(|e| format!("Failed to read TXTR for custom door '{}': {}", door.name, e))?,
+            vulnerability,
+        });
+    }
+
+    let mut protected_assets = HashSet::new();
+    for asset in config.protected_assets.iter() {
+        protected_assets.insert(parse_asset_name(asset)
+            .map_err(|e| format!("Invalid protected asset '{}': {}", asset, e))?);
+    }
+
+    let mut extra_assets = Vec::new();
+    for (asset, path) in config.extra_assets.iter() {
+        let (id, fourcc) = parse_asset_name(asset)
+            .map_err(|e| format!("Invalid extra asset '{}': {}", asset, e))?;
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read extra asset '{}': {}", asset, e))?;
+        extra_assets.push((id, fourcc, bytes));
+    }
+
+    let mut custom_pickup_aabbs = HashMap::new();
+    for (cmdl, aabb) in config.custom_pickup_aabbs.iter() {
+        let id = u32::from_str_radix(cmdl.trim_start_matches("0x"), 16)
+            .map_err(|e| format!("Invalid custom pickup AABB CMDL '{}': {}", cmdl, e))?;
+        custom_pickup_aabbs.insert(id, *aabb);
+    }
+
     let flaahgra_music_files = if config.patch_settings.fix_flaaghra_music {
         if let Some(path) = config.patch_settings.trilogy_iso {
             Some(extract_flaahgra_music_files(&path)?)
@@ -391,44 +668,39 @@ fn get_config() -> Result<patches::ParsedConfig, String>
         game_name: Some(String::from("Metroid Prime")),
         developer: Some(String::from("^_^")),
 
-        game_name_full: Some(String::from("Metroid Prime Plandomizer")),
+        game_name_full: if config.patch_settings.show_seed_in_banner {
+            None // Filled in from the seed while patching
+        } else {
+            Some(String::from("Metroid Prime Plandomizer"))
+        },
         developer_full: Some(String::from("^_^")),
         description: Some(String::from("Metroid Prime, but probably a cursed seed")),
     });
 
-    let new_save_starting_items = {
-        if config.new_save_starting_items != 123456789 {
-            config.new_save_starting_items
-        }
-        else if config.starting_pickups != 123456789 {
-            config.starting_pickups
-        }
-        else {
-            assert!(false);
-            0
-        }
-    };
-    
-    let frigate_done_starting_items = {
-        if config.frigate_done_starting_items != 123456789 {
-            config.frigate_done_starting_items
-        }
-        else if config.starting_pickups != 123456789 {
-            config.starting_pickups
-        }
-        else {
-            assert!(false);
-            0
-        }
-    };
+    let new_save_starting_items: patches::StartingItems = config.new_save_starting_items
+        .or(config.starting_pickups)
+        .ok_or("Either starting_pickups or new_save_starting_items must be specified")?
+        .into();
+
+    let frigate_done_starting_items: patches::StartingItems = config.frigate_done_starting_items
+        .or(config.starting_pickups)
+        .ok_or("Either starting_pickups or frigate_done_starting_items must be specified")?
+        .into();
 
     Ok(patches::ParsedConfig {
-        input_iso:input_iso_mmap,
-        output_iso:out_iso,
+        input_iso:Some(input_iso_mmap),
+        input_iso_format: config.input_iso_format,
+        output_iso: out_iso,
         is_item_randomized: None,
         pickup_layout, elevator_layout, seed,
+        pickup_layout_typed: None,
         item_seed,door_weights:config.door_weights,
+        strict_door_weights: config.strict_door_weights,
         excluded_doors:config.excluded_doors,
+        custom_door_types,
+        extra_assets,
+        protected_assets,
+        custom_pickup_aabbs,
         patch_map:config.patch_settings.patch_map,
         patch_power_conduits: config.patch_settings.patch_power_conduits,
         remove_missile_locks: config.patch_settings.remove_missile_locks,
@@ -444,21 +716,30 @@ fn get_config() -> Result<patches::ParsedConfig, String>
         liquid_volumes: config.liquid_volumes,
         aether_transforms: config.aether_transforms,
         additional_items: config.additional_items,
+        door_openers: config.door_openers,
+        pickup_scan_overrides: config.pickup_scan_overrides,
+        shiny_missile_odds: config.shiny_missile_odds,
+        excluded_pickups: config.excluded_pickups,
         
         layout_string,
         elevator_layout_override: config.elevator_layout_override,
+        randomize_elevators: config.patch_settings.randomize_elevators,
         missile_lock_override: config.missile_lock_override,
         new_save_spawn_room: config.new_save_spawn_room,
         frigate_done_spawn_room: config.frigate_done_spawn_room,
 
         iso_format,
-        skip_frigate: config.patch_settings.skip_frigate,
+        frigate_mode,
         skip_hudmenus: config.patch_settings.skip_hudmemos,
+        mute_item_fanfare: config.patch_settings.mute_item_fanfare,
         nonvaria_heat_damage: config.patch_settings.varia_heat_protection,
+        heat_damage_per_sec: config.patch_settings.heat_damage_per_sec,
         staggered_suit_damage: config.patch_settings.stagger_suit_damage,
+        suit_damage_reduction: config.patch_settings.suit_damage_reduction,
         powerbomb_lockpick: config.patch_settings.powerbomb_lockpick,
         keep_fmvs: false,
         obfuscate_items: config.patch_settings.obfuscate_items,
+        obfuscate_scans: config.patch_settings.obfuscate_scans,
         auto_enabled_elevators: config.patch_settings.auto_enabled_elevators,
         quiet: false,
 
@@ -466,9 +747,32 @@ fn get_config() -> Result<patches::ParsedConfig, String>
         enable_vault_ledge_door: config.patch_settings.enable_one_way_doors,
         artifact_hint_behavior,
         patch_vertical_to_blue: config.patch_settings.patch_vertical_to_blue,
+        check_door_logic: config.patch_settings.check_door_logic,
+        remove_unopenable_doors: config.patch_settings.remove_unopenable_doors,
+        min_colored_doors_per_area: config.patch_settings.min_colored_doors_per_area,
+        show_seed_ingame: config.patch_settings.show_seed_ingame,
+        show_starting_items: config.patch_settings.show_starting_items,
+        force_scan_visor: config.patch_settings.force_scan_visor,
+        warp_to_start: config.patch_settings.warp_to_start,
+        gravity_chamber_item_accessible: config.patch_settings.gravity_chamber_item_accessible,
         tiny_elvetator_samus: config.patch_settings.tiny_elvetator_samus,
+        fast_elevators: config.patch_settings.fast_elevators,
+        boss_item_independence: config.patch_settings.boss_item_independence,
+        fix_elite_research: config.patch_settings.fix_elite_research,
+        fix_mines_security_station: config.patch_settings.fix_mines_security_station,
+        disable_rel_patches: config.patch_settings.disable_rel_patches,
+        skip_ridley_shorelines_cinematic: config.patch_settings.skip_ridley_shorelines_cinematic,
+        skip_known_cutscenes: config.patch_settings.skip_known_cutscenes,
+        reveal_full_map: config.patch_settings.reveal_full_map,
+        phazon_suit_scan_follows_item: config.patch_settings.phazon_suit_scan_follows_item,
+        scan_known_destructibles: config.patch_settings.scan_known_destructibles,
+        skip_artifact_temple_intro: config.patch_settings.skip_artifact_temple_intro,
+        print_disc_info: config.patch_settings.print_disc_info,
+        validate_only: config.patch_settings.validate_only,
+        target_languages,
 
         flaahgra_music_files,
+        replace_audio: config.replace_audio,
 
         new_save_starting_items,
         frigate_done_starting_items,
@@ -484,8 +788,12 @@ fn get_config() -> Result<patches::ParsedConfig, String>
         bnr_game_name_full: banner.as_mut().and_then(|b| b.game_name_full.take()),
         bnr_developer_full: banner.as_mut().and_then(|b| b.developer_full.take()),
         bnr_description: banner.as_mut().and_then(|b| b.description.take()),
+        bnr_seed_name: config.patch_settings.show_seed_in_banner,
 
         pal_override: false,
+        print_writer_regions: config.patch_settings.print_writer_regions,
+        spoiler_log,
+        pickup_positions_file,
     })
 
 }
@@ -529,7 +837,11 @@ fn main_inner() -> Result<(), String>
 {
     let config = get_config()?;
     let pn = ProgressNotifier::new(config.quiet);
+    let validate_only = config.validate_only;
     patches::patch_iso(config, pn)?;
+    if validate_only {
+        println!("Validation succeeded, no output was written");
+    }
     println!("Done");
     Ok(())
 }
@@ -116,7 +116,7 @@ impl<'r> ResourceDb<'r>
                     extend_deps(effect.effect_file_id, effect.effect_type.as_bytes());
                 }
             } else if key.fourcc == b"PART".into() {
-                let buf = data.decompress();
+                let buf = data.decompress().unwrap();
                 let buf: &[u8] = &buf;
                 // We're cheating here. We're going to find the sub-string ICTSCNST
                 // and then using the next word as the id of a PART.
@@ -145,7 +145,7 @@ impl<'r> ResourceDb<'r>
                     }
                 }
             } else if key.fourcc == b"CMDL".into() {
-                let buf = data.decompress();
+                let buf = data.decompress().unwrap();
                 let cmdl: Cmdl = Reader::new(&buf).read(());
                 for material in cmdl.material_sets.iter() {
                     for id in material.texture_ids.iter() {
@@ -153,7 +153,7 @@ impl<'r> ResourceDb<'r>
                     }
                 }
             } else if key.fourcc == b"ANCS".into() {
-                let buf = data.decompress();
+                let buf = data.decompress().unwrap();
                 let ancs: Ancs = Reader::new(&buf).read(());
                 if let Some(ancs_node) = ancs_node {
                     let char_info = ancs.char_set.char_info.iter().nth(ancs_node as usize).unwrap();
@@ -911,6 +911,19 @@ fn create_shiny_missile(pickup_table: &mut HashMap<PickupType, PickupData>)
     }).is_none());
 }
 
+fn create_missile_launcher(pickup_table: &mut HashMap<PickupType, PickupData>)
+{
+    // The launcher is the same pickup as an expansion, it only differs in name
+    let missile = &pickup_table[&PickupType::Missile];
+    let missile_launcher = PickupData {
+        bytes: missile.bytes.clone(),
+        deps: missile.deps.clone(),
+        hudmemo_strg: missile.hudmemo_strg,
+        attainment_audio_file_name: missile.attainment_audio_file_name.clone(),
+    };
+    assert!(pickup_table.insert(PickupType::MissileLauncher, missile_launcher).is_none());
+}
+
 fn main()
 {
     let file = File::open(args().nth(1).unwrap()).unwrap();
@@ -1075,7 +1088,7 @@ fn main()
                         cmdl_aabbs.entry(pickup.cmdl).or_insert_with(|| {
                             let cmdl_key = ResourceKey::new(pickup.cmdl, b"CMDL".into());
                             // Cmdls are compressed
-                            let res_data = res_db.map[&cmdl_key].data.decompress();
+                            let res_data = res_db.map[&cmdl_key].data.decompress().unwrap();
                             let cmdl: Cmdl = Reader::new(&res_data).read(());
                             let aabb = cmdl.maab;
                             // Convert from GenericArray to [f32; 6]
@@ -1120,6 +1133,7 @@ fn main()
     create_nothing(&mut pickup_table);
     create_scan_visor(&mut pickup_table);
     create_shiny_missile(&mut pickup_table);
+    create_missile_launcher(&mut pickup_table);
 
     println!("// This file is generated by bin/resource_tracing.rs");
     println!("");
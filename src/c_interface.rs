@@ -279,7 +279,8 @@ fn inner(config_json: *const c_char, cb_data: *const (), cb: extern fn(*const ()
     comment_message.push_str(mpdr_version);
 
     let parsed_config = patches::ParsedConfig {
-        input_iso, output_iso,
+        input_iso: Some(input_iso),
+        output_iso: Some(output_iso),
         is_item_randomized: None,
         pickup_layout, elevator_layout, seed,
         item_seed,door_weights:config.door_weights,
@@ -6,7 +6,7 @@ use std::{
     iter,
 };
 
-use crate::gcz_writer::ZEROES;
+use crate::gcz_writer::{WrittenRegion, ZEROES};
 
 // Implementation examples
 // https://github.com/FIX94/Nintendont/blob/3e81dadcfc4b19129f08a947905331f1d45a1b0b/kernel/ISO.c
@@ -26,6 +26,9 @@ pub struct CisoWriter<W: Write + Seek>
     file: W,
     blocks_map: Vec<u8>,
     skipped_blocks: u32,
+
+    regions: Option<Vec<WrittenRegion>>,
+    finished: bool,
 }
 
 impl<W: Write + Seek> CisoWriter<W>
@@ -38,9 +41,58 @@ impl<W: Write + Seek> CisoWriter<W>
             file,
             blocks_map: Vec::with_capacity(HEADER_SIZE - 8),
             skipped_blocks: 0,
+
+            regions: None,
+            finished: false,
         })
     }
 
+    // Keep track of the block layout so it can be inspected with regions() after finish()
+    pub fn record_regions(&mut self)
+    {
+        self.regions = Some(Vec::new());
+    }
+
+    // Every block of the disc, with skipped blocks having a compressed length of 0
+    pub fn regions(&self) -> Option<&[WrittenRegion]>
+    {
+        self.regions.as_ref().map(|regions| &regions[..])
+    }
+
+    // Write out the header. This happens automatically on drop, but calling it directly allows
+    // errors to be handled and regions() to be inspected afterwards.
+    pub fn finish(&mut self) -> io::Result<()>
+    {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        let pos = self.file.seek(io::SeekFrom::Current(0))?;
+        let pos_rounded_up = (pos + block_size!() - 1) & !(block_size!() - 1);
+        let current_block = pos_rounded_up / block_size!() + self.skipped_blocks as u64;
+        let l = current_block as usize - self.blocks_map.len();
+        self.blocks_map.extend(iter::repeat(1).take(l));
+
+        if let Some(regions) = self.regions.as_mut() {
+            *regions = self.blocks_map.iter()
+                .enumerate()
+                .map(|(i, &used)| WrittenRegion {
+                    offset: i as u64 * block_size!(),
+                    length: block_size!(),
+                    compressed_length: if used != 0 { block_size!() } else { 0 },
+                })
+                .collect();
+        }
+
+        // Write header (We can use Writable because of big-endianness)
+        self.file.seek(io::SeekFrom::Start(0))?;
+        self.file.write_all(b"CISO")?;
+        self.file.write_u32::<LittleEndian>(BLOCK_SIZE)?;
+        self.file.write_all(&self.blocks_map[..])?;
+        Ok(())
+    }
+
     // pub fn new(mut file: W) -> io::Result<CisoWriter<W>>
     fn write_zeroes(&mut self, mut bytes: u64) -> io::Result<()>
     {
@@ -71,7 +123,7 @@ impl<W: Write + Seek> Write for CisoWriter<W>
     }
 }
 
-impl<W: Write + Seek + 'static> structs::WriteExt for CisoWriter<W>
+impl<W: Write + Seek> structs::WriteExt for CisoWriter<W>
 {
     fn skip_bytes(&mut self, bytes: u64) -> io::Result<()>
     {
@@ -105,23 +157,74 @@ impl<W: Write + Seek> Drop for CisoWriter<W>
 {
     fn drop(&mut self)
     {
-        let res = || -> io::Result<()> {
-            let pos = self.file.seek(io::SeekFrom::Current(0))?;
-            let pos_rounded_up = (pos + block_size!() - 1) & !(block_size!() - 1);
-            let current_block = pos_rounded_up / block_size!() + self.skipped_blocks as u64;
-            let l = current_block as usize - self.blocks_map.len();
-            self.blocks_map.extend(iter::repeat(1).take(l));
-
-            // Write header (We can use Writable because of big-endianness)
-            self.file.seek(io::SeekFrom::Start(0))?;
-            self.file.write_all(b"CISO")?;
-            self.file.write_u32::<LittleEndian>(BLOCK_SIZE)?;
-            self.file.write_all(&self.blocks_map[..])?;
-            Ok(())
-        }();
+        let res = self.finish();
         // We really don't want to panic from a destructor, so just write a warning instead
         if let Err(e) = res {
             eprintln!("Error closing GczWriter: {}", e);
         };
     }
 }
+
+pub fn is_ciso(data: &[u8]) -> bool
+{
+    data.len() >= 4 && &data[..4] == b"CISO"
+}
+
+// The read side of CisoWriter, for using a CISO directly as the input disc. Blocks missing from
+// the map are zeroes and the result is padded out to disc_length.
+pub fn decompress_ciso(data: &[u8], disc_length: usize) -> Result<Vec<u8>, String>
+{
+    if !is_ciso(data) || data.len() < HEADER_SIZE {
+        Err("The input file isn't a CISO".to_string())?
+    }
+    let block_size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    if block_size == 0 {
+        Err("The CISO has a block size of 0".to_string())?
+    }
+
+    let mut out = Vec::with_capacity(disc_length);
+    let mut next_block = HEADER_SIZE;
+    for &used in &data[8..HEADER_SIZE] {
+        if out.len() >= disc_length {
+            break;
+        }
+        if used != 0 {
+            // The last block might not have been padded out
+            let end = min(next_block + block_size, data.len());
+            let block = data.get(next_block..end)
+                .ok_or_else(|| "The CISO file is truncated".to_string())?;
+            out.extend_from_slice(block);
+            out.resize(out.len() + block_size - block.len(), 0);
+            next_block = end;
+        } else {
+            out.resize(out.len() + block_size, 0);
+        }
+    }
+    out.resize(disc_length, 0);
+    Ok(out)
+}
+
+#[test]
+fn test_ciso_round_trip()
+{
+    use structs::WriteExt;
+
+    let disc_length = 5 * block_size!() + 0x100;
+    // skip_bytes needs a writer that owns its file
+    let path = std::env::temp_dir().join("randomprime_test_ciso_round_trip.ciso");
+    {
+        let mut writer = CisoWriter::new(std::fs::File::create(&path).unwrap()).unwrap();
+        writer.write_all(&[0xAB; 0x300]).unwrap();
+        writer.skip_bytes(3 * block_size!()).unwrap();
+        writer.write_all(&[0xCD; 0x20]).unwrap();
+        writer.finish().unwrap();
+    }
+    let file = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut expected = vec![0xAB; 0x300];
+    expected.resize(0x300 + 3 * block_size!(), 0);
+    expected.extend_from_slice(&[0xCD; 0x20]);
+    expected.resize(disc_length, 0);
+    assert!(decompress_ciso(&file, disc_length).unwrap() == expected);
+}
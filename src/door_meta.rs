@@ -1,4 +1,4 @@
-use crate::{pickup_meta::ScriptObjectLocation, custom_asset_ids};
+use crate::{pickup_meta::{PickupType, ScriptObjectLocation}, custom_asset_ids};
 use structs::structs::{
     DamageVulnerability,
     ChargedBeams,
@@ -15,6 +15,17 @@ pub struct DoorLocation {
     pub dock_number: Option<u32>,
 }
 
+// A door color supplied by the user at patch time instead of being built into the patcher
+#[derive(Clone, Debug)]
+pub struct CustomDoorDef {
+    pub name: String,
+    pub cmdl_bytes: Vec<u8>,
+    // Vertical docks use a different shield model, so the door can only go on them if one is given
+    pub vertical_cmdl_bytes: Option<Vec<u8>>,
+    pub txtr_bytes: Vec<u8>,
+    pub vulnerability: DamageVulnerability,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum TypeVulnerability {
     Normal = 0x1,
@@ -40,6 +51,14 @@ pub enum DoorType {
     Flamethrower,
     Ai,
     Disabled,
+    Xray,
+    Thermal,
+    Solid,
+    OneWay,
+    OneWayNorth,
+    OneWaySouth,
+    OneWayEast,
+    OneWayWest,
     VerticalBlue,
     VerticalPowerOnly,
     VerticalPurple,
@@ -55,6 +74,9 @@ pub enum DoorType {
     VerticalIcespreader,
     VerticalFlamethrower,
     VerticalAi,
+    VerticalBoost,
+    VerticalSolid,
+    VerticalOneWay,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -77,6 +99,31 @@ pub struct Weights {
     pub phazon_mines: [u8;4]
 }
 
+impl Weights {
+    // The blue, purple, white and red weights of each world are relative to each other, so they
+    // only need to add up to something. When strict, they are percentages and must add up to 100.
+    pub fn validate(&self, strict: bool) -> Result<(), String> {
+        let worlds = [
+            ("Tallon Overworld", &self.tallon_overworld),
+            ("Chozo Ruins", &self.chozo_ruins),
+            ("Magmoor Caverns", &self.magmoor_caverns),
+            ("Phendrana Drifts", &self.phendrana_drifts),
+            ("Phazon Mines", &self.phazon_mines),
+        ];
+        for (world, weights) in worlds.iter() {
+            let sum: u32 = weights.iter().map(|&w| w as u32).sum();
+            if strict && sum != 100 {
+                return Err(format!("The door weights for {} add up to {}, but must add up to exactly 100", world, sum));
+            }
+            if sum == 0 {
+                return Err(format!("The door weights for {} are all 0", world));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum World {
     FrigateOrpheon,
     TallonOverworld,
@@ -126,26 +173,104 @@ impl World {
     }
 }
 
+// Every string accepted by DoorType::from_string, including the beam aliases
+const DOOR_TYPE_NAMES: &[(&str, DoorType)] = &[
+    ("blue",            DoorType::Blue),
+    ("power_only",      DoorType::PowerOnly),
+    ("purple",          DoorType::Purple),
+    ("wave",            DoorType::Purple),
+    ("wave_beam",       DoorType::Purple),
+    ("white",           DoorType::White),
+    ("ice",             DoorType::White),
+    ("ice_beam",        DoorType::White),
+    ("red",             DoorType::Red),
+    ("plasma",          DoorType::Red),
+    ("plasma_beam",     DoorType::Red),
+    ("power_bomb",      DoorType::PowerBomb),
+    ("bomb",            DoorType::Bomb),
+    ("bombs",           DoorType::Bomb),
+    ("morph_ball_bomb", DoorType::Bomb),
+    ("missile",         DoorType::Missile),
+    ("missiles",        DoorType::Missile),
+    ("charge",          DoorType::Charge),
+    ("charge_beam",     DoorType::Charge),
+    ("super",           DoorType::Super),
+    ("super_missile",   DoorType::Super),
+    ("super_missiles",  DoorType::Super),
+    ("disabled",        DoorType::Disabled),
+    ("disable",         DoorType::Disabled),
+    ("wavebuster",      DoorType::Wavebuster),
+    ("icespreader",     DoorType::Icespreader),
+    ("ice_spreader",    DoorType::Icespreader),
+    ("flamethrower",    DoorType::Flamethrower),
+    ("ai",              DoorType::Ai),
+    ("ememy",           DoorType::Ai),
+    ("ememy_weapon",    DoorType::Ai),
+    ("xray",            DoorType::Xray),
+    ("x_ray",           DoorType::Xray),
+    ("xray_visor",      DoorType::Xray),
+    ("thermal",         DoorType::Thermal),
+    ("thermal_visor",   DoorType::Thermal),
+    ("boost",           DoorType::Boost),
+    ("boostball",       DoorType::Boost),
+    ("boost_ball",      DoorType::Boost),
+    ("solid",           DoorType::Solid),
+    ("one_way",         DoorType::OneWay),
+    ("oneway",          DoorType::OneWay),
+    ("oneway_north",    DoorType::OneWayNorth),
+    ("oneway_south",    DoorType::OneWaySouth),
+    ("oneway_east",     DoorType::OneWayEast),
+    ("oneway_west",     DoorType::OneWayWest),
+];
+
 impl DoorType {
 
     pub const fn is_vertical(&self) -> bool {
+        matches!(
+            self,
+            DoorType::VerticalBlue |
+            DoorType::VerticalPowerOnly |
+            DoorType::VerticalPurple |
+            DoorType::VerticalWhite |
+            DoorType::VerticalRed |
+            DoorType::VerticalPowerBomb |
+            DoorType::VerticalBomb |
+            DoorType::VerticalMissile |
+            DoorType::VerticalCharge |
+            DoorType::VerticalSuper |
+            DoorType::VerticalDisabled |
+            DoorType::VerticalWavebuster |
+            DoorType::VerticalIcespreader |
+            DoorType::VerticalFlamethrower |
+            DoorType::VerticalAi |
+            DoorType::VerticalBoost |
+            DoorType::VerticalSolid |
+            DoorType::VerticalOneWay
+        )
+    }
+
+    // One way doors can't be opened from the room they are in, only from the room on the other side
+    pub const fn is_one_way(&self) -> bool {
+        matches!(
+            self,
+            DoorType::OneWay |
+            DoorType::OneWayNorth |
+            DoorType::OneWaySouth |
+            DoorType::OneWayEast |
+            DoorType::OneWayWest |
+            DoorType::VerticalOneWay
+        )
+    }
+
+    // The side of the forcefield that is drawn and can be shot, for the one way doors that keep
+    // their forcefield. Values as in make_main_plaza_locked_door_two_ways.
+    pub const fn render_side(&self) -> Option<u32> {
         match self {
-            DoorType::VerticalBlue         =>   true,
-            DoorType::VerticalPowerOnly    =>   true,
-            DoorType::VerticalPurple       =>   true,
-            DoorType::VerticalWhite        =>   true,
-            DoorType::VerticalRed          =>   true,
-            DoorType::VerticalPowerBomb    =>   true,
-            DoorType::VerticalBomb         =>   true,
-            DoorType::VerticalMissile      =>   true,
-            DoorType::VerticalCharge       =>   true,
-            DoorType::VerticalSuper        =>   true,
-            DoorType::VerticalDisabled     =>   true,
-            DoorType::VerticalWavebuster   =>   true,
-            DoorType::VerticalIcespreader  =>   true,
-            DoorType::VerticalFlamethrower =>   true,
-            DoorType::VerticalAi           =>   true,
-            _ => false,
+            DoorType::OneWayNorth => Some(1),
+            DoorType::OneWaySouth => Some(2),
+            DoorType::OneWayEast  => Some(3),
+            DoorType::OneWayWest  => Some(4),
+            _ => None,
         }
     }
 
@@ -166,45 +291,27 @@ impl DoorType {
             DoorType::Icespreader  =>   DoorType::VerticalIcespreader  ,
             DoorType::Flamethrower =>   DoorType::VerticalFlamethrower ,
             DoorType::Ai           =>   DoorType::VerticalAi           ,
+            DoorType::Boost        =>   DoorType::VerticalBoost        ,
+            DoorType::Solid        =>   DoorType::VerticalSolid        ,
+            DoorType::OneWay       =>   DoorType::VerticalOneWay       ,
+            // A vertical forcefield has no north, south, east or west side to render
+            DoorType::OneWayNorth  =>   DoorType::VerticalOneWay       ,
+            DoorType::OneWaySouth  =>   DoorType::VerticalOneWay       ,
+            DoorType::OneWayEast   =>   DoorType::VerticalOneWay       ,
+            DoorType::OneWayWest   =>   DoorType::VerticalOneWay       ,
             _ => self.clone().to_owned(),
         }
     }
 
     pub fn from_string(string: String) -> Option<Self> {
-        match string.to_lowercase().as_str() {
-            "blue"           => Some(DoorType::Blue         ),
-            "power_only"     => Some(DoorType::PowerOnly    ),
-            "purple"         => Some(DoorType::Purple       ),
-            "wave"           => Some(DoorType::Purple       ),
-            "wave_beam"      => Some(DoorType::Purple       ),
-            "white"          => Some(DoorType::White        ),
-            "ice"            => Some(DoorType::White        ),
-            "ice_beam"       => Some(DoorType::White        ),
-            "red"            => Some(DoorType::Red          ),
-            "plasma"         => Some(DoorType::Red          ),
-            "plasma_beam"    => Some(DoorType::Red          ),
-            "power_bomb"     => Some(DoorType::PowerBomb    ),
-            "bomb"           => Some(DoorType::Bomb         ),
-            "bombs"          => Some(DoorType::Bomb         ),
-            "morph_ball_bomb"=> Some(DoorType::Bomb         ),
-            "missile"        => Some(DoorType::Missile      ),
-            "missiles"       => Some(DoorType::Missile      ),
-            "charge"         => Some(DoorType::Charge       ),
-            "charge_beam"    => Some(DoorType::Charge       ),
-            "super"          => Some(DoorType::Super        ),
-            "super_missile"  => Some(DoorType::Super        ),
-            "super_missiles" => Some(DoorType::Super        ),
-            "disabled"       => Some(DoorType::Disabled     ),
-            "disable"        => Some(DoorType::Disabled     ),
-            "wavebuster"     => Some(DoorType::Wavebuster   ),
-            "icespreader"    => Some(DoorType::Icespreader  ),
-            "ice_spreader"   => Some(DoorType::Icespreader  ),
-            "flamethrower"   => Some(DoorType::Flamethrower ),
-            "ai"             => Some(DoorType::Ai           ),
-            "ememy"          => Some(DoorType::Ai           ),
-            "ememy_weapon"   => Some(DoorType::Ai           ),
-            _                => None                         ,
-        }
+        let string = string.to_lowercase();
+        DOOR_TYPE_NAMES.iter()
+            .find(|(name, _)| *name == string)
+            .map(|&(_, door_type)| door_type)
+    }
+
+    pub fn all_names() -> Vec<&'static str> {
+        DOOR_TYPE_NAMES.iter().map(|&(name, _)| name).collect()
     }
 
     pub const fn shield_cmdl(&self) -> u32 { // model of door, includes specification for which 128x128 texture to line door frame with
@@ -214,7 +321,7 @@ impl DoorType {
             DoorType::Purple       =>   0x33188D1B, // vanilla CMDL
             DoorType::White        =>   0x59649E9D, // vanilla CMDL
             DoorType::Red          =>   0xBBBA1EC7, // vanilla CMDL
-            DoorType::Boost        =>   0x0734977A, // unused
+            DoorType::Boost        =>   custom_asset_ids::BOOST_DOOR_CMDL,
             DoorType::PowerBomb    =>   custom_asset_ids::POWER_BOMB_DOOR_CMDL,
             DoorType::Bomb         =>   custom_asset_ids::MORPH_BALL_BOMB_DOOR_CMDL,
             DoorType::Missile      =>   custom_asset_ids::MISSILE_DOOR_CMDL,
@@ -225,6 +332,14 @@ impl DoorType {
             DoorType::Icespreader  =>   custom_asset_ids::ICESPREADER_DOOR_CMDL,
             DoorType::Flamethrower =>   custom_asset_ids::FLAMETHROWER_DOOR_CMDL,
             DoorType::Ai           =>   custom_asset_ids::AI_DOOR_CMDL,
+            DoorType::Xray         =>   custom_asset_ids::XRAY_DOOR_CMDL,
+            DoorType::Thermal      =>   custom_asset_ids::THERMAL_DOOR_CMDL,
+            DoorType::Solid        =>   0x0734977A, // vanilla CMDL - looks like a blue door
+            DoorType::OneWay       =>   0x0734977A, // vanilla CMDL
+            DoorType::OneWayNorth  =>   0x0734977A, // vanilla CMDL
+            DoorType::OneWaySouth  =>   0x0734977A, // vanilla CMDL
+            DoorType::OneWayEast   =>   0x0734977A, // vanilla CMDL
+            DoorType::OneWayWest   =>   0x0734977A, // vanilla CMDL
 
             // vertical doors need a different CMDL, otherwise it will look like this: https://i.imgur.com/jGjWnmg.png //
             DoorType::VerticalBlue         =>   0x18D0AEE6, // vanilla horizontal CMDL (blue)
@@ -242,6 +357,9 @@ impl DoorType {
             DoorType::VerticalIcespreader  =>   custom_asset_ids::VERTICAL_ICESPREADER_DOOR_CMDL,
             DoorType::VerticalFlamethrower =>   custom_asset_ids::VERTICAL_FLAMETHROWER_DOOR_CMDL,
             DoorType::VerticalAi           =>   custom_asset_ids::VERTICAL_AI_DOOR_CMDL,
+            DoorType::VerticalBoost        =>   custom_asset_ids::VERTICAL_BOOST_DOOR_CMDL,
+            DoorType::VerticalSolid        =>   0x18D0AEE6, // vanilla CMDL
+            DoorType::VerticalOneWay       =>   0x18D0AEE6, // vanilla CMDL
         }
     }
 
@@ -252,12 +370,9 @@ impl DoorType {
             DoorType::Charge        => structs::MapaObjectType::DoorNormal as u32,
             DoorType::Bomb          => structs::MapaObjectType::DoorNormal as u32,
             DoorType::Purple        => structs::MapaObjectType::DoorWave as u32,
-            DoorType::Wavebuster    => structs::MapaObjectType::DoorWave as u32,
             DoorType::White         => structs::MapaObjectType::DoorIce as u32,
-            DoorType::Icespreader   => structs::MapaObjectType::DoorIce as u32,
             DoorType::Red           => structs::MapaObjectType::DoorPlasma as u32,
-            DoorType::Flamethrower  => structs::MapaObjectType::DoorPlasma as u32,
-            _ => structs::MapaObjectType::DoorShield as u32, // everything else is non-vanilla and thus shield
+            _ => structs::MapaObjectType::DoorShield as u32, // everything else is non-vanilla and thus shield, including the combo beam doors so they aren't mistaken for plain beam doors
         }
     }
 
@@ -268,17 +383,25 @@ impl DoorType {
             DoorType::Purple       =>   0xF68DF7F1, // vanilla TXTR
             DoorType::White        =>   0xBE4CD99D, // vanilla TXTR
             DoorType::Red          =>   0xFC095F6C, // vanilla TXTR
-            DoorType::Boost        =>   0x8A7F3683, // unused
+            DoorType::Boost        =>   custom_asset_ids::BOOST_DOOR_FORCEFIELD_TXTR, // teal bands
             DoorType::PowerBomb    =>   0x1D588B22, // solid yellow
             DoorType::Bomb         =>   0xFC095F6C, // solid orange
             DoorType::Missile      =>   0x8344BEC8, // solid grey
             DoorType::Charge       =>   0x8A7F3683, // vanilla blue
             DoorType::Super        =>   0xD5C17775, // solid green
             DoorType::Disabled     =>   0x717AABCE, // void with specks
-            DoorType::Wavebuster   =>   0xF68DF7F1, // vanilla TXTR
-            DoorType::Icespreader  =>   0xBE4CD99D, // vanilla TXTR
-            DoorType::Flamethrower =>   0xFC095F6C, // vanilla TXTR
+            DoorType::Wavebuster   =>   custom_asset_ids::WAVEBUSTER_DOOR_FORCEFIELD_TXTR, // pink and purple bands
+            DoorType::Icespreader  =>   custom_asset_ids::ICESPREADER_DOOR_FORCEFIELD_TXTR, // pale cyan and deep blue bands
+            DoorType::Flamethrower =>   custom_asset_ids::FLAMETHROWER_DOOR_FORCEFIELD_TXTR, // orange and brown bands
             DoorType::Ai           =>   0x717AABCE, // void with specks
+            DoorType::Xray         =>   custom_asset_ids::XRAY_DOOR_FORCEFIELD_TXTR, // white and dark blue bands
+            DoorType::Thermal      =>   custom_asset_ids::THERMAL_DOOR_FORCEFIELD_TXTR, // blue, red and yellow bands
+            DoorType::Solid        =>   0x8A7F3683, // vanilla blue
+            DoorType::OneWay       =>   0x8A7F3683, // vanilla blue, though the forcefield is switched off
+            DoorType::OneWayNorth  =>   0x8A7F3683, // vanilla blue, drawn on one side only
+            DoorType::OneWaySouth  =>   0x8A7F3683, // vanilla blue, drawn on one side only
+            DoorType::OneWayEast   =>   0x8A7F3683, // vanilla blue, drawn on one side only
+            DoorType::OneWayWest   =>   0x8A7F3683, // vanilla blue, drawn on one side only
 
             // vertical doors use the same textures as their horizontal variants //
             DoorType::VerticalBlue         =>   DoorType::Blue.forcefield_txtr(),
@@ -296,6 +419,9 @@ impl DoorType {
             DoorType::VerticalIcespreader  =>   DoorType::Icespreader.forcefield_txtr(), 
             DoorType::VerticalFlamethrower =>   DoorType::Flamethrower.forcefield_txtr(), 
             DoorType::VerticalAi           =>   DoorType::Ai.forcefield_txtr(), 
+            DoorType::VerticalBoost        =>   DoorType::Boost.forcefield_txtr(),
+            DoorType::VerticalSolid        =>   DoorType::Solid.forcefield_txtr(),
+            DoorType::VerticalOneWay       =>   DoorType::OneWay.forcefield_txtr(),
         }
     }
 
@@ -306,7 +432,7 @@ impl DoorType {
             DoorType::Purple               =>   0xAB031EA9, // vanilla TXTR
             DoorType::White                =>   0xF6870C9F, // vanilla TXTR
             DoorType::Red                  =>   0x61A6945B, // vanilla TXTR
-            DoorType::Boost                =>   0x88ED4593, // unused
+            DoorType::Boost                =>   custom_asset_ids::BOOST_DOOR_TXTR,
             DoorType::PowerBomb            =>   custom_asset_ids::POWER_BOMB_DOOR_TXTR,
             DoorType::Bomb                 =>   custom_asset_ids::MORPH_BALL_BOMB_DOOR_TXTR,
             DoorType::Missile              =>   0x459582C1, // "bedroomeyesC"
@@ -317,6 +443,14 @@ impl DoorType {
             DoorType::Flamethrower         =>   custom_asset_ids::FLAMETHROWER_DOOR_TXTR,
             DoorType::Disabled             =>   0x717AABCE, // void with specks
             DoorType::Ai                   =>   custom_asset_ids::AI_DOOR_TXTR,
+            DoorType::Xray                 =>   0x717AABCE, // void with specks
+            DoorType::Thermal              =>   custom_asset_ids::THERMAL_DOOR_TXTR,
+            DoorType::Solid                =>   0x88ED4593, // vanilla TXTR - "blueholorim"
+            DoorType::OneWay               =>   0x88ED4593, // vanilla TXTR
+            DoorType::OneWayNorth          =>   0x88ED4593, // vanilla TXTR
+            DoorType::OneWaySouth          =>   0x88ED4593, // vanilla TXTR
+            DoorType::OneWayEast           =>   0x88ED4593, // vanilla TXTR
+            DoorType::OneWayWest           =>   0x88ED4593, // vanilla TXTR
             
             // vertical doors use the same textures as their horizontal variants //
             DoorType::VerticalBlue         =>   DoorType::Blue.holorim_texture(),
@@ -334,6 +468,9 @@ impl DoorType {
             DoorType::VerticalIcespreader  =>   DoorType::Icespreader.holorim_texture(), 
             DoorType::VerticalFlamethrower =>   DoorType::Flamethrower.holorim_texture(), 
             DoorType::VerticalAi           =>   DoorType::Ai.holorim_texture(),
+            DoorType::VerticalBoost        =>   DoorType::Boost.holorim_texture(),
+            DoorType::VerticalSolid        =>   DoorType::Solid.holorim_texture(),
+            DoorType::VerticalOneWay       =>   DoorType::OneWay.holorim_texture(),
         }
     }
 
@@ -374,6 +511,14 @@ impl DoorType {
             DoorType::Icespreader,
             DoorType::Flamethrower,
             DoorType::Ai,
+            DoorType::Xray,
+            DoorType::Thermal,
+            DoorType::Solid,
+            DoorType::OneWay,
+            DoorType::OneWayNorth,
+            DoorType::OneWaySouth,
+            DoorType::OneWayEast,
+            DoorType::OneWayWest,
             DoorType::VerticalBlue,
             DoorType::VerticalPowerOnly,
             DoorType::VerticalPurple,
@@ -389,6 +534,9 @@ impl DoorType {
             DoorType::VerticalIcespreader,
             DoorType::VerticalFlamethrower,
             DoorType::VerticalAi,
+            DoorType::VerticalBoost,
+            DoorType::VerticalSolid,
+            DoorType::VerticalOneWay,
         ].iter().map(|i| *i)
     }
 
@@ -974,6 +1122,72 @@ impl DoorType {
             DoorType::VerticalIcespreader  =>   DoorType::Icespreader.vulnerability(), 
             DoorType::VerticalFlamethrower =>   DoorType::Flamethrower.vulnerability(), 
             DoorType::VerticalAi           =>   DoorType::Ai.vulnerability(),
+            DoorType::VerticalBoost        =>   DoorType::Boost.vulnerability(),
+            DoorType::VerticalSolid        =>   DoorType::Solid.vulnerability(),
+            DoorType::VerticalOneWay       =>   DoorType::OneWay.vulnerability(),
+
+            // Any weapon opens a visor door, the forcefield just can't be hit without the visor //
+            DoorType::Xray                 =>   DoorType::Blue.vulnerability(),
+            DoorType::Thermal              =>   DoorType::Blue.vulnerability(),
+
+            // Nothing can shoot a solid door open, only a door opener from the config can open it //
+            DoorType::Solid                =>   DoorType::Disabled.vulnerability(),
+
+            // A one way door starts with its forcefield switched off, and it can't be hurt if the door switches it back on //
+            DoorType::OneWay               =>   DoorType::Disabled.vulnerability(),
+
+            // The forcefield of a directional one way door is only drawn, and can only be shot, from its render side //
+            DoorType::OneWayNorth          =>   DoorType::Blue.vulnerability(),
+            DoorType::OneWaySouth          =>   DoorType::Blue.vulnerability(),
+            DoorType::OneWayEast           =>   DoorType::Blue.vulnerability(),
+            DoorType::OneWayWest           =>   DoorType::Blue.vulnerability(),
+        }
+    }
+
+    // Visor flags (Combat|Scan|Thermal|XRay) of the visors the forcefield can be hit with. The
+    // game has no script object that checks the current visor, so visor doors rely on the same
+    // visor mask that keeps the vanilla thermal conduits from being hit outside the Thermal Visor.
+    pub fn visor_mask(&self) -> Option<u32> {
+        match self {
+            DoorType::Thermal => Some(4),
+            DoorType::Xray    => Some(8),
+            _ => None,
+        }
+    }
+
+    pub fn required_pickups(&self) -> Vec<PickupType> { // items needed to open the door from the front
+        match self {
+            DoorType::Purple       => vec![PickupType::WaveBeam],
+            DoorType::White        => vec![PickupType::IceBeam],
+            DoorType::Red          => vec![PickupType::PlasmaBeam],
+            DoorType::PowerBomb    => vec![PickupType::MorphBall, PickupType::PowerBomb],
+            DoorType::Bomb         => vec![PickupType::MorphBall, PickupType::MorphBallBomb],
+            DoorType::Boost        => vec![PickupType::MorphBall, PickupType::BoostBall],
+            DoorType::Missile      => vec![PickupType::Missile],
+            DoorType::Charge       => vec![PickupType::ChargeBeam],
+            DoorType::Super        => vec![PickupType::Missile, PickupType::ChargeBeam, PickupType::SuperMissile],
+            DoorType::Wavebuster   => vec![PickupType::Missile, PickupType::ChargeBeam, PickupType::WaveBeam, PickupType::Wavebuster],
+            DoorType::Icespreader  => vec![PickupType::Missile, PickupType::ChargeBeam, PickupType::IceBeam, PickupType::IceSpreader],
+            DoorType::Flamethrower => vec![PickupType::Missile, PickupType::ChargeBeam, PickupType::PlasmaBeam, PickupType::Flamethrower],
+            DoorType::Xray         => vec![PickupType::XRayVisor],
+            DoorType::Thermal      => vec![PickupType::ThermalVisor],
+
+            // vertical doors need the same items as their horizontal variants //
+            DoorType::VerticalPurple       => DoorType::Purple.required_pickups(),
+            DoorType::VerticalWhite        => DoorType::White.required_pickups(),
+            DoorType::VerticalRed          => DoorType::Red.required_pickups(),
+            DoorType::VerticalPowerBomb    => DoorType::PowerBomb.required_pickups(),
+            DoorType::VerticalBomb         => DoorType::Bomb.required_pickups(),
+            DoorType::VerticalMissile      => DoorType::Missile.required_pickups(),
+            DoorType::VerticalCharge       => DoorType::Charge.required_pickups(),
+            DoorType::VerticalSuper        => DoorType::Super.required_pickups(),
+            DoorType::VerticalWavebuster   => DoorType::Wavebuster.required_pickups(),
+            DoorType::VerticalIcespreader  => DoorType::Icespreader.required_pickups(),
+            DoorType::VerticalFlamethrower => DoorType::Flamethrower.required_pickups(),
+            DoorType::VerticalBoost        => DoorType::Boost.required_pickups(),
+
+            // blue, power only, ai and disabled doors don't need (or can't be opened with) any item
+            _ => Vec::new(),
         }
     }
 
@@ -1018,6 +1232,15 @@ impl BlastShieldType {
         }
     }
 
+    // Door specifications name a blast shield with a "_shield" suffix, e.g. "missile_shield"
+    pub fn from_door_specification(door_specification: &str) -> Option<Self> {
+        let door_specification = door_specification.to_lowercase();
+        if !door_specification.ends_with("_shield") {
+            return None;
+        }
+        BlastShieldType::from_string(door_specification.trim_end_matches("_shield").to_string())
+    }
+
     pub const fn cmdl(&self) -> u32 {
         match self {
             _ => 0xEFDFFB8C, // Vanilla missile lock model
@@ -1054,6 +1277,50 @@ impl BlastShieldType {
         }
     }
 
+    pub const fn scan(&self) -> u32 {
+        match self {
+            BlastShieldType::Missile        => custom_asset_ids::BLAST_SHIELD_SCAN_START,
+            BlastShieldType::PowerBomb      => custom_asset_ids::BLAST_SHIELD_SCAN_START + 2,
+            BlastShieldType::Super          => custom_asset_ids::BLAST_SHIELD_SCAN_START + 4,
+            BlastShieldType::Wavebuster     => custom_asset_ids::BLAST_SHIELD_SCAN_START + 6,
+            BlastShieldType::Icespreader    => custom_asset_ids::BLAST_SHIELD_SCAN_START + 8,
+            BlastShieldType::Flamethrower   => custom_asset_ids::BLAST_SHIELD_SCAN_START + 10,
+            BlastShieldType::None           => 0xFFFFFFFF,
+        }
+    }
+
+    pub const fn strg(&self) -> u32 { // the scan's text, always paired with the SCAN
+        match self {
+            BlastShieldType::None => 0xFFFFFFFF,
+            _ => self.scan() + 1,
+        }
+    }
+
+    pub const fn scan_text(&self) -> &'static str {
+        match self {
+            BlastShieldType::Missile        => "A Blast Shield is blocking this door.\nIt can be destroyed with Missiles.\0",
+            BlastShieldType::PowerBomb      => "A Blast Shield is blocking this door.\nIt can be destroyed with a Power Bomb.\0",
+            BlastShieldType::Super          => "A Blast Shield is blocking this door.\nIt can be destroyed with a Super Missile.\0",
+            BlastShieldType::Wavebuster     => "A Blast Shield is blocking this door.\nIt can be destroyed with the Wavebuster.\0",
+            BlastShieldType::Icespreader    => "A Blast Shield is blocking this door.\nIt can be destroyed with the Ice Spreader.\0",
+            BlastShieldType::Flamethrower   => "A Blast Shield is blocking this door.\nIt can be destroyed with the Flamethrower.\0",
+            BlastShieldType::None           => "\0",
+        }
+    }
+
+    // The door color that needs the same items, for the door logic check
+    pub const fn door_type(&self) -> DoorType {
+        match self {
+            BlastShieldType::Missile        => DoorType::Missile,
+            BlastShieldType::PowerBomb      => DoorType::PowerBomb,
+            BlastShieldType::Super          => DoorType::Super,
+            BlastShieldType::Wavebuster     => DoorType::Wavebuster,
+            BlastShieldType::Icespreader    => DoorType::Icespreader,
+            BlastShieldType::Flamethrower   => DoorType::Flamethrower,
+            BlastShieldType::None           => DoorType::Blue,
+        }
+    }
+
     pub fn dependencies(&self) -> Vec<(u32, FourCC)> { // dependencies to add to the area
         
         let mut data: Vec<(u32, FourCC)> = Vec::new();
@@ -1063,6 +1330,8 @@ impl BlastShieldType {
         data.push((self.misc_rectangles_txtr(),     FourCC::from_bytes(b"TXTR")));
         data.push((self.animation_txtr(),           FourCC::from_bytes(b"TXTR")));
         data.push((self.misc_metal_txtr(),          FourCC::from_bytes(b"TXTR")));
+        data.push((self.scan(),                     FourCC::from_bytes(b"SCAN")));
+        data.push((self.strg(),                     FourCC::from_bytes(b"STRG")));
         data.retain(|i| i.0 != 0xffffffff && i.0 != 0);
         data
     }
@@ -1091,3 +1360,126 @@ impl BlastShieldType {
         }
     }
 }
+
+#[test]
+fn test_door_type_all_names()
+{
+    let names = DoorType::all_names();
+
+    // The beam aliases are listed along with the colors
+    let aliases = [
+        ("blue", DoorType::Blue),
+        ("wave_beam", DoorType::Purple),
+        ("ice_beam", DoorType::White),
+        ("plasma_beam", DoorType::Red),
+    ];
+    for &(name, door_type) in aliases.iter() {
+        assert!(names.contains(&name), "{} isn't listed", name);
+        assert_eq!(DoorType::from_string(name.to_uppercase()), Some(door_type));
+    }
+
+    // Every door type that can be placed by name has at least one name
+    for door_type in DoorType::iter().filter(|door_type| !door_type.is_vertical()) {
+        assert!(
+            names.iter().any(|name| DoorType::from_string(name.to_string()) == Some(door_type)),
+            "{:?} has no name", door_type,
+        );
+    }
+}
+
+#[test]
+fn test_blast_shield_door_specification()
+{
+    assert_eq!(BlastShieldType::from_door_specification("missile_shield"), Some(BlastShieldType::Missile));
+    assert_eq!(BlastShieldType::from_door_specification("Super_Missile_Shield"), Some(BlastShieldType::Super));
+    assert_eq!(BlastShieldType::from_door_specification("missile"), None);
+    assert_eq!(BlastShieldType::from_door_specification("random_shield"), None);
+}
+
+#[test]
+fn test_visor_doors()
+{
+    assert_eq!(DoorType::from_string("xray_visor".to_string()), Some(DoorType::Xray));
+    assert_eq!(DoorType::from_string("thermal".to_string()), Some(DoorType::Thermal));
+    assert_eq!(DoorType::Xray.visor_mask(), Some(8));
+    assert_eq!(DoorType::Thermal.visor_mask(), Some(4));
+    assert_eq!(DoorType::Blue.visor_mask(), None);
+}
+
+#[test]
+fn test_boost_door()
+{
+    assert_eq!(DoorType::from_string("boost".to_string()), Some(DoorType::Boost));
+    assert_eq!(DoorType::from_string("BoostBall".to_string()), Some(DoorType::Boost));
+    assert_eq!(DoorType::Boost.to_vertical(), DoorType::VerticalBoost);
+    assert!(DoorType::iter().any(|d| d == DoorType::VerticalBoost));
+    assert!(DoorType::Boost.shield_cmdl() >= 0xDEAF0000);
+    assert_eq!(DoorType::Boost.map_object_type(), structs::MapaObjectType::DoorShield as u32);
+}
+
+#[test]
+fn test_solid_door()
+{
+    assert_eq!(DoorType::from_string("solid".to_string()), Some(DoorType::Solid));
+    assert_eq!(DoorType::Solid.to_vertical(), DoorType::VerticalSolid);
+    assert_eq!(DoorType::Solid.shield_cmdl(), DoorType::Blue.shield_cmdl());
+    assert_eq!(DoorType::VerticalSolid.shield_cmdl(), DoorType::VerticalBlue.shield_cmdl());
+    assert_eq!(DoorType::Solid.map_object_type(), structs::MapaObjectType::DoorShield as u32);
+    assert_eq!(DoorType::Solid.vulnerability().power, TypeVulnerability::Immune as u32);
+    assert_eq!(DoorType::Solid.vulnerability().boost_ball, TypeVulnerability::Immune as u32);
+    assert_eq!(DoorType::Solid.dependencies(), DoorType::Blue.dependencies());
+}
+
+#[test]
+fn test_door_names_to_vertical()
+{
+    // Door specifications are mapped with from_string and then to_vertical for vertical doors, so
+    // every door that can be named needs a vertical variant unless it has no vertical model yet
+    for name in DoorType::all_names() {
+        let door_type = DoorType::from_string(name.to_string()).unwrap();
+        assert!(!door_type.is_vertical(), "{} is a vertical door", name);
+        match door_type {
+            DoorType::Xray | DoorType::Thermal => assert_eq!(door_type.to_vertical(), door_type),
+            _ => assert!(door_type.to_vertical().is_vertical(), "{} has no vertical variant", name),
+        }
+    }
+}
+
+#[test]
+fn test_weights_validate()
+{
+    let mut weights = Weights {
+        tallon_overworld: [100, 0, 0, 0],
+        chozo_ruins: [25, 25, 25, 25],
+        magmoor_caverns: [0, 0, 0, 100],
+        phendrana_drifts: [40, 20, 20, 20],
+        phazon_mines: [97, 1, 1, 1],
+    };
+    assert!(weights.validate(true).is_ok());
+    weights.magmoor_caverns = [200, 200, 200, 200];
+    assert!(weights.validate(false).is_ok());
+    assert_eq!(
+        weights.validate(true),
+        Err("The door weights for Magmoor Caverns add up to 800, but must add up to exactly 100".to_string()),
+    );
+    weights.magmoor_caverns = [0; 4];
+    assert_eq!(weights.validate(false), Err("The door weights for Magmoor Caverns are all 0".to_string()));
+}
+
+#[test]
+fn test_one_way_door()
+{
+    assert_eq!(DoorType::from_string("oneway".to_string()), Some(DoorType::OneWay));
+    assert_eq!(DoorType::from_string("One_Way".to_string()), Some(DoorType::OneWay));
+    assert!(DoorType::OneWay.is_one_way());
+    assert!(DoorType::OneWay.to_vertical().is_one_way());
+    assert!(!DoorType::Blue.is_one_way());
+    assert_eq!(DoorType::OneWay.map_object_type(), structs::MapaObjectType::DoorShield as u32);
+
+    assert_eq!(DoorType::from_string("OneWay_East".to_string()), Some(DoorType::OneWayEast));
+    assert!(DoorType::OneWayEast.is_one_way());
+    assert_eq!(DoorType::OneWayEast.render_side(), Some(3));
+    assert_eq!(DoorType::OneWay.render_side(), None);
+    assert_eq!(DoorType::OneWayNorth.to_vertical(), DoorType::VerticalOneWay);
+    assert_eq!(DoorType::OneWayWest.map_object_type(), structs::MapaObjectType::DoorShield as u32);
+}
@@ -1,3 +1,5 @@
+use crate::{door_meta::World, pickup_meta::PICKUP_LOCATIONS};
+
 #[derive(Clone, Copy, Debug)]
 pub struct Elevator {
     pub pak_name: &'static str,
@@ -42,6 +44,72 @@ impl Elevator
             mrea_idx: self.mrea_idx,
         }
     }
+
+    // Look up an elevator by name, with or without the part in parentheses, e.g.
+    // "Chozo Ruins West" or "Chozo Ruins West (Main Plaza)", or by the room it's in, e.g.
+    // "Transport to Tallon Overworld North". A few elevator rooms share a name, those need the
+    // world in front like spawn rooms do, e.g. "Mines:Transport to Tallon Overworld South".
+    // Case is ignored.
+    pub fn from_string(name: &str) -> Option<Elevator>
+    {
+        let name = name.trim().to_lowercase();
+        let by_name = ELEVATORS.iter()
+            .find(|elv| {
+                let short_name = elv.name.split('\0').next().unwrap();
+                name == elv.name.replace('\0', " ").to_lowercase() || name == short_name.to_lowercase()
+            });
+        if by_name.is_some() {
+            return by_name.cloned();
+        }
+
+        let (world_name, room_name) = match name.split_once(':') {
+            Some((world_name, room_name)) => (world_name.trim(), room_name.trim()),
+            None => ("", name.as_str()),
+        };
+        let mut by_room = ELEVATORS.iter()
+            .filter(|elv| {
+                let world = World::from_pak(elv.pak_name).unwrap();
+                world.as_string().to_lowercase().starts_with(world_name) &&
+                    elv.room_name().map(|name| name.to_lowercase() == room_name).unwrap_or(false)
+            });
+        match (by_room.next(), by_room.next()) {
+            (Some(elv), None) => Some(*elv),
+            _ => None,
+        }
+    }
+
+    // The name of the room the elevator is in
+    pub fn room_name(&self) -> Option<&'static str>
+    {
+        PICKUP_LOCATIONS.iter()
+            .filter(|(pak_name, _)| *pak_name == self.pak_name)
+            .flat_map(|(_, rooms)| rooms.iter())
+            .find(|room_info| room_info.room_id == self.mrea)
+            .map(|room_info| room_info.name)
+    }
+}
+
+// The text of the three STRGs in each elevator room, for an elevator going to dest_name. Names
+// may be split over two lines with a '\0', like the names in ELEVATORS.
+pub fn elevator_room_text(dest_name: &str) -> String
+{
+    format!("Transport to {}\u{0}", dest_name.replace('\0', "\n"))
+}
+
+pub fn elevator_hologram_text(dest_name: &str) -> String
+{
+    format!(
+        "Access to &main-color=#FF3333;{} &main-color=#89D6FF;granted. Please step into the hologram.\u{0}",
+        dest_name.replace('\0', " "),
+    )
+}
+
+pub fn elevator_control_text(dest_name: &str) -> String
+{
+    format!(
+        "Transport to &main-color=#FF3333;{}&main-color=#89D6FF; active.\u{0}",
+        dest_name.replace('\0', " "),
+    )
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -84,6 +152,37 @@ impl SpawnRoom
             mrea_idx: 0,
         }
     }
+
+    // The name elevator text uses for this room: the elevator's name if there's one here,
+    // otherwise the room's own name
+    pub fn display_name(&self) -> String
+    {
+        let end_game = Elevator::end_game_elevator();
+        if *self == end_game.to_spawn_room() {
+            return end_game.name.to_string();
+        }
+        if let Some(elv) = ELEVATORS.iter().find(|elv| elv.mlvl == self.mlvl && elv.mrea == self.mrea) {
+            return elv.name.to_string();
+        }
+        PICKUP_LOCATIONS.iter()
+            .filter(|(pak_name, _)| *pak_name == self.pak_name)
+            .flat_map(|(_, rooms)| rooms.iter())
+            .find(|room_info| room_info.room_id == self.mrea)
+            .map(|room_info| room_info.name.to_string())
+            .unwrap_or_else(|| format!("0x{:08X}", self.mrea))
+    }
+
+    // Reactor Core Entrance, the room before the Parasite Queen. Defeating her starts the
+    // self-destruct sequence, which leads straight into the escape.
+    pub fn frigate_escape_spawn_room() -> SpawnRoom
+    {
+        SpawnRoom {
+            pak_name: "Metroid1.pak",
+            mlvl: 0x158EFE17,
+            mrea: 0x3EA190EE,
+            mrea_idx: 27,
+        }
+    }
 }
 
 pub const ELEVATORS: &[Elevator] = &[
@@ -400,4 +499,27 @@ pub const ELEVATORS: &[Elevator] = &[
 
 ];
 
-
+#[test]
+fn test_elevator_names()
+{
+    let elv = Elevator::from_string("chozo ruins west").unwrap();
+    assert_eq!(elv.mrea, ELEVATORS[0].mrea);
+    assert_eq!(Elevator::from_string("Chozo Ruins West (Main Plaza)").unwrap().mrea, elv.mrea);
+    assert!(Elevator::from_string("Main Plaza").is_none());
+    // Each elevator's room leads back to that elevator
+    for elv in ELEVATORS.iter() {
+        let world = World::from_pak(elv.pak_name).unwrap().as_string();
+        let room_name = format!("{}:{}", world, elv.room_name().unwrap());
+        assert_eq!(Elevator::from_string(&room_name.to_uppercase()).unwrap().mrea, elv.mrea, "{}", room_name);
+    }
+    assert_eq!(Elevator::from_string("Transport to Tallon Overworld North").unwrap().mrea, elv.mrea);
+    // Chozo Ruins and Phazon Mines both have one
+    assert!(Elevator::from_string("Transport to Tallon Overworld South").is_none());
+
+    assert_eq!(elv.to_spawn_room().display_name(), ELEVATORS[0].name);
+    assert_eq!(SpawnRoom::landing_site_spawn_room().display_name(), "Landing Site");
+    assert_eq!(
+        elevator_room_text(ELEVATORS[0].name),
+        "Transport to Chozo Ruins West\n(Main Plaza)\u{0}",
+    );
+}
@@ -1,7 +1,7 @@
 use reader_writer::byteorder::{LittleEndian, WriteBytesExt};
 use structs;
 
-use flate2::{self, Compress, Compression, FlushCompress};
+use flate2::{self, Compress, Compression, Decompress, FlushCompress, FlushDecompress};
 use adler32::adler32;
 
 use std::{
@@ -19,6 +19,15 @@ const GCZ_MAGIC: u32 = 0xB10BC001;
 
 pub const ZEROES: &[u8; block_size!()] = &[0u8; block_size!()];
 
+// One block as laid out by a writer, for debugging loader incompatibilities
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WrittenRegion
+{
+    pub offset: u64,
+    pub length: u64,
+    pub compressed_length: u64,
+}
+
 pub struct GczWriter<W: Write + Seek>
 {
     expected_uncompressed_size: u64,
@@ -34,6 +43,9 @@ pub struct GczWriter<W: Write + Seek>
 
     compressor: Compress,
     file: W,
+
+    regions: Option<Vec<WrittenRegion>>,
+    finished: bool,
 }
 
 impl<W: Write + Seek> GczWriter<W>
@@ -65,8 +77,76 @@ impl<W: Write + Seek> GczWriter<W>
 
             compressor: Compress::new(Compression::best(), true),
             file,
+
+            regions: None,
+            finished: false,
         }))
     }
+
+    // Start keeping track of every block written, see regions()
+    pub fn record_regions(&mut self)
+    {
+        self.regions = Some(Vec::new());
+    }
+
+    // The blocks written so far, if record_regions was called. The offsets are relative to the
+    // start of the block data, like the offsets in the GCZ header.
+    pub fn regions(&self) -> Option<&[WrittenRegion]>
+    {
+        self.regions.as_ref().map(|regions| &regions[..])
+    }
+
+    fn push_block(&mut self, compressed_length: u64, hash: u32, compressed: bool)
+    {
+        if let Some(regions) = self.regions.as_mut() {
+            regions.push(WrittenRegion {
+                offset: self.total_bytes_written,
+                length: block_size!(),
+                compressed_length,
+            });
+        }
+        if compressed {
+            self.block_offsets.push(self.total_bytes_written);
+        } else {
+            self.block_offsets.push(self.total_bytes_written | 0x8000000000000000);
+        }
+        self.total_bytes_written += compressed_length;
+        self.hashes.push(hash);
+    }
+
+    // Write out the last block and the header. This happens automatically on drop, but calling
+    // it directly allows errors to be handled and regions() to be inspected afterwards.
+    pub fn finish(&mut self) -> io::Result<()>
+    {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        // Write whatever is left over in our buffer to a block (empty space paddeded with zeroes)
+        if self.input_buf_used != 0 {
+            let bytes_to_zero = block_size!() - self.input_buf_used as usize;
+            self.write_all(&ZEROES[..bytes_to_zero])?;
+        }
+
+        assert!(self.input_buf_used == 0);
+
+        // Seek the file back to the start and write the header
+        self.file.seek(io::SeekFrom::Start(0))?;
+        self.file.write_u32::<LittleEndian>(GCZ_MAGIC)?;
+        self.file.write_u32::<LittleEndian>(0)?;
+        self.file.write_u64::<LittleEndian>(self.total_bytes_written)?;
+        self.file.write_u64::<LittleEndian>(self.expected_uncompressed_size)?;
+        self.file.write_u32::<LittleEndian>(block_size!())?;
+        self.file.write_u32::<LittleEndian>(self.block_offsets.len() as u32)?;
+        for offset in &self.block_offsets {
+            self.file.write_u64::<LittleEndian>(*offset)?;
+        }
+        for hash in &self.hashes {
+            self.file.write_u32::<LittleEndian>(*hash)?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -91,15 +171,13 @@ impl<W: Write + Seek> Write for GczWriter<W>
             let output_buf = &self.output_buf[..compressed_len as usize];
 
             if !finished || compressed_len > block_size!() - 10 {
-                self.block_offsets.push(self.total_bytes_written | 0x8000000000000000);
                 self.file.write_all(&self.input_buf)?;
-                self.total_bytes_written += block_size!();
-                self.hashes.push(adler32(&self.input_buf[..])?);
+                let hash = adler32(&self.input_buf[..])?;
+                self.push_block(block_size!(), hash, false);
             } else {
-                self.block_offsets.push(self.total_bytes_written);
                 self.file.write_all(&output_buf)?;
-                self.total_bytes_written += compressed_len as u64;
-                self.hashes.push(adler32(&output_buf[..])?);
+                let hash = adler32(output_buf)?;
+                self.push_block(compressed_len, hash, true);
             }
 
             self.input_buf_used = 0;
@@ -150,11 +228,10 @@ impl<W: Write + Seek> structs::WriteExt for GczWriter<W>
                 let hash = adler32(&compressed_bytes[..])?;
                 self.zero_block_data = Some((compressed_bytes, hash));
             }
-            let (compressed_bytes, hash) = self.zero_block_data.as_ref().unwrap();
-            self.block_offsets.push(self.total_bytes_written);
-            self.total_bytes_written += compressed_bytes.len() as u64;
-            self.hashes.push(*hash);
+            let (compressed_bytes, hash) = self.zero_block_data.take().unwrap();
             self.file.write_all(&compressed_bytes[..])?;
+            self.push_block(compressed_bytes.len() as u64, hash, true);
+            self.zero_block_data = Some((compressed_bytes, hash));
 
             bytes -= block_size!();
         }
@@ -168,34 +245,87 @@ impl<W: Write + Seek> Drop for GczWriter<W>
 {
     fn drop(&mut self)
     {
-        let res = || -> io::Result<()> {
-            // Write whatever is left over in our buffer to a block (empty space paddeded with zeroes)
-            if self.input_buf_used != 0 {
-                let bytes_to_zero = block_size!() - self.input_buf_used as usize;
-                self.write_all(&ZEROES[..bytes_to_zero])?;
-            }
-
-            assert!(self.input_buf_used == 0);
-
-            // Seek the file back to the start and write the header
-            self.file.seek(io::SeekFrom::Start(0))?;
-            self.file.write_u32::<LittleEndian>(GCZ_MAGIC)?;
-            self.file.write_u32::<LittleEndian>(0)?;
-            self.file.write_u64::<LittleEndian>(self.total_bytes_written)?;
-            self.file.write_u64::<LittleEndian>(self.expected_uncompressed_size)?;
-            self.file.write_u32::<LittleEndian>(block_size!())?;
-            self.file.write_u32::<LittleEndian>(self.block_offsets.len() as u32)?;
-            for offset in &self.block_offsets {
-                self.file.write_u64::<LittleEndian>(*offset)?;
-            }
-            for hash in &self.hashes {
-                self.file.write_u32::<LittleEndian>(*hash)?;
-            }
-            Ok(())
-        }();
+        let res = self.finish();
         // We really don't want to panic from a destructor, so just write a warning instead
         if let Err(e) = res {
             eprintln!("Error closing GczWriter: {}", e);
         };
     }
 }
+
+pub fn is_gcz(data: &[u8]) -> bool
+{
+    data.len() >= 4 && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == GCZ_MAGIC
+}
+
+// The read side of GczWriter, for using a GCZ directly as the input disc
+pub fn decompress_gcz(data: &[u8]) -> Result<Vec<u8>, String>
+{
+    let read_u32 = |offset: usize| data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "The GCZ file is truncated".to_string());
+    let read_u64 = |offset: usize| data.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+        .ok_or_else(|| "The GCZ file is truncated".to_string());
+
+    if !is_gcz(data) {
+        Err("The input file isn't a GCZ".to_string())?
+    }
+    let compressed_size = read_u64(8)?;
+    let uncompressed_size = read_u64(16)? as usize;
+    let block_size = read_u32(24)? as usize;
+    let num_blocks = read_u32(28)? as usize;
+    let data_start = 32 + 12 * num_blocks;
+
+    let mut out = Vec::with_capacity(num_blocks * block_size);
+    let mut decompressor = Decompress::new(true);
+    for i in 0..num_blocks {
+        let offset = read_u64(32 + 8 * i)?;
+        let start = offset & !0x8000000000000000;
+        let end = if i + 1 < num_blocks {
+            read_u64(32 + 8 * (i + 1))? & !0x8000000000000000
+        } else {
+            compressed_size
+        };
+        let block = data.get(data_start + start as usize..data_start + end as usize)
+            .ok_or_else(|| format!("GCZ block {} is out of bounds", i))?;
+        if adler32(block).unwrap() != read_u32(32 + 8 * num_blocks + 4 * i)? {
+            Err(format!("GCZ block {} is corrupt", i))?
+        }
+
+        if offset & 0x8000000000000000 != 0 {
+            out.extend_from_slice(block);
+        } else {
+            let len = out.len();
+            out.resize(len + block_size, 0);
+            decompressor.reset(true);
+            let res = decompressor.decompress(block, &mut out[len..], FlushDecompress::Finish)
+                .map_err(|e| format!("Failed to decompress GCZ block {}: {}", i, e))?;
+            if res != flate2::Status::StreamEnd || decompressor.total_out() != block_size as u64 {
+                Err(format!("GCZ block {} has the wrong size", i))?
+            }
+        }
+    }
+    out.truncate(uncompressed_size);
+    Ok(out)
+}
+
+#[test]
+fn test_gcz_round_trip()
+{
+    use structs::WriteExt;
+
+    let size = 4 * block_size!();
+    let data: Vec<u8> = (0..block_size!() + 0x300).map(|i| (i * 7 % 251) as u8).collect();
+    let mut file = io::Cursor::new(Vec::new());
+    {
+        let mut writer = GczWriter::new(&mut file, size as u64).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.skip_bytes((size - data.len()) as u64).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut expected = data.clone();
+    expected.resize(size, 0);
+    assert!(decompress_gcz(file.get_ref()).unwrap() == expected);
+}
@@ -1,25 +1,27 @@
-#![recursion_limit = "128"]
+#![recursion_limit = "256"]
 
 pub use structs;
 pub use reader_writer;
 pub use memmap;
 
 use reader_writer::{
+    FourCC,
     LCow,
     Reader,
 };
 
 
-use flate2::{Decompress, FlushDecompress};
+use flate2::{Compression, Decompress, FlushDecompress, write::ZlibEncoder};
 use num_bigint::BigUint;
 use num_integer::Integer;
-use num_traits::ToPrimitive;
+use num_traits::{Pow, ToPrimitive};
 
 use std::{
     borrow::Cow,
     collections::hash_map::DefaultHasher,
     ffi::{CStr, CString},
     hash::Hasher,
+    io::Write,
     iter,
 };
 
@@ -32,7 +34,9 @@ pub mod patches;
 pub mod c_interface;
 pub mod gcz_writer;
 pub mod ciso_writer;
+pub mod rvz_writer;
 pub mod dol_patcher;
+pub mod logic;
 
 pub trait GcDiscLookupExtensions<'a>
 {
@@ -44,7 +48,14 @@ pub trait GcDiscLookupExtensions<'a>
     fn find_resource_mut<'r, F>(&'r mut self, pak_name: &str, f: F)
         -> Option<&'r mut structs::Resource<'a>>
         where F: FnMut(&structs::Resource<'a>) -> bool;
-
+    // Like find_resource, but checks every pak on the disc and also returns the pak's name
+    fn find_resource_in_any_pak<'r>(&'r self, asset_id: u32, fourcc: FourCC)
+        -> Option<(String, LCow<'r, structs::Resource<'a>>)>;
+    // Swap the contents of an existing resource for (uncompressed) raw bytes
+    fn replace_resource(&mut self, pak_name: &str, asset_id: u32, fourcc: FourCC, bytes: Vec<u8>)
+        -> Result<(), String>;
+
+    // Fails if there is already a file with the same path, rather than adding a second entry
     fn add_file(&mut self, path: &str, file: structs::FstEntryFile<'a>) -> Result<(), String>;
 }
 
@@ -123,8 +134,49 @@ impl<'a> GcDiscLookupExtensions<'a> for structs::GcDisc<'a>
         cursor.into_value()
     }
 
+    fn find_resource_in_any_pak<'r>(&'r self, asset_id: u32, fourcc: FourCC)
+        -> Option<(String, LCow<'r, structs::Resource<'a>>)>
+    {
+        let entries = match &self.file_system_root {
+            structs::FstEntry::Dir(_, entries) => entries,
+            structs::FstEntry::File(_, _, _) => return None,
+        };
+        entries.iter()
+            .filter_map(|e| match e {
+                structs::FstEntry::File(name, _, _) => name.to_str().ok(),
+                structs::FstEntry::Dir(_, _) => None,
+            })
+            .filter(|name| name.to_ascii_lowercase().ends_with(".pak"))
+            .find_map(|name| {
+                let res = self.find_resource(name, |res| res.file_id == asset_id && res.fourcc() == fourcc)?;
+                Some((name.to_string(), res))
+            })
+    }
+
+    fn replace_resource(&mut self, pak_name: &str, asset_id: u32, fourcc: FourCC, mut bytes: Vec<u8>)
+        -> Result<(), String>
+    {
+        if self.find_file(pak_name).is_none() {
+            Err(format!("Pak {} not found", pak_name))?
+        }
+        let res = self.find_resource_mut(pak_name, |res| res.file_id == asset_id && res.fourcc() == fourcc)
+            .ok_or_else(|| format!("Resource 0x{:08X}.{} not found in {}", asset_id, fourcc, pak_name))?;
+
+        // Resources are laid out on 32 byte boundaries within the pak
+        let len = bytes.len();
+        bytes.extend(reader_writer::pad_bytes(32, len).iter());
+
+        res.compressed = false;
+        res.kind = structs::ResourceKind::External(bytes, fourcc);
+        Ok(())
+    }
+
     fn add_file(&mut self, path: &str, file: structs::FstEntryFile<'a>) -> Result<(), String>
     {
+        if self.find_file(path).is_some() {
+            Err(format!("The input ISO already contains {}", path))?
+        }
+
         let mut split = path.rsplitn(2, '/');
         let file_name = split.next()
             .ok_or_else(|| "".to_owned())?;
@@ -182,13 +234,37 @@ pub fn extract_flaahgra_music_files(iso_path: &str) -> Result<[nod_wrapper::File
     res.map_err(|s: String| format!("Failed to extract Flaahgra music files: {}", s))
 }
 
+const LAYOUT_CHAR_TABLE: [u8; 64] =
+    *b"ABCDEFGHIJKLMNOPQRSTUWVXYZabcdefghijklmnopqrstuwvxyz0123456789-_";
+
+// Reverse the order of the odd bits. Applying this twice gives back the original number.
+fn reverse_odd_bits(sum: BigUint) -> BigUint
+{
+    let mut bits = sum.to_str_radix(2).into_bytes();
+    for i in 0..(bits.len() / 4) {
+        let len = bits.len() - bits.len() % 2;
+        bits.swap(i * 2 + 1, len - i * 2 - 1);
+    }
+    BigUint::parse_bytes(&bits, 2).unwrap()
+}
+
+fn layout_checksum(sum: &BigUint, checksum_size: usize) -> u8
+{
+    let checksum_bitmask = (1u8 << checksum_size) - 1;
+    let mut computed_checksum = 0;
+    let mut sum = sum.clone();
+    while sum > 0u8.into() {
+        let remainder = (sum.clone() & BigUint::from(checksum_bitmask)).to_u8().unwrap();
+        computed_checksum = (computed_checksum + remainder) & checksum_bitmask;
+        sum >>= checksum_size;
+    }
+    computed_checksum
+}
+
 pub fn parse_layout_chars_to_ints<I>(bytes: &[u8], layout_data_size: usize, checksum_size: usize, is: I)
     -> Result<Vec<u8>, String>
     where I: Iterator<Item = u8> + Clone
 {
-    const LAYOUT_CHAR_TABLE: [u8; 64] =
-        *b"ABCDEFGHIJKLMNOPQRSTUWVXYZabcdefghijklmnopqrstuwvxyz0123456789-_";
-
     let mut sum: BigUint = 0u8.into();
     for c in bytes.iter().rev() {
         if let Some(idx) = LAYOUT_CHAR_TABLE.iter().position(|i| i == c) {
@@ -198,13 +274,7 @@ pub fn parse_layout_chars_to_ints<I>(bytes: &[u8], layout_data_size: usize, chec
         }
     }
 
-    // Reverse the order of the odd bits
-    let mut bits = sum.to_str_radix(2).into_bytes();
-    for i in 0..(bits.len() / 4) {
-        let len = bits.len() - bits.len() % 2;
-        bits.swap(i * 2 + 1, len - i * 2 - 1);
-    }
-    sum = BigUint::parse_bytes(&bits, 2).unwrap();
+    sum = reverse_odd_bits(sum);
 
     // The upper `checksum_size` bits are a checksum, so seperate them from the sum.
     let checksum_bitmask = (1u8 << checksum_size) - 1;
@@ -212,16 +282,7 @@ pub fn parse_layout_chars_to_ints<I>(bytes: &[u8], layout_data_size: usize, chec
     sum -= checksum.clone();
     let checksum = (checksum >> layout_data_size).to_u8().unwrap();
 
-    let mut computed_checksum = 0;
-    {
-        let mut sum = sum.clone();
-        while sum > 0u8.into() {
-            let remainder = (sum.clone() & BigUint::from(checksum_bitmask)).to_u8().unwrap();
-            computed_checksum = (computed_checksum + remainder) & checksum_bitmask;
-            sum >>= checksum_size;
-        }
-    }
-    if checksum != computed_checksum {
+    if checksum != layout_checksum(&sum, checksum_size) {
         return Err("Layout checksum failed.".to_string());
     }
 
@@ -238,8 +299,141 @@ pub fn parse_layout_chars_to_ints<I>(bytes: &[u8], layout_data_size: usize, chec
     Ok(res)
 }
 
+// The inverse of parse_layout_chars_to_ints
+pub fn encode_layout_ints_to_chars<I>(
+    ints: &[u8],
+    layout_data_size: usize,
+    checksum_size: usize,
+    is: I,
+    char_count: usize,
+) -> Result<String, String>
+    where I: Iterator<Item = u8> + DoubleEndedIterator
+{
+    let mut sum: BigUint = 0u8.into();
+    for (&i, denum) in ints.iter().zip(is.rev()) {
+        // Anything larger would spill into the neighbouring index and change it on parsing
+        if i >= denum {
+            Err(format!("Layout index {} is out of range, it must be less than {}", i, denum))?
+        }
+        sum = sum * BigUint::from(denum) + BigUint::from(i);
+    }
+
+    let checksum = layout_checksum(&sum, checksum_size);
+    sum += BigUint::from(checksum) << layout_data_size;
+
+    let mut sum = reverse_odd_bits(sum);
+
+    let mut res = String::with_capacity(char_count);
+    for _ in 0..char_count {
+        let (quotient, remainder) = sum.div_rem(&BigUint::from(64u8));
+        res.push(LAYOUT_CHAR_TABLE[remainder.to_usize().unwrap()] as char);
+        sum = quotient;
+    }
+    Ok(res)
+}
+
+// The versions of the layout string, told apart by a prefix on the pickup section. Each one can
+// hold the pickup indices of the ones before it plus some new ones.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum LayoutVersion
+{
+    // No prefix, up to Nothing
+    Original,
+    // "!", adds the Scan Visor
+    ScanVisor,
+    // "~", adds the Shiny Missile and Missile Launcher
+    MissileLauncher,
+}
+
+impl LayoutVersion
+{
+    const ALL: [LayoutVersion; 3] = [
+        LayoutVersion::Original,
+        LayoutVersion::ScanVisor,
+        LayoutVersion::MissileLauncher,
+    ];
+
+    fn prefix(self) -> &'static [u8]
+    {
+        match self {
+            LayoutVersion::Original => b"",
+            LayoutVersion::ScanVisor => b"!",
+            LayoutVersion::MissileLauncher => b"~",
+        }
+    }
+
+    // The number of pickup indices the version can store
+    fn radix(self) -> u8
+    {
+        match self {
+            LayoutVersion::Original => 36,
+            LayoutVersion::ScanVisor => 37,
+            LayoutVersion::MissileLauncher => 39,
+        }
+    }
+}
+
+// The number of bits used to store the pickup indices, the number of checksum bits, and the
+// number of characters in the layout string for a layout with `pickup_count` pickups. The
+// checksum fills whatever is left over in the last character, so there's always at least 1 bit of
+// it. For 100 pickups this is (517, 5, 87) for the original version, (521, 1, 87) with the scan
+// visor and (529, 5, 89) with the missile launcher.
+fn pickup_layout_sizes(pickup_count: usize, version: LayoutVersion) -> (usize, usize, usize)
+{
+    let radix = version.radix();
+    let max_sum = BigUint::from(radix).pow(pickup_count as u32) - BigUint::from(1u8);
+    let layout_data_size = max_sum.bits();
+    let char_count = (layout_data_size + 1).div_ceil(6);
+    (layout_data_size, char_count * 6 - layout_data_size, char_count)
+}
+
+// The inverse of parse_layout. The pickup section uses the oldest version that can hold every
+// pickup index, and at least the Scan Visor version when has_scan_visor is set.
+pub fn encode_layout(pickup: &[u8], elevator: &[u8], has_scan_visor: bool) -> Result<String, String>
+{
+    let max_index = pickup.iter().cloned().max().unwrap_or(0);
+    // Indices too large for every version are reported by the encoder
+    let version = LayoutVersion::ALL.iter()
+        .cloned()
+        .filter(|&version| !has_scan_visor || version != LayoutVersion::Original)
+        .find(|version| max_index < version.radix())
+        .unwrap_or(LayoutVersion::MissileLauncher);
+    encode_layout_with_pickup_count(pickup, elevator, version, 100)
+}
+
+fn encode_layout_with_pickup_count(
+    pickup: &[u8],
+    elevator: &[u8],
+    version: LayoutVersion,
+    pickup_count: usize,
+) -> Result<String, String>
+{
+    let elevator_chars = encode_layout_ints_to_chars(
+        elevator,
+        91, 5,
+        iter::once(21u8).chain(iter::repeat_n(20u8, 20)),
+        16,
+    )?;
+    let (layout_data_size, checksum_size, char_count) = pickup_layout_sizes(pickup_count, version);
+    let pickup_chars = encode_layout_ints_to_chars(
+        pickup,
+        layout_data_size,
+        checksum_size,
+        iter::repeat_n(version.radix(), pickup_count),
+        char_count,
+    )?;
+    Ok(format!("{}.{}{}", elevator_chars, String::from_utf8_lossy(version.prefix()), pickup_chars))
+}
 
 pub fn parse_layout(text: &str) -> Result<(Vec<u8>, Vec<u8>, u64), String>
+{
+    parse_layout_with_pickup_count(text, 100)
+}
+
+// Like parse_layout, but for layouts with a number of pickup locations other than the 100 in the
+// vanilla game. The expected length of the layout string is derived from `pickup_count`.
+pub fn parse_layout_with_pickup_count(text: &str, pickup_count: usize)
+    -> Result<(Vec<u8>, Vec<u8>, u64), String>
 {
     if !text.is_ascii() {
         return Err("Layout string contains non-ascii characters.".to_string());
@@ -257,13 +451,16 @@ pub fn parse_layout(text: &str) -> Result<(Vec<u8>, Vec<u8>, u64), String>
         return Err(msg.to_string());
     }
 
-    let (pickup_bytes, has_scan_visor) = if pickup_bytes.starts_with(b"!") {
-        (&pickup_bytes[1..], true)
-    } else {
-        (pickup_bytes, false)
-    };
-    if pickup_bytes.len() != 87 {
-        return Err("Layout string should be exactly 87 characters".to_string());
+    // The original version has no prefix, so it's the one left over when no other matches
+    let version = LayoutVersion::ALL.iter()
+        .rev()
+        .cloned()
+        .find(|version| pickup_bytes.starts_with(version.prefix()))
+        .unwrap();
+    let pickup_bytes = &pickup_bytes[version.prefix().len()..];
+    let (layout_data_size, checksum_size, char_count) = pickup_layout_sizes(pickup_count, version);
+    if pickup_bytes.len() != char_count {
+        return Err(format!("Layout string should be exactly {} characters", char_count));
     }
 
     // XXX The distribution on this hash probably isn't very good, but we don't use it for anything
@@ -275,9 +472,9 @@ pub fn parse_layout(text: &str) -> Result<(Vec<u8>, Vec<u8>, u64), String>
 
     let pickup_layout = parse_layout_chars_to_ints(
             pickup_bytes,
-            if has_scan_visor { 521 } else { 517 },
-            if has_scan_visor { 1 } else { 5 },
-            iter::repeat(if has_scan_visor { 37u8 } else { 36u8 }).take(100)
+            layout_data_size,
+            checksum_size,
+            iter::repeat_n(version.radix(), pickup_count)
         ).map_err(|err| format!("Parsing pickup layout: {}", err))?;
 
     let elevator_layout = parse_layout_chars_to_ints(
@@ -289,6 +486,33 @@ pub fn parse_layout(text: &str) -> Result<(Vec<u8>, Vec<u8>, u64), String>
     Ok((pickup_layout, elevator_layout, seed))
 }
 
+// Like parse_layout, but with the indices already mapped to the pickups and elevators they stand
+// for. The last entry of the elevator layout is where the player starts rather than an elevator,
+// so it is returned separately.
+pub fn parse_layout_typed(text: &str)
+    -> Result<(Vec<pickup_meta::PickupType>, Vec<elevators::Elevator>, elevators::SpawnRoom, u64), String>
+{
+    let (pickup_layout, elevator_layout, seed) = parse_layout(text)?;
+
+    let pickup_layout = pickup_layout.iter()
+        .map(|&idx| pickup_meta::PickupType::from_idx(idx as usize)
+            .ok_or_else(|| format!("Pickup index {} in the layout is out of range", idx)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let (starting_room_idx, elevator_layout) = elevator_layout.split_last()
+        .ok_or_else(|| "The elevator layout is empty".to_string())?;
+    let elevator_layout = elevator_layout.iter()
+        .map(|&idx| elevators::ELEVATORS.get(idx as usize).cloned()
+            .ok_or_else(|| format!("Elevator index {} in the layout is out of range", idx)))
+        .collect::<Result<Vec<_>, String>>()?;
+    if *starting_room_idx as usize > elevators::ELEVATORS.len() {
+        Err(format!("Starting room index {} in the layout is out of range", starting_room_idx))?
+    }
+    let starting_room = elevators::SpawnRoom::from_room_idx(*starting_room_idx as usize);
+
+    Ok((pickup_layout, elevator_layout, starting_room, seed))
+}
+
 
 
 #[derive(Clone, Debug)]
@@ -312,21 +536,37 @@ impl<'a> ResourceData<'a>
             data: reader,
         }
     }
-    pub fn decompress(&self) -> Cow<'a, [u8]>
+    pub fn decompress(&self) -> Result<Cow<'a, [u8]>, String>
     {
         if self.is_compressed {
             let mut reader = self.data.clone();
             let size: u32 = reader.read(());
             let _header: u16 = reader.read(());
-            // TODO: We could use Vec::set_len to avoid initializing the whole array.
-            let mut output = vec![0; size as usize];
-            Decompress::new(false).decompress(&reader, &mut output, FlushDecompress::Finish).unwrap();
+            // decompress_vec only writes into the Vec's spare capacity, so the buffer doesn't
+            // have to be zeroed first
+            let mut output = Vec::with_capacity(size as usize);
+            let mut decompressor = Decompress::new(false);
+            decompressor.decompress_vec(&reader, &mut output, FlushDecompress::Finish)
+                .map_err(|e| format!("Failed to decompress resource: {}", e))?;
+            if decompressor.total_out() != size as u64 {
+                Err(format!("Compressed resource inflated to {} bytes instead of {}",
+                            decompressor.total_out(), size))?
+            }
 
-            Cow::Owned(output)
+            Ok(Cow::Owned(output))
         } else {
-            Cow::Borrowed(&self.data)
+            Ok(Cow::Borrowed(&self.data))
         }
     }
+
+    // The inverse of decompress: the uncompressed size followed by a zlib stream
+    pub fn compress(data: &[u8]) -> Vec<u8>
+    {
+        let output = (data.len() as u32).to_be_bytes().to_vec();
+        let mut encoder = ZlibEncoder::new(output, Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
 }
 
 macro_rules! def_asset_ids {
@@ -395,6 +635,9 @@ pub mod custom_asset_ids {
         DISABLED_DOOR_TXTR,
         AI_DOOR_CMDL,
         AI_DOOR_TXTR,
+        WAVEBUSTER_DOOR_FORCEFIELD_TXTR,
+        ICESPREADER_DOOR_FORCEFIELD_TXTR,
+        FLAMETHROWER_DOOR_FORCEFIELD_TXTR,
         
         // Vertical Door Variants //
         VERTICAL_RED_DOOR_CMDL,
@@ -408,9 +651,196 @@ pub mod custom_asset_ids {
         VERTICAL_ICESPREADER_DOOR_CMDL,
         VERTICAL_FLAMETHROWER_DOOR_CMDL,
         VERTICAL_AI_DOOR_CMDL,
-        
-        // has to be at the end //
+
+        OBFUSCATED_ACQUIRED_HUDMEMO_STRG,
+        STARTING_ITEMS_SCAN,
+        STARTING_ITEMS_STRG,
+
+        // One STRG per PickupType, indexed by PickupType::idx //
         SKIP_HUDMEMO_STRG_START,
-        SKIP_HUDMEMO_STRG_END = SKIP_HUDMEMO_STRG_START + 38,
+        SKIP_HUDMEMO_STRG_END = SKIP_HUDMEMO_STRG_START + 39,
+
+        // User supplied door colors, a CMDL and TXTR each //
+        CUSTOM_DOOR_START = SKIP_HUDMEMO_STRG_END,
+        CUSTOM_DOOR_END = CUSTOM_DOOR_START + 64,
+
+        // A SCAN and STRG for each entry in patches::DESTRUCTIBLE_SCANS //
+        DESTRUCTIBLE_SCAN_START = CUSTOM_DOOR_END,
+        DESTRUCTIBLE_SCAN_END = DESTRUCTIBLE_SCAN_START + 32,
+
+        // A SCAN and STRG for each door_meta::BlastShieldType //
+        BLAST_SHIELD_SCAN_START = DESTRUCTIBLE_SCAN_END,
+        BLAST_SHIELD_SCAN_END = BLAST_SHIELD_SCAN_START + 12,
+
+        // A SCAN and STRG for each pickup with overridden scan text //
+        PICKUP_SCAN_OVERRIDE_START = BLAST_SHIELD_SCAN_END,
+        PICKUP_SCAN_OVERRIDE_END = PICKUP_SCAN_OVERRIDE_START + 256,
+
+        // Visor doors //
+        XRAY_DOOR_CMDL = PICKUP_SCAN_OVERRIDE_END,
+        THERMAL_DOOR_CMDL,
+        THERMAL_DOOR_TXTR,
+        THERMAL_DOOR_FORCEFIELD_TXTR,
+        XRAY_DOOR_FORCEFIELD_TXTR,
+
+        // Boost Ball door //
+        BOOST_DOOR_CMDL,
+        VERTICAL_BOOST_DOOR_CMDL,
+        BOOST_DOOR_TXTR,
+        BOOST_DOOR_FORCEFIELD_TXTR,
+    }
+}
+
+#[test]
+fn test_parse_layout_typed()
+{
+    let pickup_layout: Vec<u8> = (0..100).map(|i| (i % 36) as u8).collect();
+    let elevator_layout: Vec<u8> = (0..21).map(|i| (i * 3 % 20) as u8).collect();
+    let layout = encode_layout(&pickup_layout, &elevator_layout, false).unwrap();
+
+    let (pickups, elevators, starting_room, _) = parse_layout_typed(&layout).unwrap();
+    assert_eq!(pickups.len(), 100);
+    assert_eq!(pickups[0], pickup_meta::PickupType::Missile);
+    assert_eq!(elevators.len(), elevators::ELEVATORS.len());
+    assert_eq!(elevators[1].mrea, elevators::ELEVATORS[3].mrea);
+    assert_eq!(starting_room, elevators::SpawnRoom::from_room_idx(elevator_layout[20] as usize));
+}
+
+#[test]
+fn test_encode_layout_round_trip()
+{
+    let pickup_layout: Vec<u8> = (0..100).map(|i| (i * 7 % 36) as u8).collect();
+    let elevator_layout: Vec<u8> = (0..21).map(|i| (i * 3 % 20) as u8).collect();
+
+    for &has_scan_visor in [false, true].iter() {
+        let layout = encode_layout(&pickup_layout, &elevator_layout, has_scan_visor).unwrap();
+        assert_eq!(layout.contains(".!"), has_scan_visor);
+        let (decoded_pickups, decoded_elevators, _) = parse_layout(&layout).unwrap();
+        assert_eq!(decoded_pickups, pickup_layout);
+        assert_eq!(decoded_elevators, elevator_layout);
+        assert_eq!(encode_layout(&decoded_pickups, &decoded_elevators, has_scan_visor).unwrap(), layout);
+    }
+}
+
+#[test]
+fn test_encode_layout_round_trip_random()
+{
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(0x5EED);
+    for i in 0..200 {
+        let version = LayoutVersion::ALL[i % LayoutVersion::ALL.len()];
+        let pickup_layout: Vec<u8> = (0..100).map(|_| rng.gen_range(0, version.radix())).collect();
+        let mut elevator_layout: Vec<u8> = (0..20).map(|_| rng.gen_range(0, 20)).collect();
+        elevator_layout.push(rng.gen_range(0, 21)); // The starting room
+
+        let layout = encode_layout(&pickup_layout, &elevator_layout, version == LayoutVersion::ScanVisor).unwrap();
+        let (decoded_pickups, decoded_elevators, _) = parse_layout(&layout).unwrap();
+        assert_eq!(decoded_pickups, pickup_layout, "{}", layout);
+        assert_eq!(decoded_elevators, elevator_layout, "{}", layout);
+    }
+
+    // The largest index for every location
+    let elevator_layout: Vec<u8> = iter::repeat_n(19, 20).chain(iter::once(20)).collect();
+    for &version in LayoutVersion::ALL.iter() {
+        let pickup_layout = vec![version.radix() - 1; 100];
+        let layout = encode_layout(&pickup_layout, &elevator_layout, false).unwrap();
+        assert!(layout.contains(&format!(".{}", String::from_utf8_lossy(version.prefix()))));
+        assert_eq!(parse_layout(&layout).unwrap().0, pickup_layout);
     }
 }
+
+#[test]
+fn test_encode_layout_index_out_of_range()
+{
+    let elevator_layout: Vec<u8> = (0..21).map(|i| (i * 3 % 20) as u8).collect();
+    assert_eq!(
+        encode_layout(&[39; 100], &elevator_layout, false),
+        Err("Layout index 39 is out of range, it must be less than 39".to_string())
+    );
+}
+
+#[test]
+fn test_encode_default_elevator_layout()
+{
+    let is = iter::once(21u8).chain(iter::repeat_n(20u8, 20));
+    let elevator_layout = parse_layout_chars_to_ints(b"qzoCAr2fwehJmRjM", 91, 5, is.clone()).unwrap();
+    assert_eq!(encode_layout_ints_to_chars(&elevator_layout, 91, 5, is, 16).unwrap(), "qzoCAr2fwehJmRjM");
+}
+
+#[test]
+fn test_parse_layout_with_pickup_count()
+{
+    assert_eq!(pickup_layout_sizes(100, LayoutVersion::Original), (517, 5, 87));
+    assert_eq!(pickup_layout_sizes(100, LayoutVersion::ScanVisor), (521, 1, 87));
+    assert_eq!(pickup_layout_sizes(100, LayoutVersion::MissileLauncher), (529, 5, 89));
+
+    let pickup_layout: Vec<u8> = (0..120).map(|i| (i * 5 % 36) as u8).collect();
+    let elevator_layout: Vec<u8> = (0..21).map(|i| (i * 3 % 20) as u8).collect();
+    let layout = encode_layout_with_pickup_count(&pickup_layout, &elevator_layout, LayoutVersion::Original, 120).unwrap();
+    let (decoded_pickups, decoded_elevators, _) = parse_layout_with_pickup_count(&layout, 120).unwrap();
+    assert_eq!(decoded_pickups, pickup_layout);
+    assert_eq!(decoded_elevators, elevator_layout);
+    assert!(parse_layout(&layout).is_err());
+}
+
+#[test]
+fn test_resource_data_compress_round_trip()
+{
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * i % 251) as u8).collect();
+    let compressed = ResourceData::compress(&data);
+    assert!(compressed.len() < data.len());
+
+    let resource_data = ResourceData {
+        is_compressed: true,
+        data: Reader::new(&compressed),
+    };
+    assert_eq!(&resource_data.decompress().unwrap()[..], &data[..]);
+}
+
+#[test]
+fn test_resource_data_decompress_known_resource()
+{
+    // "Metroid Prime " * 8, in the same size-prefixed zlib layout the paks use
+    let compressed = [
+        0x00, 0x00, 0x00, 0x70, 0x78, 0xda, 0xf3, 0x4d, 0x2d, 0x29, 0xca, 0xcf, 0x4c, 0x51,
+        0x08, 0x28, 0xca, 0xcc, 0x4d, 0x55, 0xf0, 0xa5, 0x39, 0x0f, 0x00, 0x00, 0x17, 0x28,
+        0x89,
+    ];
+    let resource_data = ResourceData {
+        is_compressed: true,
+        data: Reader::new(&compressed),
+    };
+    assert_eq!(&resource_data.decompress().unwrap()[..], "Metroid Prime ".repeat(8).as_bytes());
+
+    // A header claiming more data than the stream holds is an error, not a short buffer
+    let mut truncated = compressed;
+    truncated[3] = 0x71;
+    let resource_data = ResourceData {
+        is_compressed: true,
+        data: Reader::new(&truncated),
+    };
+    assert_eq!(
+        resource_data.decompress(),
+        Err("Compressed resource inflated to 112 bytes instead of 113".to_string())
+    );
+}
+
+#[test]
+fn test_parse_layout_typed_missile_launcher()
+{
+    use pickup_meta::PickupType;
+
+    let mut pickup_layout: Vec<u8> = (0..100).map(|i| (i % 36) as u8).collect();
+    pickup_layout[3] = PickupType::MissileLauncher.idx() as u8;
+    pickup_layout[99] = PickupType::ScanVisor.idx() as u8;
+    let elevator_layout: Vec<u8> = (0..21).map(|i| (i * 3 % 20) as u8).collect();
+    let layout = encode_layout(&pickup_layout, &elevator_layout, true).unwrap();
+    assert!(layout.contains(".~"));
+
+    let (pickups, _, _, _) = parse_layout_typed(&layout).unwrap();
+    assert_eq!(pickups[3], PickupType::MissileLauncher);
+    assert_eq!(pickups[99], PickupType::ScanVisor);
+    let pickup_indices: Vec<u8> = pickups.iter().map(|pt| pt.idx() as u8).collect();
+    assert_eq!(encode_layout(&pickup_indices, &elevator_layout, true).unwrap(), layout);
+}
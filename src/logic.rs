@@ -0,0 +1,230 @@
+use crate::{
+    door_meta::DoorType,
+    elevators::{Elevator, ELEVATORS},
+    pickup_meta::{self, PickupType},
+};
+
+#[cfg(feature = "logic")]
+use crate::elevators::SpawnRoom;
+
+use std::collections::{HashMap, HashSet};
+
+// One side of a dock: the door the player has to get through to go from room_id to
+// destination_room_id. door_type is None when nothing has to be opened, e.g. a dock without a
+// door, or a solid door that a door opener in the room opens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoorAssignment
+{
+    pub room_id: u32,
+    pub dock_number: u32,
+    pub door_type: Option<DoorType>,
+    pub destination_room_id: u32,
+}
+
+// What a walk from the starting rooms reaches. Only doors and elevators gate the walk, rooms are
+// taken to be traversable inside, so an item that needs e.g. the Space Jump Boots to get to once
+// in its room still counts as reachable.
+#[derive(Clone, Debug, Default)]
+pub struct Reachability
+{
+    pub rooms: HashSet<u32>,
+    pub items: HashSet<PickupType>,
+    // The items needed for a door the walk had to open to get into a room it hadn't reached yet
+    pub key_items: HashSet<PickupType>,
+}
+
+// The items placed in each room, in the rooms' PICKUP_LOCATIONS order
+pub fn room_pickups(pickup_layout: &[PickupType]) -> HashMap<u32, Vec<PickupType>>
+{
+    let mut rooms: HashMap<u32, Vec<PickupType>> = HashMap::new();
+    let mut layout_iterator = pickup_layout.iter();
+    for (_, pak_rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+        for room_info in pak_rooms.iter() {
+            for (_, &pickup_type) in room_info.pickup_locations.iter().zip(&mut layout_iterator) {
+                rooms.entry(room_info.room_id).or_default().push(pickup_type.logic_type());
+            }
+        }
+    }
+    rooms
+}
+
+// Why the player can't go through a door holding `items`, if they can't
+pub fn door_blocker(door_type: Option<DoorType>, items: &HashSet<PickupType>) -> Option<String>
+{
+    let door_type = door_type?;
+    match door_type {
+        DoorType::Disabled | DoorType::VerticalDisabled | DoorType::Ai | DoorType::VerticalAi
+            => return Some("it can't be opened".to_string()),
+        DoorType::Solid | DoorType::VerticalSolid
+            => return Some("nothing opens it".to_string()),
+        // Which side opens is set per door and isn't known here, so assume it's the other one
+        _ if door_type.is_one_way()
+            => return Some("it may only open from the other side".to_string()),
+        _ => (),
+    }
+    door_type.required_pickups().into_iter()
+        .find(|pickup_type| !items.contains(pickup_type))
+        .map(|pickup_type| format!("{} isn't reachable before it", pickup_type.name()))
+}
+
+// Walk the rooms from the starting rooms, picking up every item in each room that is reached and
+// opening every door the items collected so far open. Doors that need nothing are tried before
+// doors that need an item, so an item only counts as a key item if there was no other way in at
+// that point. elevator_layout holds the destination of each elevator in ELEVATORS.
+pub fn walk(
+    room_pickups: &HashMap<u32, Vec<PickupType>>,
+    door_assignments: &[DoorAssignment],
+    elevator_layout: &[Elevator],
+    starting_room_ids: &[u32],
+    starting_items: &HashSet<PickupType>,
+) -> Reachability
+{
+    let mut reach = Reachability {
+        items: starting_items.clone(),
+        ..Reachability::default()
+    };
+
+    let elevators: Vec<_> = ELEVATORS.iter().zip(elevator_layout)
+        .map(|(elv, dest)| (elv.mrea, dest.mrea))
+        .collect();
+
+    let mut to_visit = starting_room_ids.to_vec();
+    loop {
+        while let Some(room_id) = to_visit.pop() {
+            if !reach.rooms.insert(room_id) {
+                continue;
+            }
+            if let Some(pickups) = room_pickups.get(&room_id) {
+                reach.items.extend(pickups.iter().cloned());
+            }
+            to_visit.extend(elevators.iter()
+                .filter(|(elv_room_id, _)| *elv_room_id == room_id)
+                .map(|(_, dest_room_id)| *dest_room_id));
+            to_visit.extend(door_assignments.iter()
+                .filter(|door| door.room_id == room_id && door.door_type.map(|t| t.required_pickups()).unwrap_or_default().is_empty())
+                .filter(|door| door_blocker(door.door_type, &reach.items).is_none())
+                .map(|door| door.destination_room_id));
+        }
+
+        // Everything open without items has been visited, so try the doors that need them
+        let door = door_assignments.iter()
+            .filter(|door| reach.rooms.contains(&door.room_id) && !reach.rooms.contains(&door.destination_room_id))
+            .find(|door| door_blocker(door.door_type, &reach.items).is_none());
+        match door {
+            Some(door) => {
+                reach.key_items.extend(door.door_type.map(|t| t.required_pickups()).unwrap_or_default());
+                to_visit.push(door.destination_room_id);
+            },
+            None => break,
+        }
+    }
+    reach
+}
+
+// The items that must be collected to make it through a layout: every door key item the walk had
+// to use to reach new rooms, and every reachable artifact, since they're all needed to open the
+// Impact Crater. The player starts in starting_room with no items.
+#[cfg(feature = "logic")]
+pub fn required_items_for_completion(
+    pickup_layout: &[PickupType],
+    door_assignments: &[DoorAssignment],
+    elevator_layout: &[Elevator],
+    starting_room: SpawnRoom,
+) -> HashSet<PickupType>
+{
+    let reach = walk(
+        &room_pickups(pickup_layout),
+        door_assignments,
+        elevator_layout,
+        &[starting_room.mrea],
+        &HashSet::new(),
+    );
+    let artifacts: Vec<_> = reach.items.iter().cloned().filter(|pickup_type| pickup_type.is_artifact()).collect();
+    reach.key_items.into_iter().chain(artifacts).collect()
+}
+
+#[cfg(test)]
+fn test_door(room_id: u32, door_type: Option<DoorType>, destination_room_id: u32) -> DoorAssignment
+{
+    DoorAssignment { room_id, dock_number: 0, door_type, destination_room_id }
+}
+
+#[test]
+fn test_walk()
+{
+    let room_pickups: HashMap<_, _> = [
+        (2, vec![PickupType::WaveBeam]),
+        (3, vec![PickupType::SuperMissile]),
+        (4, vec![PickupType::ArtifactOfTruth]),
+    ].iter().cloned().collect();
+    let doors = [
+        test_door(1, None, 2),
+        test_door(2, Some(DoorType::Purple), 3),
+        test_door(3, Some(DoorType::Blue), 1),
+        // The only Super Missiles are behind the door that needs them
+        test_door(1, Some(DoorType::Super), 4),
+    ];
+    let starting_items: HashSet<_> = [PickupType::Missile, PickupType::ChargeBeam].iter().cloned().collect();
+
+    let reach = walk(&room_pickups, &doors, &[], &[1], &starting_items);
+    let rooms: HashSet<_> = [1, 2, 3, 4].iter().cloned().collect();
+    assert_eq!(reach.rooms, rooms);
+    let key_items: HashSet<_> = [PickupType::WaveBeam, PickupType::Missile, PickupType::ChargeBeam, PickupType::SuperMissile]
+        .iter().cloned().collect();
+    assert_eq!(reach.key_items, key_items);
+
+    // Swap the items, so the Super Missiles are behind their own door
+    let room_pickups: HashMap<_, _> = [
+        (2, vec![PickupType::WaveBeam]),
+        (4, vec![PickupType::SuperMissile]),
+    ].iter().cloned().collect();
+    let reach = walk(&room_pickups, &doors, &[], &[1], &starting_items);
+    assert!(!reach.rooms.contains(&4));
+    assert!(!reach.items.contains(&PickupType::SuperMissile));
+    assert_eq!(
+        door_blocker(doors[3].door_type, &reach.items),
+        Some("Super Missile isn't reachable before it".to_string()),
+    );
+}
+
+#[test]
+fn test_walk_elevators()
+{
+    // Every elevator leads back to the room it is in, except the first one
+    let mut elevator_layout = ELEVATORS.to_vec();
+    elevator_layout[0] = ELEVATORS[5];
+    let room_pickups = HashMap::new();
+
+    let reach = walk(&room_pickups, &[], &elevator_layout, &[ELEVATORS[0].mrea], &HashSet::new());
+    let rooms: HashSet<_> = [ELEVATORS[0].mrea, ELEVATORS[5].mrea].iter().cloned().collect();
+    assert_eq!(reach.rooms, rooms);
+}
+
+#[cfg(feature = "logic")]
+#[test]
+fn test_required_items_for_completion()
+{
+    let start = SpawnRoom::landing_site_spawn_room();
+    let mut pickup_layout = vec![PickupType::Nothing; 100];
+    let room_pickups = room_pickups(&pickup_layout);
+    let (&item_room_id, _) = room_pickups.iter().find(|(room_id, _)| **room_id != start.mrea).unwrap();
+
+    // Whatever is in the room the door leads to, the Ice Beam is needed to get in
+    let doors = [test_door(start.mrea, Some(DoorType::White), item_room_id)];
+    let first_pickup_idx = pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|(_, rooms)| rooms.iter())
+        .take_while(|room_info| room_info.room_id != start.mrea)
+        .map(|room_info| room_info.pickup_locations.len())
+        .sum::<usize>();
+    pickup_layout[first_pickup_idx] = PickupType::IceBeam;
+    let item_idx = pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|(_, rooms)| rooms.iter())
+        .take_while(|room_info| room_info.room_id != item_room_id)
+        .map(|room_info| room_info.pickup_locations.len())
+        .sum::<usize>();
+    pickup_layout[item_idx] = PickupType::ArtifactOfChozo;
+
+    let required = required_items_for_completion(&pickup_layout, &doors, ELEVATORS, start);
+    let expected: HashSet<_> = [PickupType::IceBeam, PickupType::ArtifactOfChozo].iter().cloned().collect();
+    assert_eq!(required, expected);
+}
@@ -62,8 +62,32 @@ impl<'r, 'mlvl, 'cursor, 'list> MlvlArea<'r, 'mlvl, 'cursor, 'list>
         self.mrea_cursor.value().unwrap().kind.as_mrea_mut().unwrap()
     }
 
-    pub fn add_layer(&mut self, name: CStr<'r>)
+    pub fn layer_names(&self) -> &[CStr<'r>]
     {
+        &self.layer_names[..]
+    }
+
+    pub fn set_layer_name(&mut self, idx: usize, name: CStr<'r>) -> Result<(), String>
+    {
+        let layer_count = self.layer_names.len();
+        let mrea = self.mlvl_area.mrea;
+        let layer_name = self.layer_names.get_mut(idx)
+            .ok_or_else(|| format!("Layer {} doesn't exist, area 0x{:08X} only has {} layers",
+                                   idx, mrea, layer_count))?;
+        *layer_name = name;
+        Ok(())
+    }
+
+    pub fn add_layer(&mut self, name: CStr<'r>) -> Result<(), String>
+    {
+        // The layer flags are a 64 bit field, so any more layers can't be represented
+        if self.layer_flags.layer_count >= 64 {
+            return Err(format!(
+                "Cannot add layer {:?} to area 0x{:08X}, it already has the maximum of 64 layers",
+                name, self.mlvl_area.mrea,
+            ));
+        }
+
         // Mark this layer as active
         self.layer_flags.flags |= 1 << self.layer_flags.layer_count;
         self.layer_flags.layer_count += 1;
@@ -76,6 +100,7 @@ impl<'r, 'mlvl, 'cursor, 'list> MlvlArea<'r, 'mlvl, 'cursor, 'list>
         }
 
         self.mrea().scly_section_mut().layers.as_mut_vec().push(SclyLayer::new());
+        Ok(())
     }
 
     pub fn add_dependencies<I>(&mut self, pickup_resources: &HashMap<(u32, FourCC), Resource<'r>>,
@@ -83,7 +108,7 @@ impl<'r, 'mlvl, 'cursor, 'list> MlvlArea<'r, 'mlvl, 'cursor, 'list>
         where I: Iterator<Item=Dependency>,
     {
         let layers = self.mlvl_area.dependencies.deps.as_mut_vec();
-        let iter = deps.filter_map(|dep| {
+        let iter = sorted_dependencies(deps).into_iter().filter_map(|dep| {
                 if layers.iter().all(|layer| layer.iter().all(|i| *i != dep)) {
                     let res = pickup_resources[&(dep.asset_id, dep.asset_type)].clone();
                     layers[layer_num].as_mut_vec().push(dep);
@@ -95,3 +120,30 @@ impl<'r, 'mlvl, 'cursor, 'list> MlvlArea<'r, 'mlvl, 'cursor, 'list>
         self.mrea_cursor.insert_after(iter);
     }
 }
+
+// The new resources are inserted into the pak in this order, so sort them to keep the output
+// independent of the order the caller happened to produce them in (e.g. from a HashMap).
+fn sorted_dependencies<I>(deps: I) -> Vec<Dependency>
+    where I: Iterator<Item=Dependency>,
+{
+    let mut deps: Vec<_> = deps.collect();
+    deps.sort_by_key(|dep| (dep.asset_id, dep.asset_type));
+    deps.dedup();
+    deps
+}
+
+#[test]
+fn test_sorted_dependencies()
+{
+    let dep = |asset_id, asset_type: &[u8; 4]| Dependency {
+        asset_id,
+        asset_type: FourCC::from_bytes(asset_type),
+    };
+    let a = vec![dep(3, b"TXTR"), dep(1, b"CMDL"), dep(2, b"STRG"), dep(1, b"ANCS"), dep(3, b"TXTR")];
+    let mut b = a.clone();
+    b.reverse();
+
+    let sorted = sorted_dependencies(a.into_iter());
+    assert_eq!(sorted, sorted_dependencies(b.into_iter()));
+    assert_eq!(sorted, vec![dep(1, b"ANCS"), dep(1, b"CMDL"), dep(2, b"STRG"), dep(3, b"TXTR")]);
+}
@@ -30,11 +30,20 @@ pub struct PrimePatcher<'r, 's>
     // TODO: Come up with a better data structure for this. A per PAK list of patches, for example.
     resource_patches: Vec<(ResourceKey<'s>, Box<dyn FnMut(&mut Resource<'r>) -> Result<(), String> + 's>)>,
     scly_patches: Vec<(MreaKey<'s>, Vec<Box<SclyPatch<'r, 's>>>)>,
+    protected_assets: HashSet<(u32, FourCC)>,
+    custom_pickup_aabbs: HashMap<u32, [f32; 6]>,
 }
 
+// Instance ids handed out to objects the patches add
+pub const FIRST_FRESH_INSTANCE_ID: u32 = 0xDEADBABE;
+
 pub struct PatcherState
 {
     pub fresh_instance_id_range: RangeFrom<u32>,
+    // AABBs of pickup models that aren't in pickup_meta's table
+    pub custom_pickup_aabbs: HashMap<u32, [f32; 6]>,
+    // Problems a patch worked around instead of failing, returned by run
+    pub warnings: Vec<String>,
 }
 
 impl<'r, 's> PrimePatcher<'r, 's>
@@ -45,9 +54,45 @@ impl<'r, 's> PrimePatcher<'r, 's>
             file_patches: HashMap::new(),
             resource_patches: Vec::new(),
             scly_patches: Vec::new(),
+            protected_assets: HashSet::new(),
+            custom_pickup_aabbs: HashMap::new(),
         }
     }
 
+    // Assets that must be left untouched, e.g. because another mod relies on them.
+    // Patching any of them makes run fail before anything is modified.
+    pub fn protect_assets<I>(&mut self, assets: I)
+        where I: IntoIterator<Item = (u32, FourCC)>
+    {
+        self.protected_assets.extend(assets);
+    }
+
+    // The AABBs of pickup models that aren't in pickup_meta's table, so pickups using them can be
+    // centered
+    pub fn add_custom_pickup_aabbs<I>(&mut self, aabbs: I)
+        where I: IntoIterator<Item = (u32, [f32; 6])>
+    {
+        self.custom_pickup_aabbs.extend(aabbs);
+    }
+
+    fn protected_asset_conflicts(&self) -> Vec<String>
+    {
+        let resource_keys = self.resource_patches.iter()
+            .map(|(key, _)| (key.pak_name, key.id, key.kind));
+        let mrea_keys = self.scly_patches.iter()
+            .map(|(key, _)| (key.pak_name, key.room_id, b"MREA".into()));
+
+        let mut conflicts: Vec<_> = resource_keys.chain(mrea_keys)
+            .filter(|&(_, id, kind)| self.protected_assets.contains(&(id, kind)))
+            .map(|(pak_name, id, kind)| {
+                format!("0x{:08X}.{} in {}", id, kind, String::from_utf8_lossy(pak_name))
+            })
+            .collect();
+        conflicts.sort();
+        conflicts.dedup();
+        conflicts
+    }
+
     pub fn add_file_patch<F>(&mut self, name: &'s [u8], f: F)
         where F: FnMut(&mut FstEntryFile<'r>) -> Result<(), String> + 's
     {
@@ -82,10 +127,17 @@ impl<'r, 's> PrimePatcher<'r, 's>
         }
     }
 
-    pub fn run(&mut self, gc_disc: &mut GcDisc<'r>) -> Result<(), String>
+    pub fn run(&mut self, gc_disc: &mut GcDisc<'r>) -> Result<Vec<String>, String>
     {
+        let conflicts = self.protected_asset_conflicts();
+        if !conflicts.is_empty() {
+            return Err(format!("Refusing to modify protected assets: {}", conflicts.join(", ")));
+        }
+
         let mut patcher_state = PatcherState {
-            fresh_instance_id_range: 0xDEADBABE..
+            fresh_instance_id_range: FIRST_FRESH_INSTANCE_ID..,
+            custom_pickup_aabbs: self.custom_pickup_aabbs.clone(),
+            warnings: Vec::new(),
         };
 
         let files_to_patch = self.file_patches.keys()
@@ -172,6 +224,6 @@ impl<'r, 's> PrimePatcher<'r, 's>
                 }
             }
         }
-        Ok(())
+        Ok(patcher_state.warnings)
     }
 }
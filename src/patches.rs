@@ -12,20 +12,25 @@ use encoding::{
     Encoding,
     EncoderTrap,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     custom_asset_ids,
     dol_patcher::DolPatcher,
-    ciso_writer::CisoWriter,
-    elevators::{ELEVATORS, Elevator, SpawnRoom},
-    gcz_writer::GczWriter,
+    ciso_writer::{self, CisoWriter},
+    rvz_writer::RvzWriter,
+    elevators::{
+        ELEVATORS, Elevator, SpawnRoom,
+        elevator_control_text, elevator_hologram_text, elevator_room_text,
+    },
+    gcz_writer::{self, GczWriter, WrittenRegion},
+    logic::{self, DoorAssignment},
     memmap,
     mlvl_wrapper,
     pickup_meta::{self, PickupType},
-    door_meta::{DoorType, BlastShieldType, DoorLocation, Weights, World},
+    door_meta::{DoorType, BlastShieldType, DoorLocation, CustomDoorDef, Weights, World},
     reader_writer,
-    patcher::{PatcherState, PrimePatcher},
+    patcher::{PatcherState, PrimePatcher, FIRST_FRESH_INSTANCE_ID},
     structs,
     GcDiscLookupExtensions,
     ResourceData,
@@ -46,13 +51,15 @@ use reader_writer::{
 
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::CString,
     fmt,
-    fs::File,
-    io::Write,
+    fs::{self, File},
+    io::{Cursor, Seek, Write},
     iter,
     mem,
+    panic,
+    path::{Path, PathBuf},
 };
 
 #[derive(Deserialize, Debug, Clone, Copy)]
@@ -84,15 +91,99 @@ pub struct AdditionalItem {
     position: Xyz,
 }
 
+// Opens a door (usually a "solid" one) when an object already in the same room sends a
+// connection in the given state, e.g. 9 (Zero) for a relay or counter, 3 (Entered) for a trigger
+#[derive(Deserialize, Debug)]
+pub struct DoorOpener {
+    room: String,
+    dock: u32,
+    object_id: u32,
+    state: u32,
+}
+
 const ARTIFACT_OF_TRUTH_REQ_LAYER: u32 = 24;
 const ALWAYS_MODAL_HUDMENUS: &[usize] = &[23, 50, 63];
 
 
+// Search every pak for the resources in `looking_for`, with one thread per pak. The results are in
+// the same order as PICKUP_LOCATIONS, so merging them picks the same copy of a resource that
+// appears in several paks as searching the paks one after the other would.
+fn find_resources_in_paks<'r>(
+    gc_disc: &structs::GcDisc<'r>,
+    looking_for: &HashSet<(u32, FourCC)>,
+) -> Vec<HashMap<(u32, FourCC), structs::Resource<'r>>>
+{
+    // The disc itself can't be shared between threads, so look the paks up first
+    let paks: Vec<_> = pickup_meta::PICKUP_LOCATIONS.iter()
+        .map(|(pak_name, _)| {
+            let file_entry = gc_disc.find_file(pak_name).unwrap();
+            match *file_entry.file().unwrap() {
+                structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+                structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+                _ => panic!(),
+            }
+        })
+        .collect();
+
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = paks.iter()
+            .map(|pak| scope.spawn(move || {
+                let mut found = HashMap::new();
+                for res in pak.resources.iter() {
+                    let key = (res.file_id, res.fourcc());
+                    if looking_for.contains(&key) {
+                        found.entry(key).or_insert_with(|| res.into_owned());
+                    }
+                }
+                found
+            }))
+            .collect();
+        threads.into_iter()
+            .map(|thread| thread.join().unwrap_or_else(|err| panic::resume_unwind(err)))
+            .collect()
+    })
+}
+
+fn merge_pak_resources<'r>(
+    found: &mut HashMap<(u32, FourCC), structs::Resource<'r>>,
+    looking_for: &mut HashSet<(u32, FourCC)>,
+    pak_resources: Vec<HashMap<(u32, FourCC), structs::Resource<'r>>>,
+)
+{
+    for resources in pak_resources {
+        for (key, res) in resources {
+            if looking_for.remove(&key) {
+                assert!(found.insert(key, res).is_none());
+            }
+        }
+    }
+}
+
+// Assets supplied in the config replace any built-in or vanilla asset with the same id, so they
+// are put in the pool before the paks are searched and before anything is generated
+fn add_user_assets<'r>(
+    found: &mut HashMap<(u32, FourCC), structs::Resource<'r>>,
+    looking_for: &mut HashSet<(u32, FourCC)>,
+    user_assets: &[(u32, FourCC, Vec<u8>)],
+)
+{
+    for &(file_id, fourcc, ref bytes) in user_assets {
+        let mut bytes = bytes.clone();
+        let len = bytes.len();
+        bytes.extend(reader_writer::pad_bytes(32, len).iter());
+        looking_for.remove(&(file_id, fourcc));
+        found.insert(
+            (file_id, fourcc),
+            pickup_meta::build_resource(file_id, structs::ResourceKind::External(bytes, fourcc)),
+        );
+    }
+}
+
 // When changing a pickup, we need to give the room a copy of the resources/
 // assests used by the pickup. Create a cache of all the resources needed by
 // any pickup.
-fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>)
-    -> HashMap<(u32, FourCC), structs::Resource<'r>>
+fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>, user_assets: &[(u32, FourCC, Vec<u8>)])
+    -> Result<HashMap<(u32, FourCC), structs::Resource<'r>>, String>
 {
     // Get list of all dependencies patcher needs //
     let mut looking_for: HashSet<_> = PickupType::iter()
@@ -111,27 +202,11 @@ fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         looking_for.remove(&(res.file_id, res.fourcc()));
         assert!(found.insert((res.file_id, res.fourcc()), res.clone()).is_none());
     }
+    add_user_assets(&mut found, &mut looking_for, user_assets);
 
-    // Iterate through all paks //
-    for pak_name in pickup_meta::PICKUP_LOCATIONS.iter().map(|(name, _)| name) {
-
-        // Get pak //
-        let file_entry = gc_disc.find_file(pak_name).unwrap();
-        let pak = match *file_entry.file().unwrap() {
-            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
-            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
-            _ => panic!(),
-        };
-
-        // Iterate through all resources in pak //
-        for res in pak.resources.iter() {
-            // If this resource is a dependency needed by the patcher, add the resource to the output list //
-            let key = (res.file_id, res.fourcc());
-            if looking_for.remove(&key) {
-                assert!(found.insert(key, res.into_owned()).is_none());
-            }
-        }
-    }
+    // Search all paks and add any dependencies to the output list //
+    let pak_resources = find_resources_in_paks(gc_disc, &looking_for);
+    merge_pak_resources(&mut found, &mut looking_for, pak_resources);
 
     // Finally, we need to add the assets which are generated rather than read from a file locally //
     
@@ -143,14 +218,14 @@ fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         custom_asset_ids::NOTHING_ANCS,
         custom_asset_ids::NOTHING_TXTR,
         custom_asset_ids::PHAZON_SUIT_TXTR2,
-    ));
+    )?);
     new_assets.extend_from_slice(&create_suit_icon_cmdl_and_ancs(
         &found,
         custom_asset_ids::PHAZON_SUIT_CMDL,
         custom_asset_ids::PHAZON_SUIT_ANCS,
         custom_asset_ids::PHAZON_SUIT_TXTR1,
         custom_asset_ids::PHAZON_SUIT_TXTR2,
-    ));
+    )?);
     new_assets.extend_from_slice(&create_item_scan_strg_pair(
         custom_asset_ids::PHAZON_SUIT_SCAN,
         custom_asset_ids::PHAZON_SUIT_STRG,
@@ -188,7 +263,7 @@ fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         custom_asset_ids::SHINY_MISSILE_SCAN_STRG,
         "Shiny Missile\0",
     ));
-    new_assets.extend_from_slice(&create_shiny_missile_assets(&found));
+    new_assets.extend_from_slice(&create_shiny_missile_assets(&found)?);
     new_assets.push(pickup_meta::build_resource(
         custom_asset_ids::SHINY_MISSILE_ACQUIRED_HUDMEMO_STRG,
         structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
@@ -204,14 +279,21 @@ fn collect_pickup_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         }
     }
 
-    assert!(looking_for.is_empty());
+    check_resources_found(&looking_for)?;
 
-    if !looking_for.is_empty()
-    {
-        println!("error - still looking for {:?}", looking_for);
-    }
+    Ok(found)
+}
 
-    found
+// Fails with every resource a resource cache was looking for but couldn't find on the disc
+fn check_resources_found(looking_for: &HashSet<(u32, FourCC)>) -> Result<(), String>
+{
+    if looking_for.is_empty() {
+        return Ok(());
+    }
+    let mut missing: Vec<_> = looking_for.iter().collect();
+    missing.sort();
+    let missing: Vec<_> = missing.iter().map(|(file_id, fourcc)| format!("0x{:08X}.{}", file_id, fourcc)).collect();
+    Err(format!("Failed to find these resources on the disc: {}", missing.join(", ")))
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -567,7 +649,8 @@ impl WaterType
 }
 
 fn collect_liquid_resources<'r>(gc_disc: &structs::GcDisc<'r>)
--> HashMap<(u32, FourCC), structs::Resource<'r>>{
+    -> Result<HashMap<(u32, FourCC), structs::Resource<'r>>, String>
+{
     // Get list of all dependencies needed by liquids //
     let mut looking_for: HashSet<_> = WaterType::iter()
         .flat_map(|pt| pt.dependencies().into_iter())
@@ -580,7 +663,8 @@ fn collect_liquid_resources<'r>(gc_disc: &structs::GcDisc<'r>)
     for pak_name in pickup_meta::PICKUP_LOCATIONS.iter().map(|(name, _)| name) { // for all paks
 
         // get the pak //
-        let file_entry = gc_disc.find_file(pak_name).unwrap();
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("The disc has no {}", pak_name))?;
         let pak = match *file_entry.file().unwrap() {
             structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
             structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
@@ -596,18 +680,14 @@ fn collect_liquid_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         }
     }
 
-    if !looking_for.is_empty()
-    {
-        println!("error - still looking for {:?}", looking_for);
-    }
-    assert!(looking_for.is_empty());
-    found
+    check_resources_found(&looking_for)?;
+    Ok(found)
 }
 
 // Door assets are not shared across all areas either,
 // so we have to make a cache for them as well.
-fn collect_door_resources<'r>(gc_disc: &structs::GcDisc<'r>)
-    -> HashMap<(u32, FourCC), structs::Resource<'r>>
+fn collect_door_resources<'r>(gc_disc: &structs::GcDisc<'r>, user_assets: &[(u32, FourCC, Vec<u8>)])
+    -> Result<HashMap<(u32, FourCC), structs::Resource<'r>>, String>
 {   
     // Get list of all dependencies needed by custom doors //
     
@@ -641,33 +721,29 @@ fn collect_door_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         looking_for.remove(&(res.file_id, res.fourcc()));
         assert!(found.insert((res.file_id, res.fourcc()), res.clone()).is_none());
     }
+    add_user_assets(&mut found, &mut looking_for, user_assets);
 
-    // Iterate through all paks and add add any dependencies to the resource pool //
-    for pak_name in pickup_meta::PICKUP_LOCATIONS.iter().map(|(name, _)| name) { // for all paks
-
-        // get the pak //
-        let file_entry = gc_disc.find_file(pak_name).unwrap();
-        let pak = match *file_entry.file().unwrap() {
-            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
-            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
-            _ => panic!(),
-        };
-
-        // Iterate through all resources in the pak //
-        for res in pak.resources.iter() {
+    // Blast shield scans are generated rather than read from a pak, unless an extra asset //
+    // already replaced them                                                               //
+    for shield_type in BlastShieldType::iter() {
+        for res in create_item_scan_strg_pair(shield_type.scan(), shield_type.strg(), shield_type.scan_text()) {
             let key = (res.file_id, res.fourcc());
-            if looking_for.remove(&key) { // If it's one of our dependencies
-                assert!(found.insert(key, res.into_owned()).is_none()); // collect it
+            if looking_for.remove(&key) {
+                found.insert(key, res);
             }
         }
     }
 
+    // Search all paks and add any dependencies to the resource pool //
+    let pak_resources = find_resources_in_paks(gc_disc, &looking_for);
+    merge_pak_resources(&mut found, &mut looking_for, pak_resources);
+
     // Generate custom assets (new door variants) //
     let mut new_assets = vec![];
 
     for door_type in DoorType::iter() {
         if door_type.shield_cmdl() >= 0xDEAF0000 {
-            new_assets.push(create_custom_door_cmdl(&found, door_type));
+            new_assets.push(create_custom_door_cmdl(&found, door_type)?);
         }
     }
 
@@ -679,21 +755,160 @@ fn collect_door_resources<'r>(gc_disc: &structs::GcDisc<'r>)
         }
     }
 
-    if !looking_for.is_empty()
+    check_resources_found(&looking_for)?;
+
+    Ok(found)
+}
+
+// A door color registered from a CustomDoorDef
+#[derive(Clone, Debug)]
+struct CustomDoorType
+{
+    cmdl: u32,
+    vertical_cmdl: Option<u32>,
+    txtr: u32,
+    vulnerability: structs::structs::DamageVulnerability,
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> u32
+{
+    u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+// The reader trusts the section table of a CMDL, so check that every section it lists fits in the
+// user's bytes before reading them
+fn check_custom_door_cmdl(name: &str, cmdl_bytes: &[u8]) -> Result<(), String>
+{
+    if cmdl_bytes.len() < 0x2C || cmdl_bytes[0..8] != [0xDE, 0xAD, 0xBA, 0xBE, 0, 0, 0, 2] {
+        Err(format!("Custom door type '{}' has an invalid CMDL header", name))?
+    }
+    let data_section_count = be_u32(cmdl_bytes, 0x24) as usize;
+    let material_set_count = be_u32(cmdl_bytes, 0x28) as usize;
+    let sizes_end = 0x2C + data_section_count * 4;
+    if material_set_count > data_section_count || sizes_end > cmdl_bytes.len() {
+        Err(format!("Custom door type '{}' has an invalid CMDL section table", name))?
+    }
+
+    let mut offset = sizes_end + reader_writer::pad_bytes_count(32, sizes_end);
+    for i in 0..data_section_count {
+        let size = be_u32(cmdl_bytes, 0x2C + i * 4) as usize;
+        if offset + size > cmdl_bytes.len() {
+            Err(format!("Custom door type '{}' has a truncated CMDL", name))?
+        }
+        // A material set starts with its texture count and texture ids
+        if i < material_set_count && (size < 4 || be_u32(cmdl_bytes, offset) as usize * 4 + 4 > size) {
+            Err(format!("Custom door type '{}' has an invalid CMDL material set", name))?
+        }
+        offset += size;
+    }
+    Ok(())
+}
+
+// Check a user supplied door CMDL and point its rim at the door's texture, like
+// create_custom_door_cmdl does
+fn custom_door_cmdl_bytes(name: &str, cmdl_bytes: &[u8], txtr_id: u32) -> Result<Vec<u8>, String>
+{
+    check_custom_door_cmdl(name, cmdl_bytes)?;
+    let mut cmdl: structs::Cmdl = Reader::new(cmdl_bytes).read(());
     {
-        println!("error - still looking for {:?}", looking_for);
+        let material_sets = cmdl.material_sets.as_mut_vec();
+        let texture_ids = material_sets.get_mut(0)
+            .map(|material_set| material_set.texture_ids.as_mut_vec())
+            .filter(|texture_ids| !texture_ids.is_empty())
+            .ok_or_else(|| format!("Custom door type '{}' has a CMDL without textures", name))?;
+        texture_ids[0] = txtr_id;
     }
 
-    assert!(looking_for.is_empty());
+    let mut new_cmdl_bytes = vec![];
+    cmdl.write_to(&mut new_cmdl_bytes).unwrap();
+    let len = new_cmdl_bytes.len();
+    new_cmdl_bytes.extend(reader_writer::pad_bytes(32, len).iter());
+    Ok(new_cmdl_bytes)
+}
+
+// Validate the user's door assets, give them fresh ids and add them to the door resource pool
+fn add_custom_door_types<'r>(
+    door_resources: &mut HashMap<(u32, FourCC), structs::Resource<'r>>,
+    custom_door_types: &[CustomDoorDef],
+) -> Result<HashMap<String, CustomDoorType>, String>
+{
+    // Each door gets a horizontal CMDL, a vertical CMDL and a TXTR id
+    let mut ids = custom_asset_ids::CUSTOM_DOOR_START..custom_asset_ids::CUSTOM_DOOR_END;
+    let mut custom_doors = HashMap::new();
+    for def in custom_door_types {
+        let name = def.name.to_lowercase();
+        if name == "random" || name == "default" || DoorType::from_string(name.clone()).is_some() ||
+            BlastShieldType::from_door_specification(&name).is_some()
+        {
+            Err(format!("Custom door type '{}' conflicts with a built-in door type", def.name))?
+        }
+        if custom_doors.contains_key(&name) {
+            Err(format!("Custom door type '{}' is defined more than once", def.name))?
+        }
 
-    found
+        let (cmdl_id, vertical_cmdl_id, txtr_id) = match (ids.next(), ids.next(), ids.next()) {
+            (Some(cmdl_id), Some(vertical_cmdl_id), Some(txtr_id)) => (cmdl_id, vertical_cmdl_id, txtr_id),
+            _ => Err(format!("Too many custom door types, at most {} are supported",
+                (custom_asset_ids::CUSTOM_DOOR_END - custom_asset_ids::CUSTOM_DOOR_START) / 3))?,
+        };
+
+        // Check the header before handing the bytes to the reader
+        let txtr_bytes = &def.txtr_bytes[..];
+        if txtr_bytes.len() < 12 {
+            Err(format!("Custom door type '{}' has a truncated TXTR", def.name))?
+        }
+        let format = u32::from_be_bytes([txtr_bytes[0], txtr_bytes[1], txtr_bytes[2], txtr_bytes[3]]);
+        let width = u16::from_be_bytes([txtr_bytes[4], txtr_bytes[5]]);
+        let height = u16::from_be_bytes([txtr_bytes[6], txtr_bytes[7]]);
+        // Bits per pixel of each of the GX texture formats
+        let bits_per_pixel = match [4, 8, 8, 16, 4, 8, 16, 16, 16, 32, 4].get(format as usize) {
+            Some(&bits) if width != 0 && height != 0 => bits,
+            _ => Err(format!("Custom door type '{}' has an invalid TXTR header", def.name))?,
+        };
+        if 12 + width as usize * height as usize * bits_per_pixel / 8 > txtr_bytes.len() {
+            Err(format!("Custom door type '{}' has a truncated TXTR", def.name))?
+        }
+
+        let new_cmdl_bytes = custom_door_cmdl_bytes(&def.name, &def.cmdl_bytes, txtr_id)?;
+        let new_vertical_cmdl_bytes = def.vertical_cmdl_bytes.as_ref()
+            .map(|cmdl_bytes| custom_door_cmdl_bytes(&def.name, cmdl_bytes, txtr_id))
+            .transpose()?;
+
+        let mut new_txtr_bytes = txtr_bytes.to_vec();
+        let len = new_txtr_bytes.len();
+        new_txtr_bytes.extend(reader_writer::pad_bytes(32, len).iter());
+
+        door_resources.insert(
+            (cmdl_id, b"CMDL".into()),
+            pickup_meta::build_resource(cmdl_id, structs::ResourceKind::External(new_cmdl_bytes, b"CMDL".into())),
+        );
+        if let Some(new_vertical_cmdl_bytes) = new_vertical_cmdl_bytes {
+            door_resources.insert(
+                (vertical_cmdl_id, b"CMDL".into()),
+                pickup_meta::build_resource(vertical_cmdl_id, structs::ResourceKind::External(new_vertical_cmdl_bytes, b"CMDL".into())),
+            );
+        }
+        door_resources.insert(
+            (txtr_id, b"TXTR".into()),
+            pickup_meta::build_resource(txtr_id, structs::ResourceKind::External(new_txtr_bytes, b"TXTR".into())),
+        );
+
+        custom_doors.insert(name, CustomDoorType {
+            cmdl: cmdl_id,
+            vertical_cmdl: def.vertical_cmdl_bytes.as_ref().map(|_| vertical_cmdl_id),
+            txtr: txtr_id,
+            vulnerability: def.vulnerability.clone(),
+        });
+    }
+
+    Ok(custom_doors)
 }
 
 fn create_custom_door_cmdl<'r>(
     resources: &HashMap<(u32, FourCC),
     structs::Resource<'r>>,
     door_type: DoorType,
-) -> structs::Resource<'r>
+) -> Result<structs::Resource<'r>, String>
 {
     let new_cmdl_id: u32 = door_type.shield_cmdl();
     let new_txtr_id: u32 = door_type.holorim_texture();
@@ -709,7 +924,7 @@ fn create_custom_door_cmdl<'r>(
         };
 
         // Deserialize the blue door CMDL into a new mutable CMDL
-        let blue_door_cmdl_bytes = blue_door_cmdl.decompress().into_owned();
+        let blue_door_cmdl_bytes = blue_door_cmdl.decompress()?.into_owned();
         let mut new_cmdl = Reader::new(&blue_door_cmdl_bytes[..]).read::<structs::Cmdl>(());
         
         // Modify the new CMDL to make it unique
@@ -719,18 +934,15 @@ fn create_custom_door_cmdl<'r>(
         let mut new_cmdl_bytes = vec![];
         new_cmdl.write_to(&mut new_cmdl_bytes).unwrap();
 
-        // Pad length to multiple of 32 bytes //
-        let len = new_cmdl_bytes.len();
-        new_cmdl_bytes.extend(reader_writer::pad_bytes(32, len).iter());
-
-        // Assemble into a proper resource object
-        pickup_meta::build_resource(
+        // Assemble into a proper resource object, compressed like the pak's own CMDLs
+        pickup_meta::build_compressed_resource(
             new_cmdl_id, // Custom ids start with 0xDEAFxxxx
-            structs::ResourceKind::External(new_cmdl_bytes, b"CMDL".into())
+            &new_cmdl_bytes,
+            b"CMDL".into(),
         )
     };
     
-    new_door_cmdl
+    Ok(new_door_cmdl)
 }
 
 fn create_suit_icon_cmdl_and_ancs<'r>(
@@ -740,13 +952,13 @@ fn create_suit_icon_cmdl_and_ancs<'r>(
     new_ancs_id: u32,
     new_txtr1: u32,
     new_txtr2: u32,
-) -> [structs::Resource<'r>; 2]
+) -> Result<[structs::Resource<'r>; 2], String>
 {
     let new_suit_cmdl = {
         let grav_suit_cmdl = ResourceData::new(
             &resources[&resource_info!("Node1_11.CMDL").into()]
         );
-        let cmdl_bytes = grav_suit_cmdl.decompress().into_owned();
+        let cmdl_bytes = grav_suit_cmdl.decompress()?.into_owned();
         let mut cmdl = Reader::new(&cmdl_bytes[..]).read::<structs::Cmdl>(());
 
         cmdl.material_sets.as_mut_vec()[0].texture_ids.as_mut_vec()[0] = new_txtr1;
@@ -768,7 +980,7 @@ fn create_suit_icon_cmdl_and_ancs<'r>(
         let grav_suit_ancs = ResourceData::new(
             &resources[&resource_info!("Node1_11.ANCS").into()]
         );
-        let ancs_bytes = grav_suit_ancs.decompress().into_owned();
+        let ancs_bytes = grav_suit_ancs.decompress()?.into_owned();
         let mut ancs = Reader::new(&ancs_bytes[..]).read::<structs::Ancs>(());
 
         ancs.char_set.char_info.as_mut_vec()[0].cmdl = new_cmdl_id;
@@ -785,18 +997,18 @@ fn create_suit_icon_cmdl_and_ancs<'r>(
             structs::ResourceKind::External(new_ancs_bytes, b"ANCS".into())
         )
     };
-    [new_suit_cmdl, new_suit_ancs]
+    Ok([new_suit_cmdl, new_suit_ancs])
 }
 
 fn create_shiny_missile_assets<'r>(
     resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
-) -> [structs::Resource<'r>; 4]
+) -> Result<[structs::Resource<'r>; 4], String>
 {
     let shiny_missile_cmdl = {
         let shiny_missile_cmdl = ResourceData::new(
             &resources[&resource_info!("Node1_36_0.CMDL").into()]
         );
-        let cmdl_bytes = shiny_missile_cmdl.decompress().into_owned();
+        let cmdl_bytes = shiny_missile_cmdl.decompress()?.into_owned();
         let mut cmdl = Reader::new(&cmdl_bytes[..]).read::<structs::Cmdl>(());
 
         // println!("{:#?}", cmdl);
@@ -822,7 +1034,7 @@ fn create_shiny_missile_assets<'r>(
         let shiny_missile_ancs = ResourceData::new(
             &resources[&resource_info!("Node1_37_0.ANCS").into()]
         );
-        let ancs_bytes = shiny_missile_ancs.decompress().into_owned();
+        let ancs_bytes = shiny_missile_ancs.decompress()?.into_owned();
         let mut ancs = Reader::new(&ancs_bytes[..]).read::<structs::Ancs>(());
 
         ancs.char_set.char_info.as_mut_vec()[0].cmdl = custom_asset_ids::SHINY_MISSILE_CMDL;
@@ -871,7 +1083,7 @@ fn create_shiny_missile_assets<'r>(
         let shiny_missile_anim = ResourceData::new(
             &resources[&resource_info!("Missile_Launcher_ready.ANIM").into()]
         );
-        let mut anim_bytes = shiny_missile_anim.decompress().into_owned();
+        let mut anim_bytes = shiny_missile_anim.decompress()?.into_owned();
         custom_asset_ids::SHINY_MISSILE_EVNT.write_to(&mut std::io::Cursor::new(&mut anim_bytes[8..])).unwrap();
         let len = anim_bytes.len();
         anim_bytes.extend(reader_writer::pad_bytes(32, len).iter());
@@ -880,7 +1092,7 @@ fn create_shiny_missile_assets<'r>(
             structs::ResourceKind::External(anim_bytes, b"ANIM".into())
         )
     };
-    [shiny_missile_cmdl, shiny_missile_ancs, shiny_missile_evnt, shiny_missile_anim]
+    Ok([shiny_missile_cmdl, shiny_missile_ancs, shiny_missile_evnt, shiny_missile_anim])
 }
 
 fn create_item_scan_strg_pair<'r>(
@@ -977,6 +1189,222 @@ fn add_skip_hudmemos_strgs(pickup_resources: &mut HashMap<(u32, FourCC), structs
     }
 }
 
+// Obfuscated pickups already use the "???" scan from the Nothing item, so only the hudmemo is left
+fn add_obfuscated_hudmemo_strg(pickup_resources: &mut HashMap<(u32, FourCC), structs::Resource>)
+{
+    let id = custom_asset_ids::OBFUSCATED_ACQUIRED_HUDMEMO_STRG;
+    let res = pickup_meta::build_resource(
+        id,
+        structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+            "&just=center;??? acquired!\u{0}".to_owned(),
+        ])),
+    );
+    assert!(pickup_resources.insert((id, b"STRG".into()), res).is_none())
+}
+
+fn add_starting_items_scan(
+    pickup_resources: &mut HashMap<(u32, FourCC), structs::Resource>,
+    starting_items: u64,
+)
+{
+    let mut contents = "Starting items:".to_owned();
+    for pickup_type in PickupType::iter() {
+        let count = starting_item_count(starting_items, pickup_type);
+        if count == 1 {
+            contents += &format!("\n{}", pickup_type.name());
+        } else if count > 1 {
+            contents += &format!("\n{} x{}", pickup_type.name(), count);
+        }
+    }
+    contents.push('\0');
+
+    let resources = create_item_scan_strg_pair(
+        custom_asset_ids::STARTING_ITEMS_SCAN,
+        custom_asset_ids::STARTING_ITEMS_STRG,
+        &contents,
+    );
+    for res in resources.iter() {
+        assert!(pickup_resources.insert((res.file_id, res.fourcc()), res.clone()).is_none());
+    }
+}
+
+// Put a scan point listing the starting items where the player spawns
+fn patch_starting_items_scan<'r>(
+    ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    pickup_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+) -> Result<(), String>
+{
+    let deps = [
+        (custom_asset_ids::STARTING_ITEMS_SCAN, b"SCAN"),
+        (custom_asset_ids::STARTING_ITEMS_STRG, b"STRG"),
+    ];
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: fourcc.into(),
+        });
+    area.add_dependencies(pickup_resources, 0, deps_iter);
+
+    let scly = area.mrea().scly_section_mut();
+    let spawn_position = scly.layers.iter_mut()
+        .find_map(|layer| layer.objects.iter_mut()
+            .find_map(|obj| obj.property_data.as_spawn_point_mut().map(|sp| sp.position))
+        )
+        .ok_or_else(|| "Starting room has no spawn point to place the starting items scan".to_string())?;
+
+    let layer = &mut scly.layers.as_mut_vec()[0];
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: ps.fresh_instance_id_range.next().unwrap(),
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::PointOfInterest(
+            structs::PointOfInterest {
+                name: b"Starting items scan\0".as_cstr(),
+                position: [spawn_position[0], spawn_position[1], spawn_position[2] + 1.0].into(),
+                rotation: [0.0, 0.0, 0.0].into(),
+                active: 1,
+                scan_param: structs::structs::ScannableParameters {
+                    scan: custom_asset_ids::STARTING_ITEMS_SCAN,
+                },
+                unknown1: 0.0,
+            }
+        ),
+    });
+    Ok(())
+}
+
+struct DestructibleScan
+{
+    room: ResourceInfo,
+    layer: usize,
+    // The destructible platform
+    instance_id: u32,
+    // An existing point of interest for the platform, given the scan if it doesn't have one yet
+    scan_target: Option<u32>,
+    description: &'static str,
+}
+
+// The destructibles scan_known_destructibles adds a scan to. Only these have been looked up so far.
+const DESTRUCTIBLE_SCANS: &[DestructibleScan] = &[
+    DestructibleScan {
+        room: resource_info!("13_over_burningeffigy.MREA"), // Geothermal Core
+        layer: 0,
+        instance_id: 0x1403AE,
+        scan_target: Some(0x1403B4),
+        description: "Brittle rock formation.\nDestroying it clears a path through the Geothermal Core.\0",
+    },
+    DestructibleScan {
+        room: resource_info!("04_mines_pillar.MREA"), // Ore Processing
+        layer: 0,
+        instance_id: 0x60372,
+        scan_target: Some(0x60378),
+        description: "Brittle rock formation.\nDestroying it clears a path up through Ore Processing.\0",
+    },
+];
+
+fn destructible_scan_ids(idx: usize) -> (u32, u32)
+{
+    let scan = custom_asset_ids::DESTRUCTIBLE_SCAN_START + idx as u32 * 2;
+    (scan, scan + 1)
+}
+
+fn add_destructible_scans(pickup_resources: &mut HashMap<(u32, FourCC), structs::Resource>)
+{
+    assert!(destructible_scan_ids(DESTRUCTIBLE_SCANS.len()).0 <= custom_asset_ids::DESTRUCTIBLE_SCAN_END);
+    for (idx, destructible) in DESTRUCTIBLE_SCANS.iter().enumerate() {
+        let (scan, strg) = destructible_scan_ids(idx);
+        for res in create_item_scan_strg_pair(scan, strg, destructible.description).iter() {
+            assert!(pickup_resources.insert((res.file_id, res.fourcc()), res.clone()).is_none());
+        }
+    }
+}
+
+fn patch_destructible_scan<'r>(
+    ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    idx: usize,
+    pickup_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+) -> Result<(), String>
+{
+    let destructible = &DESTRUCTIBLE_SCANS[idx];
+    let (scan, strg) = destructible_scan_ids(idx);
+    let deps = [(scan, b"SCAN"), (strg, b"STRG")];
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: fourcc.into(),
+        });
+    area.add_dependencies(pickup_resources, 0, deps_iter);
+
+    let scly = area.mrea().scly_section_mut();
+    let layer = scly.layers.as_mut_vec().get_mut(destructible.layer)
+        .ok_or_else(|| format!("Destructible 0x{:X} is on a missing layer", destructible.instance_id))?;
+
+    if let Some(scan_target) = destructible.scan_target {
+        let poi = layer.objects.as_mut_vec().iter_mut()
+            .find(|obj| obj.instance_id == scan_target)
+            .and_then(|obj| obj.property_data.as_point_of_interest_mut())
+            .ok_or_else(|| format!("Failed to find scan target 0x{:X}", scan_target))?;
+        // A destructible that can already be scanned keeps its own scan
+        if poi.scan_param.scan == 0xFFFFFFFF {
+            poi.scan_param.scan = scan;
+        }
+        return Ok(());
+    }
+
+    let position = layer.objects.as_mut_vec().iter_mut()
+        .find(|obj| obj.instance_id == destructible.instance_id)
+        .and_then(|obj| obj.property_data.as_platform_mut())
+        .map(|platform| platform.position)
+        .ok_or_else(|| format!("Failed to find destructible 0x{:X}", destructible.instance_id))?;
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: ps.fresh_instance_id_range.next().unwrap(),
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::PointOfInterest(
+            structs::PointOfInterest {
+                name: b"Destructible scan\0".as_cstr(),
+                position,
+                rotation: [0.0, 0.0, 0.0].into(),
+                active: 1,
+                scan_param: structs::structs::ScannableParameters { scan },
+                unknown1: 0.0,
+            }
+        ),
+    });
+    Ok(())
+}
+
+// Everything rolled with the seed's main rng, in the order it's rolled
+struct SeedRolls
+{
+    artifact_totem_strings: [String; 12],
+    select_game_fmv_suffix: &'static str,
+    // The pickup layout with the shiny missiles swapped in
+    placed_layout: Vec<PickupType>,
+}
+
+fn roll_seed(seed: u64, pickup_layout: &[PickupType], shiny_missile_odds: Option<u32>) -> SeedRolls
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let artifact_totem_strings = build_artifact_temple_totem_scan_strings(pickup_layout, &mut rng);
+    let select_game_fmv_suffix = *["A", "B", "C"].choose(&mut rng).unwrap();
+
+    // 1 in 1024 chance of a missile being shiny means a player is likely to see a shiny missile
+    // every 40ish games (assuming most players collect about half of the missiles). Odds of 0
+    // mean never, and skip the roll.
+    let placed_layout = pickup_layout.iter()
+        .map(|&pickup_type| {
+            let is_shiny = match shiny_missile_odds.unwrap_or(1024) {
+                0 => false,
+                odds => pickup_type == PickupType::Missile && rng.gen_ratio(1, odds),
+            };
+            if is_shiny { PickupType::ShinyMissile } else { pickup_type }
+        })
+        .collect();
+
+    SeedRolls { artifact_totem_strings, select_game_fmv_suffix, placed_layout }
+}
+
 fn build_artifact_temple_totem_scan_strings<R>(pickup_layout: &[PickupType], rng: &mut R)
     -> [String; 12]
     where R: Rng
@@ -1026,23 +1454,37 @@ fn build_artifact_temple_totem_scan_strings<R>(pickup_layout: &[PickupType], rng
         // ▼▼▼▼ Only yield artifacts ▼▼▼▼
         .filter(|&(pt, _)| pt.is_artifact());
 
-    // Shame there isn't a way to flatten tuples automatically
+    // Collect every location of each artifact, in the order they appear in the layout
+    let mut artifact_rooms: Vec<Vec<(u32, &str)>> = vec![Vec::new(); scan_text.len()];
+    let mut artifact_order = Vec::new();
     for (pt, (room_id, name)) in iter {
         let artifact_id = pt.idx() - PickupType::ArtifactOfLifegiver.idx();
-        if scan_text[artifact_id].len() != 0 {
-            // If there are multiple of this particular artifact, then we use the first instance
-            // for the location of the artifact.
-            continue;
+        if artifact_rooms[artifact_id].is_empty() {
+            artifact_order.push((artifact_id, *pt));
         }
+        artifact_rooms[artifact_id].push((room_id, name));
+    }
+
+    for (artifact_id, pt) in artifact_order {
+        let rooms = &artifact_rooms[artifact_id];
 
-        // If there are specific messages for this room, choose one, other wise choose a generic
-        // message.
-        let template = specific_room_templates.iter_mut()
-            .find(|row| row.0 == room_id)
-            .and_then(|row| row.1.pop())
-            .unwrap_or_else(|| generic_templates_iter.next().unwrap());
+        // If there are specific messages for the room, choose one, other wise choose a generic
+        // message. When there are multiple copies of the artifact, every room is listed in a
+        // generic message.
+        let template = if rooms.len() == 1 {
+            specific_room_templates.iter_mut()
+                .find(|row| row.0 == rooms[0].0)
+                .and_then(|row| row.1.pop())
+        } else {
+            None
+        };
+        let template = template.unwrap_or_else(|| generic_templates_iter.next().unwrap());
+
+        // Every template colors the room name, so color each of the rooms the same way
+        let room_names: Vec<&str> = rooms.iter().map(|&(_, name)| name).collect();
+        let room_text = room_names.join("&pop; or the &push;&main-color=#43CD80;");
         let pickup_name = pt.name();
-        scan_text[artifact_id] = template.replace("{room}", name).replace("{pickup}", pickup_name);
+        scan_text[artifact_id] = template.replace("{room}", &room_text).replace("{pickup}", pickup_name);
     }
 
     // Set a default value for any artifacts that we didn't find.
@@ -1113,7 +1555,7 @@ fn patch_morphball_hud(res: &mut structs::Resource)
     Ok(())
 }
 
-fn patch_mines_savw_for_phazon_suit_scan(res: &mut structs::Resource)
+fn patch_savw_for_phazon_suit_scan(res: &mut structs::Resource)
     -> Result<(), String>
 {
     // Add a scan for the Phazon suit.
@@ -1125,11 +1567,50 @@ fn patch_mines_savw_for_phazon_suit_scan(res: &mut structs::Resource)
     Ok(())
 }
 
+// A SAVW lists the scans whose state is saved with its world. The Phazon Suit scan is only
+// listed in the Mines by default, so if the suit is placed in another world scanning it is never
+// saved, and the logbook can't reach 100% without a Phazon Suit in the Mines.
+fn world_savw(world: World) -> ResourceInfo
+{
+    match world {
+        World::FrigateOrpheon  => resource_info!("!Intro_Master.SAVW"),
+        World::TallonOverworld => resource_info!("!TalonOverworld_Master.SAVW"),
+        World::ChozoRuins      => resource_info!("!RuinsWorld_Master.SAVW"),
+        World::MagmoorCaverns  => resource_info!("!LavaWorld_Master.SAVW"),
+        World::PhendranaDrifts => resource_info!("!IceWorld_Master.SAVW"),
+        World::PhazonMines     => resource_info!("!MinesWorld_Master.SAVW"),
+        World::ImpactCrater    => resource_info!("!CraterWorld_Master.SAVW"),
+    }
+}
+
+// The worlds the Phazon Suit scan needs to be saved in
+fn phazon_suit_scan_worlds(pickup_layout: &[PickupType], follow_item: bool) -> Vec<World>
+{
+    if !follow_item {
+        return vec![World::PhazonMines];
+    }
+    let mut layout_iterator = pickup_layout.iter();
+    let mut worlds = vec![];
+    for (name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+        let world = World::from_pak(name).unwrap();
+        for room_info in rooms.iter() {
+            for pickup_type in layout_iterator.by_ref().take(room_info.pickup_locations.len()) {
+                if *pickup_type == PickupType::PhazonSuit && !worlds.contains(&world) {
+                    worlds.push(world);
+                }
+            }
+        }
+    }
+    worlds
+}
+
 #[derive(Copy, Clone, Debug)]
 enum MaybeObfuscatedPickup
 {
     Unobfuscated(PickupType),
     Obfuscated(PickupType),
+    // Also hides the item in the hudmemo shown when it's picked up
+    FullyObfuscated(PickupType),
 }
 
 impl MaybeObfuscatedPickup
@@ -1139,6 +1620,7 @@ impl MaybeObfuscatedPickup
         match self {
             MaybeObfuscatedPickup::Unobfuscated(pt) => *pt,
             MaybeObfuscatedPickup::Obfuscated(pt) => *pt,
+            MaybeObfuscatedPickup::FullyObfuscated(pt) => *pt,
         }
     }
 
@@ -1152,17 +1634,24 @@ impl MaybeObfuscatedPickup
         match self {
             MaybeObfuscatedPickup::Unobfuscated(pt) => pt.dependencies(),
             MaybeObfuscatedPickup::Obfuscated(_) => PickupType::Nothing.dependencies(),
+            MaybeObfuscatedPickup::FullyObfuscated(_) => PickupType::Nothing.dependencies(),
         }
     }
 
     fn hudmemo_strg(&self) -> u32
     {
-        self.orig().hudmemo_strg()
+        match self {
+            MaybeObfuscatedPickup::FullyObfuscated(_) => custom_asset_ids::OBFUSCATED_ACQUIRED_HUDMEMO_STRG,
+            _ => self.orig().hudmemo_strg(),
+        }
     }
 
     fn skip_hudmemos_strg(&self) -> u32
     {
-        self.orig().skip_hudmemos_strg()
+        match self {
+            MaybeObfuscatedPickup::FullyObfuscated(_) => custom_asset_ids::OBFUSCATED_ACQUIRED_HUDMEMO_STRG,
+            _ => self.orig().skip_hudmemos_strg(),
+        }
     }
 
     pub fn attainment_audio_file_name(&self) -> &'static str
@@ -1174,7 +1663,8 @@ impl MaybeObfuscatedPickup
     {
         match self {
             MaybeObfuscatedPickup::Unobfuscated(pt) => LCow::Borrowed(pt.pickup_data()),
-            MaybeObfuscatedPickup::Obfuscated(original) => {
+            MaybeObfuscatedPickup::Obfuscated(original) |
+            MaybeObfuscatedPickup::FullyObfuscated(original) => {
                 let original = original.pickup_data();
                 let nothing = PickupType::Nothing.pickup_data();
 
@@ -1202,7 +1692,9 @@ fn patch_add_item<'r>(
     // resolve dependencies
     let location_idx = 0;
 
-    let pickup_type = if config.obfuscate_items {
+    let pickup_type = if config.obfuscate_items && config.obfuscate_scans {
+        MaybeObfuscatedPickup::FullyObfuscated(pickup_type)
+    } else if config.obfuscate_items {
         MaybeObfuscatedPickup::Obfuscated(pickup_type)
     } else {
         MaybeObfuscatedPickup::Unobfuscated(pickup_type)
@@ -1216,7 +1708,7 @@ fn patch_add_item<'r>(
 
     let name = CString::new(format!(
             "Randomizer - Pickup {} ({:?})", location_idx, pickup_type.pickup_data().name)).unwrap();
-    area.add_layer(Cow::Owned(name));
+    area.add_layer(Cow::Owned(name))?;
 
     let new_layer_idx = area.layer_flags.layer_count as usize - 1;
 
@@ -1391,19 +1883,58 @@ fn patch_add_item<'r>(
     Ok(())
 }
 
+fn pickup_scan_override_ids(pickup_idx: usize) -> (u32, u32)
+{
+    let scan = custom_asset_ids::PICKUP_SCAN_OVERRIDE_START + pickup_idx as u32 * 2;
+    (scan, scan + 1)
+}
+
+fn add_pickup_scan_overrides(
+    pickup_resources: &mut HashMap<(u32, FourCC), structs::Resource>,
+    pickup_scan_overrides: &HashMap<usize, String>,
+    pickup_count: usize,
+) -> Result<(), String>
+{
+    for (&pickup_idx, text) in pickup_scan_overrides.iter() {
+        if pickup_idx >= pickup_count {
+            return Err(format!(
+                "Scan override for pickup {}, but the layout only has {} pickups",
+                pickup_idx, pickup_count,
+            ));
+        }
+        let (scan, strg) = pickup_scan_override_ids(pickup_idx);
+        if scan >= custom_asset_ids::PICKUP_SCAN_OVERRIDE_END {
+            return Err(format!("Scan overrides are only supported for the first {} pickups",
+                               (custom_asset_ids::PICKUP_SCAN_OVERRIDE_END
+                                    - custom_asset_ids::PICKUP_SCAN_OVERRIDE_START) / 2));
+        }
+
+        let mut text = text.clone();
+        if !text.ends_with('\0') {
+            text.push('\0');
+        }
+        for res in create_item_scan_strg_pair(scan, strg, &text).iter() {
+            assert!(pickup_resources.insert((res.file_id, res.fourcc()), res.clone()).is_none());
+        }
+    }
+    Ok(())
+}
+
 fn modify_pickups_in_mrea<'r>(
     ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    pickup_idx: usize,
     pickup_type: PickupType,
     pickup_location: pickup_meta::PickupLocation,
-    pickup_count: u32,
     pickup_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
     config: &ParsedConfig,
 ) -> Result<(), String>
 {
     let location_idx = 0;
 
-    let pickup_type = if config.obfuscate_items {
+    let pickup_type = if config.obfuscate_items && config.obfuscate_scans {
+        MaybeObfuscatedPickup::FullyObfuscated(pickup_type)
+    } else if config.obfuscate_items {
         MaybeObfuscatedPickup::Obfuscated(pickup_type)
     } else {
         MaybeObfuscatedPickup::Unobfuscated(pickup_type)
@@ -1417,7 +1948,7 @@ fn modify_pickups_in_mrea<'r>(
 
     let name = CString::new(format!(
             "Randomizer - Pickup {} ({:?})", location_idx, pickup_type.pickup_data().name)).unwrap();
-    area.add_layer(Cow::Owned(name));
+    area.add_layer(Cow::Owned(name))?;
 
     let new_layer_idx = area.layer_flags.layer_count as usize - 1;
 
@@ -1431,8 +1962,22 @@ fn modify_pickups_in_mrea<'r>(
         asset_type: b"STRG".into(),
     };
     let deps_iter = deps_iter.chain(iter::once(hudmemo_dep));
+
+    // A custom scan replaces the one belonging to the pickup type
+    let scan_override = if config.pickup_scan_overrides.contains_key(&pickup_idx) {
+        Some(pickup_scan_override_ids(pickup_idx))
+    } else {
+        None
+    };
+    let scan_override_deps = scan_override.iter()
+        .flat_map(|&(scan, strg)| vec![
+            structs::Dependency { asset_id: scan, asset_type: b"SCAN".into() },
+            structs::Dependency { asset_id: strg, asset_type: b"STRG".into() },
+        ]);
+    let deps_iter = deps_iter.chain(scan_override_deps);
     area.add_dependencies(pickup_resources, new_layer_idx, deps_iter);
 
+    let room_id = area.mlvl_area.mrea;
     let scly = area.mrea().scly_section_mut();
     let layers = scly.layers.as_mut_vec();
 
@@ -1462,38 +2007,56 @@ fn modify_pickups_in_mrea<'r>(
         });
     }
 
-    let pickup = layers[pickup_location.location.layer as usize].objects.iter_mut()
-        .find(|obj| obj.instance_id ==  pickup_location.location.instance_id)
-        .unwrap();
-    update_pickup(pickup, pickup_type, pickup_count);
+    let pickup = find_pickup_location_object(layers, pickup_location.location, room_id, "pickup")?;
+    // Randomized pickups keep the amount their type gives
+    update_pickup(pickup, pickup_type, 0xFFFFFFFF, &ps.custom_pickup_aabbs)?;
+    if let Some((scan, _)) = scan_override {
+        pickup.property_data.as_pickup_mut().unwrap().actor_params.scan_params.scan = scan;
+    }
     if additional_connections.len() > 0 {
         pickup.connections.as_mut_vec().extend_from_slice(&additional_connections);
     }
 
-    let hudmemo = layers[pickup_location.hudmemo.layer as usize].objects.iter_mut()
-        .find(|obj| obj.instance_id ==  pickup_location.hudmemo.instance_id)
-        .unwrap();
+    let hudmemo = find_pickup_location_object(layers, pickup_location.hudmemo, room_id, "hudmemo")?;
     update_hudmemo(hudmemo, pickup_type, location_idx, config.skip_hudmenus);
 
     let location = pickup_location.attainment_audio;
-    let attainment_audio = layers[location.layer as usize].objects.iter_mut()
-        .find(|obj| obj.instance_id ==  location.instance_id)
-        .unwrap();
-    update_attainment_audio(attainment_audio, pickup_type);
+    let attainment_audio = find_pickup_location_object(layers, location, room_id, "attainment audio")?;
+    update_attainment_audio(attainment_audio, pickup_type, config.mute_item_fanfare);
     Ok(())
 }
 
-fn update_pickup(
+// The locations come from PICKUP_LOCATIONS, which may not match every version of the game,
+// so report a missing object instead of panicking
+fn find_pickup_location_object<'a, 'r>(
+    layers: &'a mut [structs::SclyLayer<'r>],
+    location: pickup_meta::ScriptObjectLocation,
+    room_id: u32,
+    kind: &str,
+) -> Result<&'a mut structs::SclyObject<'r>, String>
+{
+    layers.get_mut(location.layer as usize)
+        .and_then(|layer| layer.objects.iter_mut().find(|obj| obj.instance_id == location.instance_id))
+        .ok_or_else(|| format!(
+            "Room 0x{:08X} has no {} object 0x{:X} on layer {}",
+            room_id, kind, location.instance_id, location.layer,
+        ))
+}
+
+fn update_pickup(
     pickup: &mut structs::SclyObject,
     pickup_type: MaybeObfuscatedPickup,
     pickup_count: u32,
-)
+    custom_aabbs: &HashMap<u32, [f32; 6]>,
+) -> Result<(), String>
 {
     let pickup = pickup.property_data.as_pickup_mut().unwrap();
     let original_pickup = pickup.clone();
     
-    let original_aabb = pickup_meta::aabb_for_pickup_cmdl(original_pickup.cmdl).unwrap();
-    let new_aabb = pickup_meta::aabb_for_pickup_cmdl(pickup_type.pickup_data().cmdl).unwrap();
+    let aabb = |cmdl| pickup_meta::aabb_for_pickup_cmdl(cmdl, custom_aabbs)
+        .ok_or_else(|| format!("No AABB for pickup CMDL 0x{:08X}, add one to custom_pickup_aabbs", cmdl));
+    let original_aabb = aabb(original_pickup.cmdl)?;
+    let new_aabb = aabb(pickup_type.pickup_data().cmdl)?;
     let original_center = calculate_center(original_aabb, original_pickup.rotation,
                                             original_pickup.scale);
     let new_center = calculate_center(new_aabb, pickup_type.pickup_data().rotation,
@@ -1526,6 +2089,7 @@ fn update_pickup(
         pickup.max_increase  = pickup_count;
         pickup.curr_increase = pickup_count;
     }
+    Ok(())
 }
 
 fn update_hudmemo(
@@ -1548,11 +2112,17 @@ fn update_hudmemo(
 }
 
 fn update_attainment_audio(attainment_audio: &mut structs::SclyObject,
-                           pickup_type: MaybeObfuscatedPickup)
+                           pickup_type: MaybeObfuscatedPickup,
+                           mute: bool)
 {
     let attainment_audio = attainment_audio.property_data.as_streamed_audio_mut().unwrap();
     let bytes = pickup_type.attainment_audio_file_name().as_bytes();
     attainment_audio.audio_file_name = bytes.as_cstr();
+
+    // An inactive StreamedAudio ignores PLAY, so the pickup's connections to it can stay as they are
+    if mute {
+        attainment_audio.active = 0;
+    }
 }
 
 fn calculate_center(aabb: [f32; 6], rotation: GenericArray<f32, U3>, scale: GenericArray<f32, U3>)
@@ -1594,12 +2164,35 @@ fn rotate(mut coordinate: [f32; 3], mut rotation: [f32; 3], center: [f32; 3])
 }
 
 
+// Shuffle the elevators into new pairs, so every elevator leads to another one that leads back to
+// it, like in the unmodified game. The Artifact Temple and Crater Entry Point elevators keep
+// leading to each other, otherwise the Impact Crater could be reached without collecting the
+// artifacts.
+fn randomize_elevator_layout(rng: &mut StdRng) -> Vec<u8>
+{
+    let mut shuffled: Vec<usize> = ELEVATORS.iter()
+        .enumerate()
+        .filter(|(_, elv)| elv.name != "Artifact Temple" && elv.name != "Crater Entry Point")
+        .map(|(i, _)| i)
+        .collect();
+    shuffled.shuffle(rng);
+
+    let mut layout: Vec<u8> = ELEVATORS.iter().map(|elv| elv.default_dest).collect();
+    for pair in shuffled.chunks_exact(2) {
+        layout[pair[0]] = pair[1] as u8;
+        layout[pair[1]] = pair[0] as u8;
+    }
+    layout
+}
+
 fn make_elevators_patch<'a>(
     patcher: &mut PrimePatcher<'_, 'a>,
     layout: &'a [Elevator],
     dest_names: &Vec<String>,
     auto_enabled_elevators: bool,
     tiny_elvetator_samus: bool,
+    fast_elevators: bool,
+    languages: &'a [Language],
 )
 {
     let mut idx = 0;
@@ -1625,6 +2218,15 @@ fn make_elevators_patch<'a>(
                     {
                         wt.player_scale = [0.33,0.33,0.33].into();
                     }
+
+                    // Only the destination text timing is exposed on the WorldTransporter, the
+                    // length of the ride itself is decided by the engine
+                    if fast_elevators
+                    {
+                        wt.show_delay = 0.0;
+                        wt.char_fade_in_time = 0.0;
+                        wt.chars_per_second = 1000.0;
+                    }
                 }
             }
 
@@ -1673,30 +2275,24 @@ fn make_elevators_patch<'a>(
             }
         };
 
-        let room_dest_name = dest_name.replace('\0', "\n");
-        let hologram_name = dest_name.replace('\0', " ");
-        let control_name = dest_name.replace('\0', " ");
+        let room_text = elevator_room_text(dest_name);
+        let hologram_text = elevator_hologram_text(dest_name);
+        let control_text = elevator_control_text(dest_name);
         patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.room_strg, b"STRG".into()), move |res| {
-            let string = format!("Transport to {}\u{0}", room_dest_name);
-            let strg = structs::Strg::from_strings(vec![string]);
+            let mut strg = structs::Strg::from_strings(vec![room_text.clone()]);
+            add_strg_languages(&mut strg, languages);
             res.kind = structs::ResourceKind::Strg(strg);
             Ok(())
         });
         patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.hologram_strg, b"STRG".into()), move |res| {
-            let string = format!(
-                "Access to &main-color=#FF3333;{} &main-color=#89D6FF;granted. Please step into the hologram.\u{0}",
-                hologram_name,
-            );
-            let strg = structs::Strg::from_strings(vec![string]);
+            let mut strg = structs::Strg::from_strings(vec![hologram_text.clone()]);
+            add_strg_languages(&mut strg, languages);
             res.kind = structs::ResourceKind::Strg(strg);
             Ok(())
         });
         patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.control_strg, b"STRG".into()), move |res| {
-            let string = format!(
-                "Transport to &main-color=#FF3333;{}&main-color=#89D6FF; active.\u{0}",
-                control_name,
-            );
-            let strg = structs::Strg::from_strings(vec![string]);
+            let mut strg = structs::Strg::from_strings(vec![control_text.clone()]);
+            add_strg_languages(&mut strg, languages);
             res.kind = structs::ResourceKind::Strg(strg);
             Ok(())
         });
@@ -1713,6 +2309,7 @@ fn patch_landing_site_cutscene_triggers(
     // XXX I'd like to do this some other way than inserting a timer to trigger
     //     the memory relay, but I couldn't figure out how to make the memory
     //     relay default to on/enabled.
+    deactivate_objects(area, &[221])?;
     let layer = area.mrea().scly_section_mut().layers.iter_mut().next().unwrap();
     let timer_id = ps.fresh_instance_id_range.next().unwrap();
     for obj in layer.objects.iter_mut() {
@@ -1723,9 +2320,6 @@ fn patch_landing_site_cutscene_triggers(
                 target_object_id: timer_id,
             });
         }
-        if obj.instance_id == 221 {
-            obj.property_data.as_trigger_mut().unwrap().active = 0;
-        }
     }
     layer.objects.as_mut_vec().push(structs::SclyObject {
         instance_id: timer_id,
@@ -1796,9 +2390,129 @@ fn patch_frigate_teleporter<'r>(area: &mut mlvl_wrapper::MlvlArea, spawn_room: S
     Ok(())
 }
 
-fn calculate_door_type(pak_name: &str, mut rng: &mut StdRng, weights: &Weights) -> DoorType {
-    let range = Uniform::from(0..100);
-    let weights : &[u8;4] = match pak_name {
+// Standing in a save station's beam for a few seconds warps the player back to the spawn room. A
+// WorldTransporter without models or text is used, the same object the elevators teleport with.
+fn patch_save_station_warp_to_start(
+    ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    spawn_room: SpawnRoom,
+    version: Version,
+) -> Result<(), String>
+{
+    let trigger_id = ps.fresh_instance_id_range.next().unwrap();
+    let timer_id = ps.fresh_instance_id_range.next().unwrap();
+    let world_transporter_id = ps.fresh_instance_id_range.next().unwrap();
+
+    let scly = area.mrea().scly_section_mut();
+    let save_station_position = scly.layers.iter_mut()
+        .flat_map(|layer| layer.objects.iter_mut())
+        .filter_map(|obj| obj.property_data.as_special_function_mut())
+        .find(|sf| sf.type_ == 7) // SaveStation
+        .map(|sf| sf.position)
+        .ok_or("Could not find the save station to add a warp to")?;
+
+    let pal_additions = if version == Version::Pal {
+        Some(structs::WorldTransporterPalAdditions {
+            audio_stream: b"\0".as_cstr(),
+            unknown0: 0,
+            unknown1: 0.0,
+            unknown2: 0.0,
+            unknown3: 0.0,
+        })
+    } else {
+        None
+    };
+
+    let layer = &mut scly.layers.as_mut_vec()[0];
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: trigger_id,
+        property_data: structs::SclyProperty::Trigger(structs::Trigger {
+            name: b"Trigger Warp To Start\0".as_cstr(),
+            position: save_station_position,
+            scale: [3.0, 3.0, 4.0].into(),
+            damage_info: structs::structs::DamageInfo {
+                weapon_type: 0,
+                damage: 0.0,
+                radius: 0.0,
+                knockback_power: 0.0
+            },
+            unknown0: [0.0, 0.0, 0.0].into(),
+            unknown1: 1,
+            active: 1,
+            unknown2: 0,
+            unknown3: 0
+        }),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ENTERED,
+                message: structs::ConnectionMsg::RESET_AND_START,
+                target_object_id: timer_id,
+            },
+            structs::Connection {
+                state: structs::ConnectionState::EXITED,
+                message: structs::ConnectionMsg::STOP_AND_RESET,
+                target_object_id: timer_id,
+            },
+        ].into(),
+    });
+
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: timer_id,
+        property_data: structs::SclyProperty::Timer(structs::Timer {
+            name: b"Timer Warp To Start\0".as_cstr(),
+            start_time: 5.0,
+            max_random_add: 0f32,
+            reset_to_zero: 0,
+            start_immediately: 0,
+            active: 1,
+        }),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::SET_TO_ZERO,
+                target_object_id: world_transporter_id,
+            },
+        ].into(),
+    });
+
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: world_transporter_id,
+        property_data: structs::SclyProperty::WorldTransporter(structs::WorldTransporter {
+            name: b"WorldTransporter Warp To Start\0".as_cstr(),
+            unknown0: 1,
+            mlvl: spawn_room.mlvl,
+            mrea: spawn_room.mrea,
+            ancs: structs::structs::AncsProp {
+                file_id: 0xFFFFFFFF,
+                node_index: 0,
+                unknown: 0xFFFFFFFF,
+            },
+            player_scale: [1.0, 1.0, 1.0].into(),
+            platform_model: 0xFFFFFFFF,
+            platform_scale: [1.0, 1.0, 1.0].into(),
+            background_model: 0xFFFFFFFF,
+            background_scale: [1.0, 1.0, 1.0].into(),
+            up_elevator: 0,
+            elevator_sound: 0xFFFFFFFF,
+            volume: 0,
+            panning: 0,
+            show_text: 0,
+            font: 0xFFFFFFFF,
+            strg: 0xFFFFFFFF,
+            fade_white: 0,
+            char_fade_in_time: 0.0,
+            chars_per_second: 0.0,
+            show_delay: 0.0,
+            pal_additions,
+        }),
+        connections: vec![].into(),
+    });
+
+    Ok(())
+}
+
+fn door_weights_for_pak<'a>(pak_name: &str, weights: &'a Weights) -> &'a [u8;4] {
+    match pak_name {
         "Metroid2.pak" => &weights.chozo_ruins,
         "Metroid3.pak" => &weights.phendrana_drifts,
         "Metroid4.pak" => &weights.tallon_overworld,
@@ -1806,15 +2520,529 @@ fn calculate_door_type(pak_name: &str, mut rng: &mut StdRng, weights: &Weights)
         "Metroid6.pak" => &weights.magmoor_caverns,
         "Metroid7.pak" => &[0,0,0,100],
         _ => &[100,0,0,0]
-    };
-    if weights[0]+weights[1]+weights[2]+weights[3] != 100 { panic!("The sum of all weights for each area must equal exactly 100.") }
-    let num:u8 = range.sample(&mut rng);
+    }
+}
+
+// Widened so the weights can be added up without overflowing
+fn door_weights_u16(pak_name: &str, weights: &Weights) -> [u16; 4] {
+    let weights = door_weights_for_pak(pak_name, weights);
+    [weights[0] as u16, weights[1] as u16, weights[2] as u16, weights[3] as u16]
+}
+
+// Each door gets its own RNG derived from the seed, room and dock, so its color doesn't depend on
+// how many doors were rolled before it. The mixing is spelled out (splitmix64) rather than using
+// std's hasher, whose output isn't guaranteed to stay the same between Rust releases.
+fn seeded_door_rng(seed: u64, room_id: u32, dock_number: u32) -> StdRng
+{
+    let mut x = seed ^ (((room_id as u64) << 32) | dock_number as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^= x >> 31;
+    StdRng::seed_from_u64(x)
+}
+
+// The weights are relative to each other, so [1,1,1,1] is as likely to roll each color as
+// [25,25,25,25]. Weights adding up to 100 roll the same colors as when they had to.
+fn calculate_door_type(pak_name: &str, mut rng: &mut StdRng, weights: &Weights) -> DoorType {
+    let weights = door_weights_u16(pak_name, weights); // checked by Weights::validate
+    let range = Uniform::from(0..weights.iter().sum::<u16>());
+    let num:u16 = range.sample(&mut rng);
     if num < weights[0] { DoorType::Blue }
     else if num < (weights[1]+weights[0]) { DoorType::Purple }
     else if num < (weights[2]+weights[1]+weights[0]) { DoorType::White }
-    else if num < (weights[3]+weights[2]+weights[1]+weights[0]) { DoorType::Red }
-    else {
-        panic!("RNG outside the range 0-99.")
+    else { DoorType::Red }
+}
+
+// The color the patcher rolls for a door, before excluded doors and custom doors are applied
+fn random_door_type(pak_name: &str, seed: u64, room_id: u32, dock_number: u32, weights: &Weights) -> DoorType {
+    calculate_door_type(pak_name, &mut seeded_door_rng(seed, room_id, dock_number), weights)
+}
+
+// Like calculate_door_type, but never blue. None if the weights don't allow any other color.
+fn calculate_colored_door_type(pak_name: &str, rng: &mut StdRng, weights: &Weights) -> Option<DoorType> {
+    let weights = door_weights_u16(pak_name, weights);
+    let total = weights[1] + weights[2] + weights[3];
+    if total == 0 {
+        return None;
+    }
+    let num = rng.gen_range(0, total);
+    if num < weights[1] { Some(DoorType::Purple) }
+    else if num < (weights[2]+weights[1]) { Some(DoorType::White) }
+    else { Some(DoorType::Red) }
+}
+
+// Mirrors the bit layout consumed by patch_starting_pickups
+pub(crate) fn starting_items_contain(starting_items: u64, pickup_type: PickupType) -> bool
+{
+    starting_item_count(starting_items, pickup_type) != 0
+}
+
+fn starting_item_count(starting_items: u64, pickup_type: PickupType) -> u64
+{
+    let (offset, bits) = match pickup_type {
+        PickupType::ScanVisor =>          (0, 1),
+        PickupType::Missile =>            (1, 8),
+        PickupType::EnergyTank =>         (9, 4),
+        PickupType::PowerBomb =>          (13, 4),
+        PickupType::WaveBeam =>           (17, 1),
+        PickupType::IceBeam =>            (18, 1),
+        PickupType::PlasmaBeam =>         (19, 1),
+        PickupType::ChargeBeam =>         (20, 1),
+        PickupType::MorphBall =>          (21, 1),
+        PickupType::MorphBallBomb =>      (22, 1),
+        PickupType::SpiderBall =>         (23, 1),
+        PickupType::BoostBall =>          (24, 1),
+        PickupType::VariaSuit =>          (25, 1),
+        PickupType::GravitySuit =>        (26, 1),
+        PickupType::PhazonSuit =>         (27, 1),
+        PickupType::ThermalVisor =>       (28, 1),
+        PickupType::XRayVisor =>          (29, 1),
+        PickupType::SpaceJumpBoots =>     (30, 1),
+        PickupType::GrappleBeam =>        (31, 1),
+        PickupType::SuperMissile =>       (32, 1),
+        PickupType::Wavebuster =>         (33, 1),
+        PickupType::IceSpreader =>        (34, 1),
+        PickupType::Flamethrower =>       (35, 1),
+        _ => return 0,
+    };
+    (starting_items >> offset) & ((1 << bits) - 1)
+}
+
+// Named form of the starting items bitfield. from_u64/to_u64 keep the legacy bit layout (lowest
+// bits first): scan visor (1), missiles (8), energy tanks (4), power bombs (4), then one bit each
+// for wave, ice, plasma, charge, morph ball, bombs, spider ball, boost ball, varia, gravity,
+// phazon, thermal, x-ray, space jump, grapple, super missile, wavebuster, ice spreader and
+// flamethrower.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct StartingItems
+{
+    pub scan_visor: bool,
+    pub missiles: u8,
+    pub energy_tanks: u8,
+    pub power_bombs: u8,
+    pub wave: bool,
+    pub ice: bool,
+    pub plasma: bool,
+    pub charge: bool,
+    pub morph_ball: bool,
+    pub bombs: bool,
+    pub spider_ball: bool,
+    pub boost_ball: bool,
+    pub varia_suit: bool,
+    pub gravity_suit: bool,
+    pub phazon_suit: bool,
+    pub thermal_visor: bool,
+    pub xray: bool,
+    pub space_jump: bool,
+    pub grapple: bool,
+    pub super_missile: bool,
+    pub wavebuster: bool,
+    pub ice_spreader: bool,
+    pub flamethrower: bool,
+}
+
+impl StartingItems
+{
+    pub fn from_u64(mut starting_items: u64) -> Self
+    {
+        let mut fetch_bits = move |bits: u8| {
+            let ret = starting_items & ((1 << bits) - 1);
+            starting_items >>= bits;
+            ret as u8
+        };
+
+        StartingItems {
+            scan_visor: fetch_bits(1) != 0,
+            missiles: fetch_bits(8),
+            energy_tanks: fetch_bits(4),
+            power_bombs: fetch_bits(4),
+            wave: fetch_bits(1) != 0,
+            ice: fetch_bits(1) != 0,
+            plasma: fetch_bits(1) != 0,
+            charge: fetch_bits(1) != 0,
+            morph_ball: fetch_bits(1) != 0,
+            bombs: fetch_bits(1) != 0,
+            spider_ball: fetch_bits(1) != 0,
+            boost_ball: fetch_bits(1) != 0,
+            varia_suit: fetch_bits(1) != 0,
+            gravity_suit: fetch_bits(1) != 0,
+            phazon_suit: fetch_bits(1) != 0,
+            thermal_visor: fetch_bits(1) != 0,
+            xray: fetch_bits(1) != 0,
+            space_jump: fetch_bits(1) != 0,
+            grapple: fetch_bits(1) != 0,
+            super_missile: fetch_bits(1) != 0,
+            wavebuster: fetch_bits(1) != 0,
+            ice_spreader: fetch_bits(1) != 0,
+            flamethrower: fetch_bits(1) != 0,
+        }
+    }
+
+    // The counts have to fit the bits they get in the u64 and in the save file (e.g. at most 15
+    // energy tanks)
+    pub fn validate(&self, name: &str) -> Result<(), String>
+    {
+        let counts = [("energyTanks", self.energy_tanks, 4), ("powerBombs", self.power_bombs, 4)];
+        for &(item, count, bits) in counts.iter() {
+            if count as u64 >= 1 << bits {
+                Err(format!("{} gives {} {}, at most {} are supported", name, count, item, (1 << bits) - 1))?
+            }
+        }
+        Ok(())
+    }
+
+    // Counts wider than their field are truncated, call validate first
+    pub fn to_u64(&self) -> u64
+    {
+        let mut starting_items = 0u64;
+        let mut offset = 0;
+        let mut push_bits = |value: u64, bits: u8| {
+            starting_items |= (value & ((1 << bits) - 1)) << offset;
+            offset += bits;
+        };
+
+        push_bits(self.scan_visor as u64, 1);
+        push_bits(self.missiles as u64, 8);
+        push_bits(self.energy_tanks as u64, 4);
+        push_bits(self.power_bombs as u64, 4);
+        push_bits(self.wave as u64, 1);
+        push_bits(self.ice as u64, 1);
+        push_bits(self.plasma as u64, 1);
+        push_bits(self.charge as u64, 1);
+        push_bits(self.morph_ball as u64, 1);
+        push_bits(self.bombs as u64, 1);
+        push_bits(self.spider_ball as u64, 1);
+        push_bits(self.boost_ball as u64, 1);
+        push_bits(self.varia_suit as u64, 1);
+        push_bits(self.gravity_suit as u64, 1);
+        push_bits(self.phazon_suit as u64, 1);
+        push_bits(self.thermal_visor as u64, 1);
+        push_bits(self.xray as u64, 1);
+        push_bits(self.space_jump as u64, 1);
+        push_bits(self.grapple as u64, 1);
+        push_bits(self.super_missile as u64, 1);
+        push_bits(self.wavebuster as u64, 1);
+        push_bits(self.ice_spreader as u64, 1);
+        push_bits(self.flamethrower as u64, 1);
+
+        starting_items
+    }
+}
+
+// Configs may give the starting items either as the legacy bitfield or as named fields
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(untagged)]
+pub enum StartingItemsConfig
+{
+    Legacy(u64),
+    Named(StartingItems),
+}
+
+impl From<StartingItemsConfig> for StartingItems
+{
+    fn from(config: StartingItemsConfig) -> Self
+    {
+        match config {
+            StartingItemsConfig::Legacy(bits) => StartingItems::from_u64(bits),
+            StartingItemsConfig::Named(items) => items,
+        }
+    }
+}
+
+// Splits an excluded pickup into its world, its room name and, for "World:Room Name:index", the
+// location index. The names are lowercased to be compared case-insensitively.
+fn parse_excluded_pickup(excluded_pickup: &str) -> Result<(String, String, Option<usize>), String>
+{
+    let mut parts: Vec<&str> = excluded_pickup.split(':').collect();
+    let location_idx = match parts.last().map(|idx| idx.trim().parse::<usize>()) {
+        Some(Ok(location_idx)) if parts.len() == 3 => {
+            parts.pop();
+            Some(location_idx)
+        },
+        _ => None,
+    };
+    if parts.len() != 2 {
+        Err(format!("Excluded pickup '{}' should be written as \"World:Room Name\" or \"World:Room Name:index\"", excluded_pickup))?
+    }
+    Ok((parts[0].trim().to_lowercase(), parts[1].trim().to_lowercase(), location_idx))
+}
+
+// The layout indices of the locations the excluded pickups cover. Like spawn rooms, the world
+// can be shortened to the start of its name, as long as only one room matches.
+fn excluded_pickup_indices(excluded_pickups: &HashSet<String>) -> Result<HashSet<usize>, String>
+{
+    let mut indices = HashSet::new();
+    for excluded_pickup in excluded_pickups {
+        let (world_name, room_name, location_idx) = parse_excluded_pickup(excluded_pickup)?;
+
+        let mut matches = vec![];
+        let mut layout_idx = 0;
+        for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+            let world = World::from_pak(pak_name).unwrap();
+            for room_info in rooms.iter() {
+                let count = room_info.pickup_locations.len();
+                if count > 0 && world.as_string().to_lowercase().starts_with(&world_name) &&
+                    room_info.name.to_lowercase() == room_name
+                {
+                    matches.push((layout_idx, count));
+                }
+                layout_idx += count;
+            }
+        }
+
+        match (&matches[..], location_idx) {
+            ([], _) =>
+                Err(format!("Excluded pickup '{}' isn't a room with pickups", excluded_pickup))?,
+            ([_, _, ..], _) =>
+                Err(format!("Excluded pickup '{}' matches a room in more than one world", excluded_pickup))?,
+            (&[(_, count)], Some(location_idx)) if location_idx >= count =>
+                Err(format!("Excluded pickup '{}': the room only has {} pickup(s)", excluded_pickup, count))?,
+            (&[(start, _)], Some(location_idx)) => {
+                indices.insert(start + location_idx);
+            },
+            (&[(start, count)], None) => indices.extend(start..start + count),
+        }
+    }
+    Ok(indices)
+}
+
+// The room and dock of every door a door opener is connected to. Openers have to be in the door's
+// room, so a solid door with one is as reachable as the door itself.
+fn door_opener_docks(door_openers: &[DoorOpener]) -> Result<HashSet<(u32, usize)>, String>
+{
+    door_openers.iter()
+        .map(|opener| Ok((spawn_room_from_string(opener.room.to_string())?.mrea, opener.dock as usize)))
+        .collect()
+}
+
+// The door a patched dock is given, as far as the logic is concerned
+fn logic_door_type(
+    opened_docks: &HashSet<(u32, usize)>,
+    room_id: u32,
+    door_index: usize,
+    door_type: DoorType,
+    is_custom_door: bool,
+    blast_shield: Option<BlastShieldType>,
+) -> Option<DoorType>
+{
+    if let Some(shield_type) = blast_shield {
+        Some(shield_type.door_type())
+    } else if is_custom_door {
+        // The items needed to open a custom door aren't known, so it is taken to be open
+        None
+    } else if (door_type == DoorType::Solid || door_type == DoorType::VerticalSolid) &&
+        opened_docks.contains(&(room_id, door_index))
+    {
+        None
+    } else {
+        Some(door_type)
+    }
+}
+
+// Every dock of every room, with the room it leads to and the door in it as the unpatched game
+// has it. Blast shields the game places itself aren't read, so the doors behind them count as
+// their forcefield's color.
+pub fn vanilla_door_assignments(gc_disc: &structs::GcDisc) -> Result<Vec<DoorAssignment>, String>
+{
+    let mut door_assignments = vec![];
+    for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("The disc has no {}", pak_name))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+
+        let mlvl = pak.resources.iter()
+            .find(|res| res.fourcc() == b"MLVL".into())
+            .and_then(|res| res.kind.as_mlvl().map(|mlvl| mlvl.into_owned()))
+            .ok_or_else(|| format!("{} has no MLVL", pak_name))?;
+        let area_mreas: Vec<u32> = mlvl.areas.iter().map(|area| area.mrea).collect();
+
+        for area in mlvl.areas.iter() {
+            let room_info = rooms.iter().find(|room_info| room_info.room_id == area.mrea);
+            let mut scly = None;
+            for (dock_number, dock) in area.docks.iter().enumerate() {
+                let destination_room_id = match dock.connecting_docks.iter().next() {
+                    Some(connection) => *area_mreas.get(connection.array_index as usize)
+                        .ok_or_else(|| format!("Dock {} of room 0x{:08X} leads to a missing area", dock_number, area.mrea))?,
+                    None => continue,
+                };
+                let door_location = room_info.and_then(|room_info| room_info.door_locations.iter()
+                    .find(|door_location| door_location.dock_number == Some(dock_number as u32)));
+                let door_type = match door_location {
+                    Some(door_location) => {
+                        if scly.is_none() {
+                            let res = pak.resources.iter()
+                                .find(|res| res.file_id == area.mrea && res.fourcc() == b"MREA".into())
+                                .ok_or_else(|| format!("{} has no room 0x{:08X}", pak_name, area.mrea))?;
+                            let mrea = res.kind.as_mrea()
+                                .ok_or_else(|| format!("Room 0x{:08X} isn't an MREA", area.mrea))?;
+                            scly = Some(mrea.scly_section().into_owned());
+                        }
+                        let layers = scly.as_mut().unwrap().layers.as_mut_vec();
+                        let door_force = find_pickup_location_object(layers, door_location.door_force_location, area.mrea, "door forcefield")?;
+                        let color_txtr = door_force.property_data.as_damageable_trigger()
+                            .ok_or_else(|| format!("Object 0x{:X} in room 0x{:08X} isn't a damageable trigger", door_force.instance_id, area.mrea))?
+                            .color_txtr;
+                        DoorType::from_txtr(&color_txtr)
+                    },
+                    None => None,
+                };
+                door_assignments.push(DoorAssignment {
+                    room_id: area.mrea,
+                    dock_number: dock_number as u32,
+                    door_type,
+                    destination_room_id,
+                });
+            }
+        }
+    }
+    Ok(door_assignments)
+}
+
+fn room_description(room_id: u32) -> String
+{
+    pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|(pak_name, rooms)| rooms.iter().map(move |room_info| (pak_name, room_info)))
+        .find(|(_, room_info)| room_info.room_id == room_id)
+        .map(|(pak_name, room_info)| format!("{} - {}", World::from_pak(pak_name).unwrap().as_string(), room_info.name))
+        .unwrap_or_else(|| format!("Room 0x{:08X}", room_id))
+}
+
+// Walk the rooms from the starting rooms, and warn about every door that keeps the walk out of a
+// room and every room with pickups the walk never gets to. The returned warnings are
+// informational only and never stop the patching.
+fn check_door_logic(
+    room_pickups: &HashMap<u32, Vec<PickupType>>,
+    door_assignments: &[DoorAssignment],
+    elevator_layout: &[Elevator],
+    starting_room_ids: &[u32],
+    starting_items: &HashSet<PickupType>,
+) -> Vec<String>
+{
+    let reach = logic::walk(room_pickups, door_assignments, elevator_layout, starting_room_ids, starting_items);
+
+    let mut warnings = Vec::new();
+    for door in door_assignments {
+        if !reach.rooms.contains(&door.room_id) || reach.rooms.contains(&door.destination_room_id) {
+            continue;
+        }
+        if let (Some(door_type), Some(reason)) = (door.door_type, logic::door_blocker(door.door_type, &reach.items)) {
+            warnings.push(format!(
+                "{} door {} is {:?}, but {}",
+                room_description(door.room_id),
+                door.dock_number,
+                door_type,
+                reason,
+            ));
+        }
+    }
+
+    let mut unreached_rooms: Vec<_> = room_pickups.iter()
+        .filter(|(room_id, pickups)| !reach.rooms.contains(room_id) && pickups.iter().any(|&t| t != PickupType::Nothing))
+        .map(|(&room_id, _)| room_id)
+        .collect();
+    unreached_rooms.sort();
+    for room_id in unreached_rooms {
+        warnings.push(format!("{} can't be reached, so its pickups can't be collected", room_description(room_id)));
+    }
+    warnings
+}
+fn with_patched_doors(
+    vanilla_doors: &[DoorAssignment],
+    patched_door_types: &HashMap<(u32, u32), Option<DoorType>>,
+) -> Vec<DoorAssignment>
+{
+    vanilla_doors.iter()
+        .map(|door| DoorAssignment {
+            door_type: patched_door_types.get(&(door.room_id, door.dock_number)).cloned().unwrap_or(door.door_type),
+            ..*door
+        })
+        .collect()
+}
+
+// Turn randomly colored doors blue one at a time, each time picking the first one the walk from
+// the starting rooms is stuck at, until the walk is stuck at none of them. Returns the doors that
+// were changed, as they were before, along with why the walk couldn't get through them.
+fn open_unopenable_doors(
+    room_pickups: &HashMap<u32, Vec<PickupType>>,
+    door_assignments: &mut [DoorAssignment],
+    recolorable_docks: &HashSet<(u32, u32)>,
+    elevator_layout: &[Elevator],
+    starting_room_ids: &[u32],
+    starting_items: &HashSet<PickupType>,
+) -> Vec<(DoorAssignment, String)>
+{
+    let mut opened_doors = Vec::new();
+    loop {
+        let reach = logic::walk(room_pickups, door_assignments, elevator_layout, starting_room_ids, starting_items);
+        let stuck_door = door_assignments.iter_mut()
+            .filter(|door| reach.rooms.contains(&door.room_id) && !reach.rooms.contains(&door.destination_room_id))
+            .filter(|door| recolorable_docks.contains(&(door.room_id, door.dock_number)))
+            .find_map(|door| logic::door_blocker(door.door_type, &reach.items).map(|reason| (door, reason)));
+        match stuck_door {
+            Some((door, reason)) => {
+                opened_doors.push((*door, reason));
+                door.door_type = door.door_type
+                    .map(|door_type| if door_type.is_vertical() { DoorType::VerticalBlue } else { DoorType::Blue });
+            },
+            None => return opened_doors,
+        }
+    }
+}
+
+// The door specification the config gives a door. Every door the patcher knows of needs one.
+fn door_specification<'a>(config: &'a ParsedConfig, level: usize, room_name: &str, door_index: usize) -> Result<&'a String, String>
+{
+    config.excluded_doors[level].get(room_name)
+        .and_then(|doors| doors.get(door_index))
+        .ok_or_else(|| format!("The config has no door specification for door {} in {}", door_index, room_name))
+}
+
+// Typos in a door specification would otherwise be caught only by an unwrap, so list everything
+// that would have been accepted
+fn unknown_door_specification(door_specification: &str, room_name: &str, door_index: usize) -> String
+{
+    format!(
+        concat!("Unknown door type '{}' for door {} in {}. Expected \"default\", \"random\", ",
+                "the name of a custom door, a blast shield such as \"missile_shield\" or one of: {}"),
+        door_specification,
+        door_index,
+        room_name,
+        DoorType::all_names().join(", "),
+    )
+}
+
+fn is_vertical_door(room_id: u32, door_index: usize) -> bool
+{
+    (room_id == 0x11BD63B7 && door_index == 0) || // Tower Chamber
+    (room_id == 0x0D72F1F7 && door_index == 1) || // Tower of Light
+    (room_id == 0xFB54A0CB && door_index == 4) || // Hall of the Elders
+    (room_id == 0xE1981EFC && door_index == 0) || // Elder Chamber
+    (room_id == 0x43E4CC25 && door_index == 1) || // Research Lab Hydra
+    (room_id == 0x37BBB33C && door_index == 1) || // Observatory Access
+    (room_id == 0xD8E905DD && door_index == 1) || // Research Core Access
+    (room_id == 0x21B4BFF6 && door_index == 1) || // Research Lab Aether
+    (room_id == 0x3F375ECC && door_index == 2) || // Omega Research
+    (room_id == 0xF517A1EA && door_index == 1) || // Dynamo Access (Careful of Chozo room w/ same name)
+    (room_id == 0x8A97BB54 && door_index == 1) || // Elite Research
+    (room_id == 0xA20201D4                   ) || // Security Access B (both doors)
+    (room_id == 0x956F1552 && door_index == 1) || // Mine Security Station
+    (room_id == 0xC50AF17A && door_index == 2) || // Elite Control
+    (room_id == 0x90709AAC && door_index == 1)    // Ventilation Shaft
+}
+
+// The door type to put on a vertical dock. Every vertical door goes through this so
+// patch_vertical_to_blue is honored the same way everywhere.
+fn vertical_door_type(door_type: DoorType, patch_vertical_to_blue: bool) -> DoorType
+{
+    if patch_vertical_to_blue {
+        DoorType::VerticalBlue
+    } else {
+        door_type.to_vertical()
     }
 }
 
@@ -1849,30 +3077,16 @@ fn calculate_door_type(pak_name: &str, mut rng: &mut StdRng, weights: &Weights)
 }
 */
 fn patch_door<'r>(
-    ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
     door_loc: DoorLocation,
+    door_index: usize,
     door_type: DoorType,
-    blast_shield_type: Option<BlastShieldType>,
     door_resources:&HashMap<(u32, FourCC), structs::Resource<'r>>,
     lockpick: bool,
 ) -> Result<(), String> {
+    let room_id = area.mlvl_area.mrea;
 
-    let mut deps = door_type.dependencies();
-    
-    let new_layer_idx = {
-        if blast_shield_type.is_some() {
-            // Update dependencies //
-            deps.extend_from_slice(&blast_shield_type.unwrap().dependencies());
-
-            // Create new layer to store the new blast shield //
-            area.add_layer(b"Custom Shield Layer\0".as_cstr());
-            area.layer_flags.layer_count as usize - 1
-        } else {
-            0 // unused
-        }
-    };
-
+    let deps = door_type.dependencies();
     let deps_iter = deps.iter()
     .map(|&(file_id, fourcc)| structs::Dependency {
             asset_id: file_id,
@@ -1880,283 +3094,414 @@ fn patch_door<'r>(
     });
     area.add_dependencies(&door_resources,0,deps_iter);
 
-    let area_internal_id = area.mlvl_area.internal_id;
     let scly = area.mrea().scly_section_mut();
     let layers = &mut scly.layers.as_mut_vec();
 
     let door_force = layers[0].objects.iter_mut()
         .find(|obj| obj.instance_id == door_loc.door_force_location.instance_id)
         .and_then(|obj| obj.property_data.as_damageable_trigger_mut())
-        .unwrap();
+        .ok_or_else(|| format!("Failed to find the forcefield of {} door {}", room_description(room_id), door_index))?;
     door_force.color_txtr = door_type.forcefield_txtr();
     door_force.damage_vulnerability = door_type.vulnerability();
+    if let Some(visor_mask) = door_type.visor_mask() {
+        door_force.visor_params.unknown2 = visor_mask;
+    }
+    // A directional one way door keeps its forcefield, but only on one side. Without its
+    // forcefield the door can't be shot open from this side, but it still opens when shot from
+    // the room on the other side.
+    if let Some(render_side) = door_type.render_side() {
+        door_force.unknown0 = render_side;
+    } else if door_type.is_one_way() {
+        door_force.active = 0;
+    }
 
     if lockpick {
         door_force.damage_vulnerability.power_bomb = 0x1 as u32;
     }
 
-    if door_loc.door_shield_location.is_some() {
+    if let Some(door_shield_location) = door_loc.door_shield_location {
         let door_shield = layers[0].objects.iter_mut()
-            .find(|obj| obj.instance_id == door_loc.door_shield_location.unwrap().instance_id)
+            .find(|obj| obj.instance_id == door_shield_location.instance_id)
             .and_then(|obj| obj.property_data.as_actor_mut())
-            .unwrap();
+            .ok_or_else(|| format!("Failed to find the shield of {} door {}", room_description(room_id), door_index))?;
         door_shield.cmdl = door_type.shield_cmdl();
+    }
 
-        if blast_shield_type.is_some() {
-            let blast_shield_type = blast_shield_type.unwrap();
-
-            // Calculate placement //
-            let position: GenericArray<f32, U3>;
-            let rotation: GenericArray<f32, U3>;
-            let scale: GenericArray<f32, U3>;
-            let hitbox: GenericArray<f32, U3>;
-            let scan_offset: GenericArray<f32, U3>;
-
-            if door_shield.rotation[2] >= 45.0 && door_shield.rotation[2] < 135.0 {
-                // Leads North
-                position    = [door_shield.position[0], door_shield.position[1] - 0.1, door_shield.position[2] - 1.8017].into();
-                rotation    = [door_shield.rotation[0], door_shield.rotation[1], door_shield.rotation[2]].into();
-                scale       = [1.0, 1.5, 1.5].into();
-                hitbox      = [5.0, 0.875, 4.0].into();
-                scan_offset = [0.0, 0.438, 2.0].into();
-                
-            } else if (door_shield.rotation[2] >= 135.0 && door_shield.rotation[2] < 225.0) || (door_shield.rotation[2] < -135.0 && door_shield.rotation[2] > -225.0) {
-                // Leads East
-                position    = [door_shield.position[0] + 0.1, door_shield.position[1], door_shield.position[2] - 1.8017].into();
-                rotation    = [door_shield.rotation[0], door_shield.rotation[1], 0.0].into();
-                scale       = [1.0, 1.5, 1.5].into();
-                hitbox      = [0.875, 5.0, 4.0].into();
-                scan_offset = [-0.438, 0.0, 2.0].into();
-
-            } else if door_shield.rotation[2] >= -135.0 && door_shield.rotation[2] < -45.0 {
-                // Leads South
-                position    = [door_shield.position[0], door_shield.position[1] + 0.1, door_shield.position[2] - 1.8017].into();
-                rotation    = [door_shield.rotation[0], door_shield.rotation[1], door_shield.rotation[2]].into();
-                scale       = [1.0, 1.5, 1.5].into();
-                hitbox      = [5.0, 0.875, 4.0].into();
-                scan_offset = [0.0, 0.438, 2.0].into();
-
-            } else if door_shield.rotation[2] >= -45.0 && door_shield.rotation[2] < 45.0 {
-                // Leads West
-                position    = [door_shield.position[0] - 0.1, door_shield.position[1], door_shield.position[2] - 1.8017].into();
-                rotation    = [door_shield.rotation[0], door_shield.rotation[1], -179.99].into();
-                scale       = [1.0, 1.5, 1.5].into();
-                hitbox      = [0.875, 5.0, 4.0].into();
-                scan_offset = [0.438, 0.0, 2.0].into();
+    Ok(())
+}
 
-            } else {
-                assert!(false);
-                position    = [0.0, 0.0, 0.0].into();
-                rotation    = [0.0, 0.0, 0.0].into();
-                scale       = [0.0, 0.0, 0.0].into();
-                hitbox      = [0.0, 0.0, 0.0].into();
-                scan_offset = [0.0, 0.0, 0.0].into();
-            }
+// Opens the door when the object sends a connection in the given state. The forcefield is switched
+// off too, as it is when the door is shot open.
+fn patch_door_opener<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    door_loc: DoorLocation,
+    object_id: u32,
+    state: u32,
+) -> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+    let opener = scly.layers.as_mut_vec().iter_mut()
+        .flat_map(|layer| layer.objects.as_mut_vec().iter_mut())
+        .find(|obj| obj.instance_id == object_id)
+        .ok_or_else(|| format!("Failed to find door opener 0x{:X}", object_id))?;
+
+    let connections = opener.connections.as_mut_vec();
+    connections.push(structs::Connection {
+        state: structs::ConnectionState(state),
+        message: structs::ConnectionMsg::OPEN,
+        target_object_id: door_loc.door_location.instance_id,
+    });
+    connections.push(structs::Connection {
+        state: structs::ConnectionState(state),
+        message: structs::ConnectionMsg::DEACTIVATE,
+        target_object_id: door_loc.door_force_location.instance_id,
+    });
 
-            // Create new blast shield actor //
-            let blast_shield_instance_id = ps.fresh_instance_id_range.next().unwrap();
-            let mut blast_shield = structs::SclyObject {
-                instance_id: blast_shield_instance_id,
-                connections: vec![
-                    structs::Connection {
-                        state: structs::ConnectionState::DEAD,
-                        message: structs::ConnectionMsg::DEACTIVATE,
-                        target_object_id: blast_shield_instance_id,
-                    },
-                ].into(),
-                property_data: structs::SclyProperty::Actor(Box::new(
-                    structs::Actor {
-                        name: b"Custom Blast Shield\0".as_cstr(),
-                        position,
-                        rotation,
-                        scale,
-                        hitbox,
-                        scan_offset,
-                        unknown1: 1.0, // mass  
-                        unknown2: 0.0, // momentum
-                        health_info: structs::scly_structs::HealthInfo {
-                            health: 1.0,
-                            knockback_resistance: 1.0,
-                        },
-                        damage_vulnerability: blast_shield_type.vulnerability(),
-                        cmdl: blast_shield_type.cmdl(),
-                        ancs: structs::scly_structs::AncsProp {
-                            file_id: ResId::invalid(),
-                            node_index: 0,
-                            default_animation: 0xFFFFFFFF,
-                        },
-                        actor_params: structs::scly_structs::ActorParameters {
-                            light_params: structs::scly_structs::LightParameters {
-                                unknown0: 1,
-                                unknown1: 1.0,
-                                shadow_tessellation: 0,
-                                unknown2: 1.0,
-                                unknown3: 20.0,
-                                color: [1.0, 1.0, 1.0, 1.0].into(), // RGBA
-                                unknown4: 1,
-                                world_lighting: 1,
-                                light_recalculation: 1,
-                                unknown5: [0.0, 0.0, 0.0].into(),
-                                unknown6: 4,
-                                unknown7: 4,
-                                unknown8: 0,
-                                light_layer_id: 0,
-                            },
-                            scan_params: structs::scly_structs::ScannableParameters {
-                                scan: blast_shield_type.scan(),
-                            },
-                            xray_cmdl: ResId::invalid(),
-                            xray_cskr: ResId::invalid(),
-                            thermal_cmdl: ResId::invalid(),
-                            thermal_cskr: ResId::invalid(),
-                            unknown0: 1,
-                            unknown1: 1.0,
-                            unknown2: 1.0,
-                            visor_params: structs::scly_structs::VisorParameters {
-                                unknown0: 0,
-                                target_passthrough: 0,
-                                visor_mask: 15, // Visor Flags : Combat|Scan|Thermal|XRay
-                            },
-                            enable_thermal_heat: 0,
-                            unknown3: 0,
-                            unknown4: 0,
-                            unknown5: 1.0,
-                        },
-                        looping: 1,
-                        snow: 1, // immovable
-                        solid: 1,
-                        camera_passthrough: 0,
-                        active: 1,
+    Ok(())
+}
+
+// Applied after patch_door to put a blast shield in front of the door. The shield, and
+// everything that goes with it, lives on its own layer which is switched off once the shield
+// is destroyed so it doesn't come back when the room is reloaded.
+fn patch_blast_shield<'r>(
+    ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    door_loc: DoorLocation,
+    shield_type: BlastShieldType,
+    door_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+) -> Result<(), String>
+{
+    let door_shield_location = door_loc.door_shield_location
+        .ok_or_else(|| format!("Door 0x{:X} has no shield to put a blast shield in front of",
+            door_loc.door_location.instance_id))?;
+
+    let deps = shield_type.dependencies();
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: fourcc,
+        });
+    area.add_dependencies(door_resources, 0, deps_iter);
+
+    area.add_layer(b"Custom Shield Layer\0".as_cstr())?;
+    let new_layer_idx = area.layer_flags.layer_count as usize - 1;
+
+    let area_internal_id = area.mlvl_area.internal_id;
+    let scly = area.mrea().scly_section_mut();
+    let layers = &mut scly.layers.as_mut_vec();
+
+    let (door_position, door_rotation) = layers[0].objects.iter_mut()
+        .find(|obj| obj.instance_id == door_shield_location.instance_id)
+        .and_then(|obj| obj.property_data.as_actor_mut())
+        .map(|door_shield| (door_shield.position, door_shield.rotation))
+        .ok_or_else(|| format!("Failed to find door shield 0x{:X}", door_shield_location.instance_id))?;
+
+    // Calculate placement from the direction the door faces //
+    let position: GenericArray<f32, U3>;
+    let rotation: GenericArray<f32, U3>;
+    let hitbox: GenericArray<f32, U3>;
+    let scan_offset: GenericArray<f32, U3>;
+    if door_rotation[2] >= 45.0 && door_rotation[2] < 135.0 {
+        // Leads North
+        position    = [door_position[0], door_position[1] - 0.1, door_position[2] - 1.8017].into();
+        rotation    = [door_rotation[0], door_rotation[1], door_rotation[2]].into();
+        hitbox      = [5.0, 0.875, 4.0].into();
+        scan_offset = [0.0, 0.438, 2.0].into();
+    } else if (door_rotation[2] >= 135.0 && door_rotation[2] < 225.0) || (door_rotation[2] < -135.0 && door_rotation[2] > -225.0) {
+        // Leads East
+        position    = [door_position[0] + 0.1, door_position[1], door_position[2] - 1.8017].into();
+        rotation    = [door_rotation[0], door_rotation[1], 0.0].into();
+        hitbox      = [0.875, 5.0, 4.0].into();
+        scan_offset = [-0.438, 0.0, 2.0].into();
+    } else if door_rotation[2] >= -135.0 && door_rotation[2] < -45.0 {
+        // Leads South
+        position    = [door_position[0], door_position[1] + 0.1, door_position[2] - 1.8017].into();
+        rotation    = [door_rotation[0], door_rotation[1], door_rotation[2]].into();
+        hitbox      = [5.0, 0.875, 4.0].into();
+        scan_offset = [0.0, 0.438, 2.0].into();
+    } else if door_rotation[2] >= -45.0 && door_rotation[2] < 45.0 {
+        // Leads West
+        position    = [door_position[0] - 0.1, door_position[1], door_position[2] - 1.8017].into();
+        rotation    = [door_rotation[0], door_rotation[1], -179.99].into();
+        hitbox      = [0.875, 5.0, 4.0].into();
+        scan_offset = [0.438, 0.0, 2.0].into();
+    } else {
+        return Err(format!("Door 0x{:X} has an unexpected rotation of {}", door_loc.door_location.instance_id, door_rotation[2]));
+    }
+
+    let blast_shield_id = ps.fresh_instance_id_range.next().unwrap();
+    let damageable_trigger_id = ps.fresh_instance_id_range.next().unwrap();
+    let special_function_id = ps.fresh_instance_id_range.next().unwrap();
+    let sound_id = ps.fresh_instance_id_range.next().unwrap();
+    let streamed_audio_id = ps.fresh_instance_id_range.next().unwrap();
+
+    // The shield itself is only for show and can't be damaged. It's the trigger wrapped around
+    // it that takes the hits.
+    let blast_shield = structs::SclyObject {
+        instance_id: blast_shield_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::Actor(
+            structs::Actor {
+                name: b"Custom Blast Shield\0".as_cstr(),
+                position,
+                rotation,
+                scale: [1.0, 1.5, 1.5].into(),
+                hitbox,
+                scan_offset,
+                unknown1: 1.0, // mass
+                unknown2: 0.0, // momentum
+                health_info: structs::structs::HealthInfo {
+                    health: 1.0,
+                    knockback_resistance: 1.0,
+                },
+                damage_vulnerability: DoorType::Disabled.vulnerability(),
+                cmdl: shield_type.cmdl(),
+                ancs: structs::structs::AncsProp {
+                    file_id: 0xFFFFFFFF, // None
+                    node_index: 0,
+                    unknown: 0xFFFFFFFF, // -1
+                },
+                actor_params: structs::structs::ActorParameters {
+                    light_params: structs::structs::LightParameters {
+                        unknown0: 1,
+                        unknown1: 1.0,
+                        shadow_tessellation: 0,
+                        unknown2: 1.0,
+                        unknown3: 20.0,
+                        color: [1.0, 1.0, 1.0, 1.0].into(), // RGBA
+                        unknown4: 1,
+                        world_lighting: 1,
+                        light_recalculation: 1,
+                        unknown5: [0.0, 0.0, 0.0].into(),
+                        unknown6: 4,
+                        unknown7: 4,
                         unknown8: 0,
-                        unknown9: 1.0,
-                        unknown10: 0,
-                        unknown11: 0,
-                        unknown12: 0,
-                        unknown13: 0,
-                    }
-                )),
-            };
+                        light_layer_id: 0,
+                    },
+                    scan_params: structs::structs::ScannableParameters {
+                        scan: shield_type.scan(),
+                    },
+                    xray_cmdl: 0xFFFFFFFF,
+                    xray_cskr: 0xFFFFFFFF,
+                    thermal_cmdl: 0xFFFFFFFF,
+                    thermal_cskr: 0xFFFFFFFF,
+                    unknown0: 1,
+                    unknown1: 1.0,
+                    unknown2: 1.0,
+                    visor_params: structs::structs::VisorParameters {
+                        unknown0: 0,
+                        target_passthrough: 0,
+                        unknown2: 15, // Visor Flags : Combat|Scan|Thermal|XRay
+                    },
+                    enable_thermal_heat: 0,
+                    unknown3: 0,
+                    unknown4: 0,
+                    unknown5: 1.0,
+                },
+                looping: 1,
+                snow: 1, // immovable
+                solid: 1,
+                camera_passthrough: 0,
+                active: 1,
+                unknown8: 0,
+                unknown9: 1.0,
+                unknown10: 0,
+                unknown11: 0,
+                unknown12: 0,
+                unknown13: 0,
+            }
+        ),
+    };
 
-            // Create Special Function to disable layer once shield is destroyed
-            // This is needed because otherwise the shield would re-appear every
-            // time the room is loaded
-            let special_function = structs::SclyObject {
-                instance_id: ps.fresh_instance_id_range.next().unwrap(),
-                connections: vec![].into(),
-                property_data: structs::SclyProperty::SpecialFunction(Box::new(
-                    structs::SpecialFunction {
-                        name: b"myspecialfun\0".as_cstr(),
-                        position: [0., 0., 0.].into(),
-                        rotation: [0., 0., 0.].into(),
-                        type_: 16, // layer change
-                        unknown0: b"\0".as_cstr(),
-                        unknown1: 0.,
-                        unknown2: 0.,
-                        unknown3: 0.,
-                        layer_change_room_id: area_internal_id,
-                        layer_change_layer_id: new_layer_idx as u32,
-                        item_id: 0,
-                        unknown4: 1, // active
-                        unknown5: 0.,
-                        unknown6: 0xFFFFFFFF,
-                        unknown7: 0xFFFFFFFF,
-                        unknown8: 0xFFFFFFFF,
-                    }
-                )),
-            };
+    let damageable_trigger = structs::SclyObject {
+        instance_id: damageable_trigger_id,
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::DEAD,
+                message: structs::ConnectionMsg::DEACTIVATE,
+                target_object_id: blast_shield_id,
+            },
+            // Switch off the shield layer so the shield stays gone
+            structs::Connection {
+                state: structs::ConnectionState::DEAD,
+                message: structs::ConnectionMsg::DECREMENT,
+                target_object_id: special_function_id,
+            },
+            structs::Connection {
+                state: structs::ConnectionState::DEAD,
+                message: structs::ConnectionMsg::PLAY,
+                target_object_id: sound_id,
+            },
+            structs::Connection {
+                state: structs::ConnectionState::DEAD,
+                message: structs::ConnectionMsg::PLAY,
+                target_object_id: streamed_audio_id,
+            },
+            // The door itself is left as it was, so it opens as soon as the shield is gone
+            structs::Connection {
+                state: structs::ConnectionState::DEAD,
+                message: structs::ConnectionMsg::ACTIVATE,
+                target_object_id: door_loc.door_force_location.instance_id,
+            },
+        ].into(),
+        property_data: structs::SclyProperty::DamageableTrigger(
+            structs::DamageableTrigger {
+                name: b"Custom Blast Shield Trigger\0".as_cstr(),
+                position: [
+                    position[0] + scan_offset[0],
+                    position[1] + scan_offset[1],
+                    position[2] + scan_offset[2],
+                ].into(),
+                scale: hitbox,
+                health_info: structs::structs::HealthInfo {
+                    health: 1.0,
+                    knockback_resistance: 1.0,
+                },
+                damage_vulnerability: shield_type.vulnerability(),
+                unknown0: 0, // Render Side : None
+                pattern_txtr0: 0xFFFFFFFF,
+                pattern_txtr1: 0xFFFFFFFF,
+                color_txtr: 0xFFFFFFFF,
+                lock_on: 0,
+                active: 1,
+                visor_params: structs::structs::VisorParameters {
+                    unknown0: 0,
+                    target_passthrough: 0,
+                    unknown2: 15, // Visor Flags : Combat|Scan|Thermal|XRay
+                },
+            }
+        ),
+    };
 
-            // Activate the layer change when blast shield is destroyed
-            blast_shield.connections.as_mut_vec().push(
-                structs::Connection {
-                    state: structs::ConnectionState::DEAD,
-                    message: structs::ConnectionMsg::DECREMENT,
-                    target_object_id: special_function.instance_id,
-                }
-            );
+    let special_function = structs::SclyObject {
+        instance_id: special_function_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::SpecialFunction(
+            structs::SpecialFunction {
+                name: b"Blast Shield Layer Switch\0".as_cstr(),
+                position: [0., 0., 0.].into(),
+                rotation: [0., 0., 0.].into(),
+                type_: 16, // layer change
+                unknown0: b"\0".as_cstr(),
+                unknown1: 0.,
+                unknown2: 0.,
+                unknown3: 0.,
+                layer_change_room_id: area_internal_id,
+                layer_change_layer_id: new_layer_idx as u32,
+                item_id: 0,
+                unknown4: 1, // active
+                unknown5: 0.,
+                unknown6: 0xFFFFFFFF,
+                unknown7: 0xFFFFFFFF,
+                unknown8: 0xFFFFFFFF,
+            }
+        ),
+    };
 
-            // Create Gibbs and activate on DEAD //
-            // TODO: It's possible, but there's so many goddam dependencies
+    // explosion sfx
+    let sound = structs::SclyObject {
+        instance_id: sound_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::Sound(
+            structs::Sound { // copied from main plaza half-pipe
+                name: b"mysound\0".as_cstr(),
+                position,
+                rotation: [0.0,0.0,0.0].into(),
+                sound_id: 3621,
+                active: 1,
+                max_dist: 100.0,
+                dist_comp: 0.2,
+                start_delay: 0.0,
+                min_volume: 20,
+                volume: 127,
+                priority: 127,
+                pan: 64,
+                loops: 0,
+                non_emitter: 0,
+                auto_start: 0,
+                occlusion_test: 0,
+                acoustics: 1,
+                world_sfx: 0,
+                allow_duplicates: 0,
+                pitch: 0,
+            }
+        ),
+    };
 
-            // Create camera shake and activate on DEAD //
-            // TODO: It's possible, I'm just lazy
-            
-            // Create explosion sfx //
-            let sound = structs::SclyObject {
-                instance_id: ps.fresh_instance_id_range.next().unwrap(),
-                connections: vec![].into(),
-                property_data: structs::SclyProperty::Sound(Box::new(
-                    structs::Sound { // copied from main plaza half-pipe
-                        name: b"mysound\0".as_cstr(),
-                        position: [
-                            position[0],
-                            position[1],
-                            position[2],
-                        ].into(),
-                        rotation: [0.0,0.0,0.0].into(),
-                        sound_id: 3621,
-                        active: 1,
-                        max_dist: 100.0,
-                        dist_comp: 0.2,
-                        start_delay: 0.0,
-                        min_volume: 20,
-                        volume: 127,
-                        priority: 127,
-                        pan: 64,
-                        loops: 0,
-                        non_emitter: 0,
-                        auto_start: 0,
-                        occlusion_test: 0,
-                        acoustics: 1,
-                        world_sfx: 0,
-                        allow_duplicates: 0,
-                        pitch: 0,
-                    }
-                ))
-            };
+    // "You did it" jingle
+    let streamed_audio = structs::SclyObject {
+        instance_id: streamed_audio_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::StreamedAudio(
+            structs::StreamedAudio {
+                name: b"mystreamedaudio\0".as_cstr(),
+                active: 1,
+                audio_file_name: b"/audio/evt_x_event_00.dsp\0".as_cstr(),
+                no_stop_on_deactivate: 0,
+                fade_in_time: 0.0,
+                fade_out_time: 0.0,
+                volume: 92,
+                oneshot: 1,
+                is_music: 1,
+            }
+        ),
+    };
 
-            // Blast shield triggers explosion sfx when dead //
-            blast_shield.connections.as_mut_vec().push(
-                structs::Connection {
-                    state: structs::ConnectionState::DEAD,
-                    message: structs::ConnectionMsg::PLAY,
-                    target_object_id: sound.instance_id,
-                }
-            );
+    layers[new_layer_idx].objects.as_mut_vec().extend_from_slice(&[
+        special_function,
+        streamed_audio,
+        sound,
+        blast_shield,
+        damageable_trigger,
+    ]);
 
-            // Create "You did it" Jingle //
-            let streamed_audio = structs::SclyObject {
-                instance_id: ps.fresh_instance_id_range.next().unwrap(),
-                connections: vec![].into(),
-                property_data: structs::SclyProperty::StreamedAudio(Box::new(
-                    structs::StreamedAudio {
-                        name: b"mystreamedaudio\0".as_cstr(),
-                        active: 1,
-                        audio_file_name: b"/audio/evt_x_event_00.dsp\0".as_cstr(),
-                        no_stop_on_deactivate: 0,
-                        fade_in_time: 0.0,
-                        fade_out_time: 0.0,
-                        volume: 92,
-                        oneshot: 1,
-                        is_music: 1,
-                    }
-                )),
-            };
+    Ok(())
+}
 
-            // Blast shield triggers jingle when dead //
-            blast_shield.connections.as_mut_vec().push(
-                structs::Connection {
-                    state: structs::ConnectionState::DEAD,
-                    message: structs::ConnectionMsg::PLAY,
-                    target_object_id: streamed_audio.instance_id,
-                }
-            );
+// Applied after patch_door to swap in a user supplied door color
+fn patch_custom_door<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    door_loc: DoorLocation,
+    door_index: usize,
+    custom_door: &CustomDoorType,
+    is_vertical: bool,
+    door_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    lockpick: bool,
+) -> Result<(), String>
+{
+    let room_id = area.mlvl_area.mrea;
+    let cmdl = if is_vertical {
+        custom_door.vertical_cmdl.ok_or("Custom door type has no vertical model")?
+    } else {
+        custom_door.cmdl
+    };
+    let deps = [(cmdl, b"CMDL"), (custom_door.txtr, b"TXTR")];
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: fourcc.into(),
+        });
+    area.add_dependencies(door_resources, 0, deps_iter);
 
-            // add new script objects to layer //
-            layers[new_layer_idx].objects.as_mut_vec().push(special_function);
-            layers[new_layer_idx].objects.as_mut_vec().push(streamed_audio);
-            layers[new_layer_idx].objects.as_mut_vec().push(sound);
-            layers[new_layer_idx].objects.as_mut_vec().push(blast_shield);
-        }
+    let scly = area.mrea().scly_section_mut();
+    let layer = &mut scly.layers.as_mut_vec()[0];
+
+    let door_force = layer.objects.iter_mut()
+        .find(|obj| obj.instance_id == door_loc.door_force_location.instance_id)
+        .and_then(|obj| obj.property_data.as_damageable_trigger_mut())
+        .ok_or_else(|| format!("Failed to find the forcefield of {} door {}", room_description(room_id), door_index))?;
+    door_force.color_txtr = custom_door.txtr;
+    door_force.damage_vulnerability = custom_door.vulnerability.clone();
+    if lockpick {
+        door_force.damage_vulnerability.power_bomb = 1;
+    }
+
+    if let Some(door_shield_location) = door_loc.door_shield_location {
+        let door_shield = layer.objects.iter_mut()
+            .find(|obj| obj.instance_id == door_shield_location.instance_id)
+            .and_then(|obj| obj.property_data.as_actor_mut())
+            .ok_or_else(|| format!("Failed to find the shield of {} door {}", room_description(room_id), door_index))?;
+        door_shield.cmdl = cmdl;
     }
 
     Ok(())
@@ -2165,7 +3510,7 @@ fn patch_door<'r>(
 fn patch_map_door_icon(
     res: &mut structs::Resource,
     door: DoorLocation,
-    door_type: DoorType,
+    map_object_type: u32,
 ) -> Result<(), String>
 {
     let mapa = res.kind.as_mapa_mut().unwrap();
@@ -2175,46 +3520,92 @@ fn patch_map_door_icon(
         .unwrap();
     
     if !door_icon.is_vertical() {
-        door_icon.type_ = door_type.map_object_type();
+        door_icon.type_ = map_object_type;
     };
 
     Ok(())
 }
 
-fn fix_artifact_of_truth_requirements(
-    ps: &mut PatcherState,
-    area: &mut mlvl_wrapper::MlvlArea,
-    pickup_layout: &[PickupType],
-) -> Result<(), String>
+// Draw the room and its map icons without the player having visited it or used a map station.
+// This is done for every room, including ones the layout makes unreachable; those show up on the
+// map like any other room, the same as they would after using the area's map station.
+fn patch_reveal_map(res: &mut structs::Resource) -> Result<(), String>
 {
-    let truth_req_layer_id = area.layer_flags.layer_count;
-    // assert_eq!(truth_req_layer_id, ARTIFACT_OF_TRUTH_REQ_LAYER);
+    let mapa = res.kind.as_mapa_mut().unwrap();
+    mapa.visibility_mode = structs::MapaVisibilityMode::Always as u32;
 
-    // Create a new layer that will be toggled on when the Artifact of Truth is collected
-    area.add_layer(b"Randomizer - Got Artifact 1\0".as_cstr());
+    // Objects the game never draws (e.g. unused doors) stay hidden
+    for obj in mapa.objects.iter_mut() {
+        if obj.visibility_mode != structs::MapaVisibilityMode::Never as u32 {
+            obj.visibility_mode = structs::MapaVisibilityMode::Always as u32;
+        }
+    }
 
-    let at_pickup_kind = pickup_layout[63].pickup_data().kind;
-    for i in 0..12 {
-        let layer_number = if i == 0 {
-            truth_req_layer_id
-        } else {
-            i + 1
-        };
-        let kind = i + 29;
-        let exists = pickup_layout.iter()
-            .any(|pt| kind == pt.pickup_data().kind);
+    Ok(())
+}
+
+// The Artifact Temple layer that is activated when each artifact is collected, by pickup kind.
+// The Artifact of Truth has no layer in the vanilla game, so it uses the layer added by
+// fix_artifact_of_truth_requirements instead (None).
+const ARTIFACT_TEMPLE_LAYERS: [(u32, Option<u32>); 12] = [
+    (29, None),     // Truth
+    (30, Some(2)),  // Strength
+    (31, Some(3)),  // Elder
+    (32, Some(4)),  // Wild
+    (33, Some(5)),  // Lifegiver
+    (34, Some(6)),  // Warrior
+    (35, Some(7)),  // Chozo
+    (36, Some(8)),  // Nature
+    (37, Some(9)),  // Sun
+    (38, Some(10)), // World
+    (39, Some(11)), // Spirit
+    (40, Some(12)), // Newborn
+];
+
+fn artifact_temple_layer_flags(
+    mut flags: u64,
+    truth_req_layer_id: u32,
+    pickup_kinds: &[u32],
+    at_pickup_kind: u32,
+) -> u64
+{
+    for &(kind, layer) in ARTIFACT_TEMPLE_LAYERS.iter() {
+        let layer_number = layer.unwrap_or(truth_req_layer_id);
+        let exists = pickup_kinds.contains(&kind);
         if exists && at_pickup_kind != kind {
             // If the artifact exsts, but is not the artifact at the Artifact Temple, mark this
             // layer as inactive. It will be activated when the item is collected.
-            area.layer_flags.flags &= !(1 << layer_number);
+            flags &= !(1 << layer_number);
         } else {
             // Either the artifact doesn't exist or it does and it is in the Artifact Temple, so
             // mark this layer as active. In the former case, it needs to always be active since it
             // will never be collect and in the latter case it needs to be active so the Ridley
             // fight can start immediately if its the last artifact collected.
-            area.layer_flags.flags |= 1 << layer_number;
+            flags |= 1 << layer_number;
         }
     }
+    flags
+}
+
+fn fix_artifact_of_truth_requirements(
+    ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    pickup_layout: &[PickupType],
+) -> Result<(), String>
+{
+    let truth_req_layer_id = area.layer_flags.layer_count;
+    // assert_eq!(truth_req_layer_id, ARTIFACT_OF_TRUTH_REQ_LAYER);
+
+    // Create a new layer that will be toggled on when the Artifact of Truth is collected
+    area.add_layer(b"Randomizer - Got Artifact 1\0".as_cstr())?;
+
+    let pickup_kinds: Vec<u32> = pickup_layout.iter().map(|pt| pt.pickup_data().kind).collect();
+    area.layer_flags.flags = artifact_temple_layer_flags(
+        area.layer_flags.flags,
+        truth_req_layer_id,
+        &pickup_kinds,
+        pickup_layout[63].pickup_data().kind,
+    );
 
     let scly = area.mrea().scly_section_mut();
 
@@ -2249,6 +3640,89 @@ fn fix_artifact_of_truth_requirements(
     Ok(())
 }
 
+// Fire "Relay One Shot Out", which the temple's intro ends with, the first time the room loads.
+// The artifact hints and the Ridley fight are driven from it, so neither has to wait for the
+// intro. A memory relay remembers that it has fired and turns the timer off on later loads.
+fn patch_artifact_temple_skip_intro(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+) -> Result<(), String>
+{
+    const INTRO_RELAY_ID: u32 = 1048956; // "Relay One Shot Out"
+
+    let scly = area.mrea().scly_section_mut();
+    if !scly.layers.as_mut_vec()[0].objects.iter().any(|obj| obj.instance_id == INTRO_RELAY_ID) {
+        Err("Failed to find the Artifact Temple intro relay".to_string())?
+    }
+
+    // The game only sends a memory relay's messages to objects whose ids are in the relay's own
+    // area, so the new objects can't come from fresh_instance_id_range
+    let area_bits = INTRO_RELAY_ID & 0x03FF0000;
+    let last_id = scly.layers.iter()
+        .filter_map(|layer| {
+            layer.objects.iter()
+                .map(|obj| obj.instance_id)
+                .filter(|id| id & 0x03FF0000 == area_bits)
+                .map(|id| id & 0xFFFF)
+                .max()
+        })
+        .max()
+        .unwrap_or(0);
+    if last_id + 2 > 0xFFFF {
+        Err("No free instance ids left in the Artifact Temple".to_string())?
+    }
+    let timer_id = area_bits | (last_id + 1);
+    let memory_relay_id = area_bits | (last_id + 2);
+
+    let layer = &mut scly.layers.as_mut_vec()[0];
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: timer_id,
+        property_data: structs::SclyProperty::Timer(structs::Timer {
+            name: b"Skip intro timer\0".as_cstr(),
+
+            start_time: 0.001,
+            max_random_add: 0f32,
+            reset_to_zero: 0,
+            start_immediately: 1,
+            active: 1,
+        }),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::SET_TO_ZERO,
+                target_object_id: INTRO_RELAY_ID,
+            },
+            structs::Connection {
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::ACTIVATE,
+                target_object_id: memory_relay_id,
+            },
+        ].into(),
+    });
+    layer.objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: memory_relay_id,
+        property_data: structs::SclyProperty::MemoryRelay(structs::MemoryRelay {
+            name: b"Skip intro memory relay\0".as_cstr(),
+            unknown: 0,
+            active: 1,
+        }),
+        connections: vec![].into(),
+    });
+
+    // Once activated, the relay deactivates the timer whenever the room is loaded again
+    let conns = area.memory_relay_conns.as_mut_vec();
+    let pos = conns.iter()
+        .position(|conn| conn.sender_id > memory_relay_id)
+        .unwrap_or(conns.len());
+    conns.insert(pos, structs::MemoryRelayConn {
+        sender_id: memory_relay_id,
+        target_id: timer_id,
+        message: structs::ConnectionMsg::DEACTIVATE.0 as u16,
+        active: 0,
+    });
+    Ok(())
+}
+
 fn patch_artifact_hint_availability(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea,
@@ -2286,15 +3760,31 @@ fn patch_artifact_hint_availability(
                 }
             }));
         },
-        ArtifactHintBehavior::None => {
-            // Remove relays that activate artifact hint objects
+        ArtifactHintBehavior::None | ArtifactHintBehavior::NoHints => {
+            // Remove relays that activate artifact hint objects, and the connections other
+            // objects have to them
             scly.layers.as_mut_vec()[1].objects.as_mut_vec()
                 .retain(|obj| !HINT_RELAY_OBJS.contains(&obj.instance_id));
+            for layer in scly.layers.as_mut_vec().iter_mut() {
+                for obj in layer.objects.as_mut_vec().iter_mut() {
+                    obj.connections.as_mut_vec()
+                        .retain(|conn| !HINT_RELAY_OBJS.contains(&conn.target_object_id));
+                }
+            }
         },
     }
     Ok(())
 }
 
+// The hint system's timed popups pointing the way to the next item are all listed in the one HINT
+// resource, so emptying it leaves the system nothing to show even if it's turned on in the options
+fn patch_remove_hints(res: &mut structs::Resource) -> Result<(), String>
+{
+    let hint = res.kind.as_hint_mut().unwrap();
+    hint.hints.as_mut_vec().clear();
+    Ok(())
+}
+
 fn patch_sun_tower_prevent_wild_before_flaahgra(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea
@@ -2353,28 +3843,183 @@ fn patch_sunchamber_prevent_wild_before_flaahgra(
     Ok(())
 }
 
-fn patch_temple_security_station_cutscene_trigger(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+// The `active` flag of the object types that have one
+fn object_active_flag<'a, 'r>(obj: &'a mut structs::SclyObject<'r>) -> Option<&'a mut u8>
+{
+    macro_rules! active_flag {
+        ($($is:ident, $as_mut:ident;)*) => {
+            $(
+            if obj.property_data.$is() {
+                return obj.property_data.$as_mut().map(|props| &mut props.active);
+            }
+            )*
+        };
+    }
+    active_flag! {
+        is_actor, as_actor_mut;
+        is_damageable_trigger, as_damageable_trigger_mut;
+        is_door, as_door_mut;
+        is_hud_memo, as_hud_memo_mut;
+        is_memory_relay, as_memory_relay_mut;
+        is_pickup, as_pickup_mut;
+        is_platform, as_platform_mut;
+        is_player_actor, as_player_actor_mut;
+        is_point_of_interest, as_point_of_interest_mut;
+        is_relay, as_relay_mut;
+        is_sound, as_sound_mut;
+        is_spawn_point, as_spawn_point_mut;
+        is_streamed_audio, as_streamed_audio_mut;
+        is_timer, as_timer_mut;
+        is_trigger, as_trigger_mut;
+        is_water, as_water_mut;
+    }
+    None
+}
+
+// Sets the active flag of the objects with these instance ids, whichever layer they're on
+fn set_objects_active(area: &mut mlvl_wrapper::MlvlArea, instance_ids: &[u32], active: bool)
     -> Result<(), String>
 {
+    let room_id = area.mlvl_area.mrea;
     let scly = area.mrea().scly_section_mut();
-    let trigger = scly.layers.iter_mut()
-        .flat_map(|layer| layer.objects.iter_mut())
-        .find(|obj| obj.instance_id == 0x70067)
-        .and_then(|obj| obj.property_data.as_trigger_mut())
-        .unwrap();
-    trigger.active = 0;
+    for &instance_id in instance_ids {
+        let obj = scly.layers.iter_mut()
+            .flat_map(|layer| layer.objects.iter_mut())
+            .find(|obj| obj.instance_id == instance_id)
+            .ok_or_else(|| format!("Room 0x{:08X} has no object 0x{:X}", room_id, instance_id))?;
+        let flag = object_active_flag(obj)
+            .ok_or_else(|| format!("Object 0x{:X} in room 0x{:08X} can't be (de)activated", instance_id, room_id))?;
+        *flag = active as u8;
+    }
+    Ok(())
+}
+
+fn deactivate_objects(area: &mut mlvl_wrapper::MlvlArea, instance_ids: &[u32])
+    -> Result<(), String>
+{
+    set_objects_active(area, instance_ids, false)
+}
 
+// Removes every object from a layer, e.g. one that only holds a cutscene
+fn clear_layer(area: &mut mlvl_wrapper::MlvlArea, layer_idx: usize)
+    -> Result<(), String>
+{
+    let room_id = area.mlvl_area.mrea;
+    let scly = area.mrea().scly_section_mut();
+    let layer_count = scly.layers.len();
+    let layer = scly.layers.as_mut_vec().get_mut(layer_idx)
+        .ok_or_else(|| format!(
+            "Room 0x{:08X} has {} layers, expected at least {}",
+            room_id, layer_count, layer_idx + 1,
+        ))?;
+    layer.objects.as_mut_vec().clear();
     Ok(())
 }
 
-fn patch_ridley_phendrana_shorelines_cinematic(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+// Object types without a struct in structs::scly_props
+const CAMERA_OBJECT_TYPE: u8 = 0x0C;
+const CAMERA_FILTER_KEYFRAME_OBJECT_TYPE: u8 = 0x18;
+
+// Rooms skip_known_cutscenes is applied to, starting with the ones that already needed a targeted
+// cutscene fix. Rerouting a cinematic is only as safe as the room's script, so rooms should be
+// added one at a time.
+const KNOWN_CUTSCENE_ROOMS: &[ResourceInfo] = &[
+    resource_info!("01_over_mainplaza.MREA"), // Landing Site
+    resource_info!("00j_over_hall.MREA"), // Temple Security Station
+    resource_info!("01_ice_plaza.MREA"), // Phendrana Shorelines
+];
+
+// The connections a camera makes when it starts and finishes, with the cinematic-only targets
+// (other cameras' fades and Samus' cutscene stand-in) left out. Chained cameras are followed.
+// Skipping the cinematic fires them all at once, so when the start and the finish both message
+// the same object, only the finish is kept: it's the state the room is left in once the
+// cinematic is over, and a start message fired after it would undo it.
+fn forwarded_camera_connections(
+    cameras: &HashMap<u32, Vec<structs::Connection>>,
+    cinematic_only: &HashSet<u32>,
+    camera_id: u32,
+    visited: &mut HashSet<u32>,
+) -> Vec<structs::Connection>
+{
+    let mut started = vec![];
+    let mut finished = vec![];
+    if !visited.insert(camera_id) {
+        return started;
+    }
+    for conn in cameras[&camera_id].iter() {
+        let forwarded = if conn.state == structs::ConnectionState::ACTIVE {
+            &mut started
+        } else if conn.state == structs::ConnectionState::INACTIVE {
+            &mut finished
+        } else {
+            continue;
+        };
+        if cameras.contains_key(&conn.target_object_id) {
+            if conn.message == structs::ConnectionMsg::ACTIVATE {
+                forwarded.extend(forwarded_camera_connections(cameras, cinematic_only, conn.target_object_id, visited));
+            }
+        } else if !cinematic_only.contains(&conn.target_object_id) {
+            forwarded.push(conn.clone());
+        }
+    }
+    started.retain(|conn| finished.iter().all(|finish| finish.target_object_id != conn.target_object_id));
+    started.extend(finished);
+    started
+}
+
+// Replaces every "activate this camera" connection in the room with the connections the camera
+// would have made, so the cinematic never plays but what it sets in motion still happens
+fn skip_room_cutscenes(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
     -> Result<(), String>
 {
     let scly = area.mrea().scly_section_mut();
-    scly.layers.as_mut_vec()[4].objects.as_mut_vec().clear();
+
+    let mut cameras = HashMap::new();
+    let mut cinematic_only = HashSet::new();
+    for obj in scly.layers.iter_mut().flat_map(|layer| layer.objects.iter_mut()) {
+        let object_type = obj.property_data.object_type();
+        if object_type == CAMERA_OBJECT_TYPE {
+            cameras.insert(obj.instance_id, obj.connections.iter().map(|conn| conn.into_owned()).collect::<Vec<_>>());
+        } else if object_type == CAMERA_FILTER_KEYFRAME_OBJECT_TYPE || obj.property_data.is_player_actor() {
+            cinematic_only.insert(obj.instance_id);
+        }
+    }
+
+    for obj in scly.layers.iter_mut().flat_map(|layer| layer.objects.iter_mut()) {
+        if cameras.contains_key(&obj.instance_id) {
+            continue;
+        }
+        let connections = obj.connections.as_mut_vec();
+        let mut rerouted = vec![];
+        connections.retain(|conn| {
+            if conn.message != structs::ConnectionMsg::ACTIVATE || !cameras.contains_key(&conn.target_object_id) {
+                return true;
+            }
+            let forwarded = forwarded_camera_connections(&cameras, &cinematic_only, conn.target_object_id, &mut HashSet::new());
+            rerouted.extend(forwarded.into_iter().map(|forwarded_conn| structs::Connection {
+                state: conn.state,
+                message: forwarded_conn.message,
+                target_object_id: forwarded_conn.target_object_id,
+            }));
+            false
+        });
+        connections.extend(rerouted);
+    }
     Ok(())
 }
 
+fn patch_temple_security_station_cutscene_trigger(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    deactivate_objects(area, &[0x70067])
+}
+
+fn patch_ridley_phendrana_shorelines_cinematic(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    clear_layer(area, 4) // the Ridley cinematic
+}
+
 fn make_elite_research_fight_prereq_patches(patcher: &mut PrimePatcher)
 {
     patcher.add_scly_patch(resource_info!("03_mines.MREA").into(), |_ps, area| {
@@ -2932,17 +4577,28 @@ fn patch_geothermal_core_door_lock_0_02<'r>(_ps: &mut PatcherState, area: &mut m
     Ok(())
 }
 
-fn patch_hive_totem_boss_trigger_0_02(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+// 1.02 moved the trigger which starts the Hive Totem fight; this puts it back where it is in 1.00.
+// Only 1.02 is affected, so if the trigger can't be found this is some other build and the patch is
+// skipped rather than failing the whole ISO.
+fn patch_hive_totem_boss_trigger_0_02(ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
     -> Result<(), String>
 {
     let scly = area.mrea().scly_section_mut();
-    let layer = &mut scly.layers.as_mut_vec()[1];
     let trigger_obj_id = 0x4240140;
 
-    let trigger_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == trigger_obj_id)
-        .and_then(|obj| obj.property_data.as_trigger_mut())
-        .unwrap();
+    let trigger_obj = scly.layers.as_mut_vec().get_mut(1)
+        .and_then(|layer| {
+            layer.objects.as_mut_vec().iter_mut()
+                .find(|obj| obj.instance_id == trigger_obj_id)
+        })
+        .and_then(|obj| obj.property_data.as_trigger_mut());
+    let trigger_obj = match trigger_obj {
+        Some(trigger_obj) => trigger_obj,
+        None => {
+            ps.warnings.push(format!("Hive Totem trigger 0x{:X} not found, skipping boss trigger fix", trigger_obj_id));
+            return Ok(());
+        },
+    };
     trigger_obj.position = [94.571053, 301.616028, 0.344905].into();
     trigger_obj.scale = [6.052994, 24.659973, 7.878154].into();
 
@@ -3281,6 +4937,114 @@ fn patch_elite_quarters_access(_ps: &mut PatcherState, area: &mut mlvl_wrapper::
     Ok(())
 }
 
+#[derive(Copy, Clone, Debug)]
+enum BossWeapon
+{
+    Power,
+    Missile,
+    Bomb,
+    PowerBomb,
+}
+
+impl BossWeapon
+{
+    fn vulnerability_mut(self, dv: &mut structs::structs::DamageVulnerability) -> &mut u32
+    {
+        match self {
+            BossWeapon::Power => &mut dv.power,
+            BossWeapon::Missile => &mut dv.missile,
+            BossWeapon::Bomb => &mut dv.bomb,
+            BossWeapon::PowerBomb => &mut dv.power_bomb,
+        }
+    }
+}
+
+struct BossVulnerabilityFix
+{
+    pak_name: &'static str,
+    room_id: u32,
+    // The weapon the fight normally requires
+    required: BossWeapon,
+    // Also accepted in its place when boss_item_independence is enabled
+    fallback: BossWeapon,
+}
+
+// Boss arenas where a damageable trigger needs a weapon the randomizer may place after the fight
+const BOSS_VULNERABILITY_FIXES: &[BossVulnerabilityFix] = &[
+    // Flaahgra's weak point
+    BossVulnerabilityFix {
+        pak_name: "Metroid2.pak",
+        room_id: 0x9A0A03EB, // Sunchamber
+        required: BossWeapon::Bomb,
+        fallback: BossWeapon::PowerBomb,
+    },
+    // Hive Mecha
+    BossVulnerabilityFix {
+        pak_name: "Metroid2.pak",
+        room_id: 0xC8309DF6, // Hive Totem
+        required: BossWeapon::Missile,
+        fallback: BossWeapon::Power,
+    },
+    // Phazon Elite
+    BossVulnerabilityFix {
+        pak_name: "metroid5.pak",
+        room_id: 0x8A97BB54, // Elite Research
+        required: BossWeapon::Missile,
+        fallback: BossWeapon::Power,
+    },
+];
+
+// Reflect, immune, pass through and direct immune
+fn takes_damage(vulnerability: u32) -> bool
+{
+    !matches!(vulnerability, 2 | 3 | 4 | 7)
+}
+
+fn patch_boss_vulnerability(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    fix: &BossVulnerabilityFix,
+) -> Result<(), String>
+{
+    // Door force fields are damageable triggers too, and have to keep the lock their door color
+    // gives them. So do the blast shields and other triggers added by the patcher.
+    let door_force_ids: HashSet<u32> = pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|(_, rooms)| rooms.iter())
+        .filter(|room_info| room_info.room_id == fix.room_id)
+        .flat_map(|room_info| room_info.door_locations.iter())
+        .map(|door_loc| door_loc.door_force_location.instance_id)
+        .collect();
+
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            if door_force_ids.contains(&obj.instance_id) || obj.instance_id >= FIRST_FRESH_INSTANCE_ID {
+                continue;
+            }
+            let trigger = match obj.property_data.as_damageable_trigger_mut() {
+                Some(trigger) => trigger,
+                None => continue,
+            };
+            let dv = &mut trigger.damage_vulnerability;
+            let required = *fix.required.vulnerability_mut(dv);
+            if takes_damage(required) && !takes_damage(*fix.fallback.vulnerability_mut(dv)) {
+                *fix.fallback.vulnerability_mut(dv) = required;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn make_boss_item_independence_patches(patcher: &mut PrimePatcher)
+{
+    for fix in BOSS_VULNERABILITY_FIXES {
+        patcher.add_scly_patch(
+            (fix.pak_name.as_bytes(), fix.room_id),
+            move |ps, area| patch_boss_vulnerability(ps, area, fix),
+        );
+    }
+}
+
 /* removed the beams blocking elite quarters, removing the need for plasma beam */
 fn make_patch_elite_quarters_access<'a>(patcher: &mut PrimePatcher<'_, 'a>)
 {
@@ -3601,6 +5365,7 @@ fn patch_deheat_room<'r>(
 fn patch_superheated_room<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    heat_damage_per_sec: f32,
 )
 -> Result<(), String>
 {
@@ -3671,7 +5436,7 @@ fn patch_superheated_room<'r>(
                 rotation: [0., 0., 0.].into(),
                 type_: 18,
                 unknown0: b"\0".as_cstr(),
-                unknown1: 10.0,
+                unknown1: heat_damage_per_sec,
                 unknown2: 0.0,
                 unknown3: 0.0,
                 layer_change_room_id: 4294967295,
@@ -3692,69 +5457,50 @@ fn patch_superheated_room<'r>(
     Ok(())
 }
 
-fn patch_geothermal_core_destructible_rock_pal(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
-    -> Result<(), String>
+// The damage rate of heated rooms lives on each area damage SpecialFunction rather than in the
+// dol, so it's set per room
+fn patch_heat_damage_per_sec<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    heat_damage_per_sec: f32,
+)
+-> Result<(), String>
 {
     let scly = area.mrea().scly_section_mut();
-    let layer = &mut scly.layers.as_mut_vec()[0];
-
-    let platform_obj_id = 0x1403AE;
-    let scan_target_platform_obj_id = 0x1403B4;
-
-    let platform_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == platform_obj_id)
-        .and_then(|obj| obj.property_data.as_platform_mut())
-        .unwrap();
-    platform_obj.active = 0;
-
-    let scan_target_platform_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == scan_target_platform_obj_id)
-        .and_then(|obj| obj.property_data.as_point_of_interest_mut())
-        .unwrap();
-    scan_target_platform_obj.active = 0;
-
+    for layer in scly.layers.iter_mut() {
+        for obj in layer.objects.iter_mut() {
+            if let Some(special_function) = obj.property_data.as_special_function_mut() {
+                if special_function.type_ == 18 { // is area damage type
+                    special_function.unknown1 = heat_damage_per_sec;
+                }
+            }
+        }
+    }
     Ok(())
 }
 
-fn patch_ore_processing_destructible_rock_pal(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+fn patch_geothermal_core_destructible_rock_pal(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
     -> Result<(), String>
 {
-    let scly = area.mrea().scly_section_mut();
-    let layer = &mut scly.layers.as_mut_vec()[0];
-
-    let platform_obj_id = 0x60372;
-    let scan_target_platform_obj_id = 0x60378;
-
-    let platform_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == platform_obj_id)
-        .and_then(|obj| obj.property_data.as_platform_mut())
-        .unwrap();
-    platform_obj.active = 0;
-
-    let scan_target_platform_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == scan_target_platform_obj_id)
-        .and_then(|obj| obj.property_data.as_point_of_interest_mut())
-        .unwrap();
-    scan_target_platform_obj.active = 0;
+    deactivate_objects(area, &[
+        0x1403AE, // platform
+        0x1403B4, // platform scan target
+    ])
+}
 
-    Ok(())
+fn patch_ore_processing_destructible_rock_pal(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    deactivate_objects(area, &[
+        0x60372, // platform
+        0x60378, // platform scan target
+    ])
 }
 
 fn patch_main_quarry_door_lock_pal(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
     -> Result<(), String>
 {
-    let scly = area.mrea().scly_section_mut();
-    let layer = &mut scly.layers.as_mut_vec()[7];
-
-    let locked_door_actor_obj_id = 0x1c0205db;
-
-    let locked_door_actor_obj = layer.objects.as_mut_vec().iter_mut()
-        .find(|obj| obj.instance_id == locked_door_actor_obj_id)
-        .and_then(|obj| obj.property_data.as_actor_mut())
-        .unwrap();
-    locked_door_actor_obj.active = 0;
-
-    Ok(())
+    deactivate_objects(area, &[0x1c0205db]) // locked door actor
 }
 
 fn patch_mines_security_station_soft_lock<'r>(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
@@ -3791,6 +5537,32 @@ fn patch_gravity_chamber_stalactite_grapple_point<'r>(_ps: &mut PatcherState, ar
     Ok(())
 }
 
+// The upper pickup sits on a ledge that can only be reached with the grapple point, even
+// with the stalactite fix. Drop it into the pool next to the lower pickup, which can
+// always be reached by walking in from either door.
+fn patch_gravity_chamber_ledge_item(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+    let layer = &mut scly.layers.as_mut_vec()[0];
+
+    let lower_position = layer.objects.iter_mut()
+        .find(|obj| obj.instance_id == 3473439)
+        .and_then(|obj| obj.property_data.as_pickup_mut())
+        .map(|pickup| pickup.position)
+        .ok_or_else(|| "Gravity Chamber lower pickup not found".to_string())?;
+
+    let ledge_pickup = layer.objects.iter_mut()
+        .find(|obj| obj.instance_id == 3473708)
+        .and_then(|obj| obj.property_data.as_pickup_mut())
+        .ok_or_else(|| "Gravity Chamber ledge pickup not found".to_string())?;
+
+    // Slightly above the lower pickup so the two don't overlap
+    ledge_pickup.position = [lower_position[0], lower_position[1], lower_position[2] + 2.0].into();
+
+    Ok(())
+}
+
 fn patch_main_strg(res: &mut structs::Resource, msg: &str) -> Result<(), String>
 {
     let strings = res.kind.as_strg_mut().unwrap()
@@ -3811,8 +5583,46 @@ fn patch_main_strg(res: &mut structs::Resource, msg: &str) -> Result<(), String>
     Ok(())
 }
 
-fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String>
+// The seed is added to the file select screen's message rather than shown in game, as it stays
+// on screen there for as long as the player wants
+fn main_menu_message_with_seed(config: &ParsedConfig) -> String
 {
+    if !config.show_seed_ingame {
+        return config.main_menu_message.clone();
+    }
+    let seed = format!("Seed {} - {:016X}", config.seed, config.item_seed);
+    if config.main_menu_message.is_empty() {
+        seed
+    } else {
+        format!("{}\n{}", config.main_menu_message, seed)
+    }
+}
+
+// Roughly how many characters of the main menu font fit on one line of the message's text pane
+const MAIN_MENU_MESSAGE_LINE_CHARS: usize = 36;
+
+// The number of lines the main menu message takes up once long lines are word wrapped
+fn main_menu_message_lines(msg: &str) -> usize
+{
+    msg.lines()
+        .map(|line| {
+            let chars = line.chars().count();
+            std::cmp::max(1, chars.div_ceil(MAIN_MENU_MESSAGE_LINE_CHARS))
+        })
+        .sum()
+}
+
+fn patch_main_menu(res: &mut structs::Resource, msg: &str) -> Result<(), String>
+{
+    // Nothing to show, so leave the vanilla menu alone. The REL skips missing text panes.
+    if msg.is_empty() {
+        return Ok(());
+    }
+
+    // The vanilla-sized pane holds two lines, so only grow it for longer messages
+    let line_count = std::cmp::max(2, main_menu_message_lines(msg));
+    let height_scale = line_count as f32 / 2.0;
+
     let frme = res.kind.as_frme_mut().unwrap();
 
     frme.widgets.as_mut_vec().push(structs::FrmeWidget {
@@ -3827,20 +5637,20 @@ fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String>
         kind: structs::FrmeWidgetKind::TextPane(
             structs::TextPaneWidget {
                 x_dim: 10.455326,
-                z_dim: 1.813613,
+                z_dim: 1.813613 * height_scale,
                 scale_center: [
                     -5.227663,
                     0.0,
                     -0.51,
                 ].into(),
                 font: 3265024497,
-                word_wrap: 0,
+                word_wrap: 1,
                 horizontal: 1,
                 justification: 0,
                 vertical_justification: 0,
                 fill_color: [1.0, 1.0, 1.0, 1.0].into(),
                 outline_color: [0.0, 0.0, 0.0, 1.0].into(),
-                block_extent: [213.0, 38.0].into(),
+                block_extent: [213.0, 38.0 * height_scale].into(),
                 jpn_font: None,
                 jpn_point_scale: None,
             },
@@ -3938,13 +5748,14 @@ fn patch_credits(res: &mut structs::Resource, pickup_layout: &[PickupType])
 
 fn patch_starting_pickups(
     area: &mut mlvl_wrapper::MlvlArea,
-    mut starting_items: u64,
+    starting_items: StartingItems,
     debug_print: bool,
 ) -> Result<(), String>
 {
-
+    let room_id = area.mlvl_area.mrea;
     let scly = area.mrea().scly_section_mut();
     let mut first = debug_print;
+    let mut spawn_point_count = 0;
     macro_rules! print_maybe {
         ($first:ident, $($tts:tt)*) => {
             if $first {
@@ -3961,86 +5772,87 @@ fn patch_starting_pickups(
                 continue;
             };
 
-            let mut fetch_bits = move |bits: u8| {
-                let ret = starting_items & ((1 << bits) - 1);
-                starting_items >>= bits;
-                ret as u32
-            };
-
             print_maybe!(first, "Starting pickups set:");
 
-            spawn_point.scan_visor = fetch_bits(1);
+            spawn_point.scan_visor = starting_items.scan_visor as u32;
             print_maybe!(first, "    scan_visor: {}", spawn_point.scan_visor);
 
-            spawn_point.missiles = fetch_bits(8);
+            spawn_point.missiles = starting_items.missiles as u32;
             print_maybe!(first, "    missiles: {}", spawn_point.missiles);
 
-            spawn_point.energy_tanks = fetch_bits(4);
+            spawn_point.energy_tanks = starting_items.energy_tanks as u32;
             print_maybe!(first, "    energy_tanks: {}", spawn_point.energy_tanks);
 
-            spawn_point.power_bombs = fetch_bits(4);
+            spawn_point.power_bombs = starting_items.power_bombs as u32;
             print_maybe!(first, "    power_bombs: {}", spawn_point.power_bombs);
 
-            spawn_point.wave = fetch_bits(1);
+            spawn_point.wave = starting_items.wave as u32;
             print_maybe!(first, "    wave: {}", spawn_point.wave);
 
-            spawn_point.ice = fetch_bits(1);
+            spawn_point.ice = starting_items.ice as u32;
             print_maybe!(first, "    ice: {}", spawn_point.ice);
 
-            spawn_point.plasma = fetch_bits(1);
+            spawn_point.plasma = starting_items.plasma as u32;
             print_maybe!(first, "    plasma: {}", spawn_point.plasma);
 
-            spawn_point.charge = fetch_bits(1);
+            spawn_point.charge = starting_items.charge as u32;
             print_maybe!(first, "    charge: {}", spawn_point.charge);
 
-            spawn_point.morph_ball = fetch_bits(1);
+            spawn_point.morph_ball = starting_items.morph_ball as u32;
             print_maybe!(first, "    morph_ball: {}", spawn_point.morph_ball);
 
-            spawn_point.bombs = fetch_bits(1);
+            spawn_point.bombs = starting_items.bombs as u32;
             print_maybe!(first, "    bombs: {}", spawn_point.bombs);
 
-            spawn_point.spider_ball = fetch_bits(1);
+            spawn_point.spider_ball = starting_items.spider_ball as u32;
             print_maybe!(first, "    spider_ball: {}", spawn_point.spider_ball);
 
-            spawn_point.boost_ball = fetch_bits(1);
+            spawn_point.boost_ball = starting_items.boost_ball as u32;
             print_maybe!(first, "    boost_ball: {}", spawn_point.boost_ball);
 
-            spawn_point.varia_suit = fetch_bits(1);
+            spawn_point.varia_suit = starting_items.varia_suit as u32;
             print_maybe!(first, "    varia_suit: {}", spawn_point.varia_suit);
 
-            spawn_point.gravity_suit = fetch_bits(1);
+            spawn_point.gravity_suit = starting_items.gravity_suit as u32;
             print_maybe!(first, "    gravity_suit: {}", spawn_point.gravity_suit);
 
-            spawn_point.phazon_suit = fetch_bits(1);
+            spawn_point.phazon_suit = starting_items.phazon_suit as u32;
             print_maybe!(first, "    phazon_suit: {}", spawn_point.phazon_suit);
 
-            spawn_point.thermal_visor = fetch_bits(1);
+            spawn_point.thermal_visor = starting_items.thermal_visor as u32;
             print_maybe!(first, "    thermal_visor: {}", spawn_point.thermal_visor);
 
-            spawn_point.xray= fetch_bits(1);
+            spawn_point.xray = starting_items.xray as u32;
             print_maybe!(first, "    xray: {}", spawn_point.xray);
 
-            spawn_point.space_jump = fetch_bits(1);
+            spawn_point.space_jump = starting_items.space_jump as u32;
             print_maybe!(first, "    space_jump: {}", spawn_point.space_jump);
 
-            spawn_point.grapple = fetch_bits(1);
+            spawn_point.grapple = starting_items.grapple as u32;
             print_maybe!(first, "    grapple: {}", spawn_point.grapple);
 
-            spawn_point.super_missile = fetch_bits(1);
+            spawn_point.super_missile = starting_items.super_missile as u32;
             print_maybe!(first, "    super_missile: {}", spawn_point.super_missile);
 
-            spawn_point.wavebuster = fetch_bits(1);
+            spawn_point.wavebuster = starting_items.wavebuster as u32;
             print_maybe!(first, "    wavebuster: {}", spawn_point.wavebuster);
 
-            spawn_point.ice_spreader = fetch_bits(1);
+            spawn_point.ice_spreader = starting_items.ice_spreader as u32;
             print_maybe!(first, "    ice_spreader: {}", spawn_point.ice_spreader);
 
-            spawn_point.flamethrower = fetch_bits(1);
+            spawn_point.flamethrower = starting_items.flamethrower as u32;
             print_maybe!(first, "    flamethrower: {}", spawn_point.flamethrower);
 
             first = false;
+            spawn_point_count += 1;
         }
     }
+
+    // The starting items live on the room's SpawnPoint, so without one they'd be silently dropped //
+    if spawn_point_count == 0 {
+        Err(format!("Room 0x{:X} has no spawn point to give the starting items from, pick another starting room", room_id))?
+    }
+
     Ok(())
 }
 
@@ -4065,6 +5877,8 @@ fn patch_dol<'r>(
     version: Version,
     patch_heat_damage: bool,
     patch_suit_damage: bool,
+    suit_damage_reduction: [f32; 4],
+    rel_loader: bool,
 ) -> Result<(), String>
 {
     macro_rules! symbol_addr {
@@ -4189,6 +6003,9 @@ fn patch_dol<'r>(
     });
     dol_patcher.ppcasm_patch(&disable_hints_setting_patch)?;
 
+    // Only changes which suit protects from heat, the damage per second is set on the rooms' area
+    // damage objects (see patch_heat_damage_per_sec). The offset comes from the 0-00, 0-02 and PAL
+    // symbol tables, and 0-01 is rejected before patching.
     if patch_heat_damage {
         let heat_damage_patch = ppcasm!(symbol_addr!("ThinkAreaDamage__22CScriptSpecialFunctionFfR13CStateManager", version) + 0x4c, {
                 lwz     r4, 0xdc(r4);
@@ -4201,6 +6018,11 @@ fn patch_dol<'r>(
     }
 
     if patch_suit_damage {
+        if suit_damage_reduction.iter().any(|r| !(0.0..=1.0).contains(r)) {
+            Err(format!("Suit damage reductions must be between 0 and 1, got {:?}", suit_damage_reduction))?
+        }
+        // The table is indexed by suit count * 4 (the rlwinm), so only its values change with the
+        // config; its size, and with it the lfsx offsets and the jump target, stay the same.
         // TODO: The jump offset is almost certainly wrong, so double check that
         let staggered_suit_damage_patch = ppcasm!(symbol_addr!("ApplyLocalDamage__13CStateManagerFRC9CVector3fRC9CVector3fR6CActorfRC11CWeaponMode", version) + 0x128, {
                 lwz     r3, 0x8b8(r25);
@@ -4216,10 +6038,10 @@ fn patch_dol<'r>(
                 lfsx     f0, r4, r6;
                 b       { symbol_addr!("ApplyLocalDamage__13CStateManagerFRC9CVector3fRC9CVector3fR6CActorfRC11CWeaponMode", version) + 0x1c4 };
             data:
-                .float 0.0;
-                .float 0.1;
-                .float 0.2;
-                .float 0.5;
+                .float { suit_damage_reduction[0] };
+                .float { suit_damage_reduction[1] };
+                .float { suit_damage_reduction[2] };
+                .float { suit_damage_reduction[3] };
         });
         dol_patcher.ppcasm_patch(&staggered_suit_damage_patch)?;
     }
@@ -4230,42 +6052,44 @@ fn patch_dol<'r>(
         });
         dol_patcher.ppcasm_patch(&players_choice_scan_dash_patch)?;
     }
-    let (rel_loader_bytes, rel_loader_map_str) = match version {
-        Version::Ntsc0_00 => {
-            let loader_bytes = generated::REL_LOADER_100;
-            let map_str = generated::REL_LOADER_100_MAP;
-            (loader_bytes, map_str)
-        },
-        Version::Ntsc0_01 => unreachable!(),
-        Version::Ntsc0_02 => {
-            let loader_bytes = generated::REL_LOADER_102;
-            let map_str = generated::REL_LOADER_102_MAP;
-            (loader_bytes, map_str)
-        },
-        Version::Pal => {
-            let loader_bytes = generated::REL_LOADER_PAL;
-            let map_str = generated::REL_LOADER_PAL_MAP;
-            (loader_bytes, map_str)
-        },
-    };
-
-    let mut rel_loader = rel_loader_bytes.to_vec();
+    // Without the REL loader only the ppcasm patches above are applied
+    if rel_loader {
+        let (rel_loader_bytes, rel_loader_map_str) = match version {
+            Version::Ntsc0_00 => {
+                let loader_bytes = generated::REL_LOADER_100;
+                let map_str = generated::REL_LOADER_100_MAP;
+                (loader_bytes, map_str)
+            },
+            Version::Ntsc0_01 => unreachable!(),
+            Version::Ntsc0_02 => {
+                let loader_bytes = generated::REL_LOADER_102;
+                let map_str = generated::REL_LOADER_102_MAP;
+                (loader_bytes, map_str)
+            },
+            Version::Pal => {
+                let loader_bytes = generated::REL_LOADER_PAL;
+                let map_str = generated::REL_LOADER_PAL_MAP;
+                (loader_bytes, map_str)
+            },
+        };
 
-    let rel_loader_map = dol_linker::parse_symbol_table(
-        "extra_assets/rel_loader_1.0?.bin.map".as_ref(),
-        rel_loader_map_str.lines().map(|l| Ok(l.to_owned())),
-    ).map_err(|e| e.to_string())?;
+        let mut rel_loader = rel_loader_bytes.to_vec();
 
+        let rel_loader_map = dol_linker::parse_symbol_table(
+            "extra_assets/rel_loader_1.0?.bin.map".as_ref(),
+            rel_loader_map_str.lines().map(|l| Ok(l.to_owned())),
+        ).map_err(|e| e.to_string())?;
 
-    let bytes_needed = ((rel_loader.len() + 31) & !31) - rel_loader.len();
-    rel_loader.extend([0; 32][..bytes_needed].iter().copied());
 
-    dol_patcher.add_text_segment(0x80002000, Cow::Owned(rel_loader))?;
+        let bytes_needed = ((rel_loader.len() + 31) & !31) - rel_loader.len();
+        rel_loader.extend([0; 32][..bytes_needed].iter().copied());
 
-    dol_patcher.ppcasm_patch(&ppcasm!(symbol_addr!("PPCSetFpIEEEMode", version) + 4, {
-        b      { rel_loader_map["rel_loader_hook"] };
-    }))?;
+        dol_patcher.add_text_segment(0x80002000, Cow::Owned(rel_loader))?;
 
+        dol_patcher.ppcasm_patch(&ppcasm!(symbol_addr!("PPCSetFpIEEEMode", version) + 4, {
+            b      { rel_loader_map["rel_loader_hook"] };
+        }))?;
+    }
 
     *file = structs::FstEntryFile::ExternalFile(Box::new(dol_patcher));
     Ok(())
@@ -4314,39 +6138,120 @@ fn patch_bnr(file: &mut structs::FstEntryFile, config: &ParsedConfig) -> Result<
         Ok(())
     }
 
-    write_encoded_str("game_name", &config.bnr_game_name, &mut bnr.english_fields.game_name)?;
-    write_encoded_str("developer", &config.bnr_developer, &mut bnr.english_fields.developer)?;
-    write_encoded_str(
-        "game_name_full",
-        &config.bnr_game_name_full,
-        &mut bnr.english_fields.game_name_full
-    )?;
-    write_encoded_str(
-        "developer_full",
-        &config.bnr_developer_full,
-        &mut bnr.english_fields.developer_full)
-    ?;
-    write_encoded_str("description", &config.bnr_description, &mut bnr.english_fields.description)?;
+    for language in &config.target_languages {
+        let fields = match language.bnr_index() {
+            None => &mut bnr.english_fields,
+            Some(idx) => match bnr.other_lang_fields.as_mut() {
+                Some(other_lang_fields) => &mut other_lang_fields[idx],
+                // NTSC banners only have English fields
+                None => continue,
+            },
+        };
+        write_encoded_str("game_name", &config.bnr_game_name, &mut fields.game_name)?;
+        write_encoded_str("developer", &config.bnr_developer, &mut fields.developer)?;
+        match (&config.bnr_game_name_full, config.bnr_seed_name) {
+            (None, true) => {
+                // Made up by the patcher, so shorten it to fit instead of failing
+                let name = truncate_encoded_str(&seed_banner_name(config), fields.game_name_full.len());
+                write_encoded_str("game_name_full", &Some(name), &mut fields.game_name_full)?;
+            },
+            (game_name_full, _) => write_encoded_str("game_name_full", game_name_full, &mut fields.game_name_full)?,
+        }
+        write_encoded_str("developer_full", &config.bnr_developer_full, &mut fields.developer_full)?;
+        write_encoded_str("description", &config.bnr_description, &mut fields.description)?;
+    }
 
     Ok(())
 }
 
+fn seed_banner_name(config: &ParsedConfig) -> String
+{
+    format!("Metroid Prime Randomizer - {} ({:08X})", config.seed, config.item_seed as u32)
+}
+
+// Cut a banner string down to the longest prefix that patch_bnr accepts for a field of
+// field_len bytes. Every character is a single byte in WINDOWS-1252, and the field needs
+// room for the terminator plus one more byte.
+fn truncate_encoded_str(s: &str, field_len: usize) -> String
+{
+    s.chars().take(field_len.saturating_sub(2)).collect()
+}
+
 // XXX Deserialize is implemented here for c_interface. Ideally this could be done in
 //     c_interface.rs itself...
-#[derive(Deserialize)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum IsoFormat
 {
     Iso,
     Gcz,
     Ciso,
+    Rvz,
+}
+
+impl Default for IsoFormat
+{
+    fn default() -> IsoFormat
+    {
+        IsoFormat::Iso
+    }
+}
+
+// Parses an output spec like "gcz:/path/to/out.gcz". Without a scheme, the format is inferred
+// from the extension.
+pub fn parse_output_spec(spec: &str) -> Result<(IsoFormat, PathBuf), String>
+{
+    let schemes = [("iso", IsoFormat::Iso), ("gcz", IsoFormat::Gcz), ("ciso", IsoFormat::Ciso), ("rvz", IsoFormat::Rvz)];
+    // A single letter before the colon is a Windows drive, not a scheme
+    if let Some((scheme, path)) = spec.split_once(':').filter(|(scheme, _)| scheme.len() > 1) {
+        if scheme.chars().all(|c| c.is_ascii_alphanumeric()) {
+            let (_, format) = schemes.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(scheme))
+                .ok_or_else(|| format!("The output spec '{}' has an unknown format '{}'", spec, scheme))?;
+            if path.is_empty() {
+                return Err(format!("The output spec '{}' has no path", spec));
+            }
+            return Ok((*format, PathBuf::from(path)));
+        }
+    }
+    if spec.is_empty() {
+        return Err("The output path is empty".to_string());
+    }
+
+    let path = PathBuf::from(spec);
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    let format = match extension.as_deref() {
+        Some("gcz") => IsoFormat::Gcz,
+        Some("ciso") => IsoFormat::Ciso,
+        Some("rvz") => IsoFormat::Rvz,
+        _ => IsoFormat::Iso,
+    };
+    Ok((format, path))
 }
 
-impl Default for IsoFormat
+// Compressed input discs are decompressed into memory, a raw ISO is used as is
+fn decode_input_iso(input_iso: &[u8], format: Option<IsoFormat>) -> Result<Cow<'_, [u8]>, String>
 {
-    fn default() -> IsoFormat
-    {
-        IsoFormat::Iso
+    let format = format.unwrap_or_else(|| {
+        if gcz_writer::is_gcz(input_iso) {
+            IsoFormat::Gcz
+        } else if ciso_writer::is_ciso(input_iso) {
+            IsoFormat::Ciso
+        } else {
+            IsoFormat::Iso
+        }
+    });
+    match format {
+        IsoFormat::Iso => Ok(Cow::Borrowed(input_iso)),
+        IsoFormat::Gcz => gcz_writer::decompress_gcz(input_iso)
+            .map(Cow::Owned)
+            .map_err(|e| format!("Failed to read the input GCZ: {}", e)),
+        IsoFormat::Ciso => ciso_writer::decompress_ciso(input_iso, structs::GC_DISC_LENGTH)
+            .map(Cow::Owned)
+            .map_err(|e| format!("Failed to read the input CISO: {}", e)),
+        IsoFormat::Rvz => Err("RVZ is only supported as an output format".to_string()),
     }
 }
 
@@ -4357,6 +6262,8 @@ pub enum ArtifactHintBehavior
     Default,
     None,
     All,
+    // None, and the hint system's popups everywhere else are removed too
+    NoHints,
 }
 
 impl Default for ArtifactHintBehavior
@@ -4367,16 +6274,108 @@ impl Default for ArtifactHintBehavior
     }
 }
 
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Language
+{
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+}
+
+impl Language
+{
+    pub fn from_string(string: String) -> Option<Language>
+    {
+        match string.trim().to_lowercase().as_str() {
+            "english" => Some(Language::English),
+            "german"  => Some(Language::German),
+            "french"  => Some(Language::French),
+            "spanish" => Some(Language::Spanish),
+            "italian" => Some(Language::Italian),
+            "dutch"   => Some(Language::Dutch),
+            _ => None,
+        }
+    }
+
+    fn strg_lang(&self) -> FourCC
+    {
+        match self {
+            Language::English => b"ENGL".into(),
+            Language::German  => b"GERM".into(),
+            Language::French  => b"FREN".into(),
+            Language::Spanish => b"SPAN".into(),
+            Language::Italian => b"ITAL".into(),
+            Language::Dutch   => b"DUTC".into(),
+        }
+    }
+
+    // Index into Bnr::other_lang_fields, English has its own fields
+    fn bnr_index(&self) -> Option<usize>
+    {
+        match self {
+            Language::English => None,
+            Language::German  => Some(0),
+            Language::French  => Some(1),
+            Language::Spanish => Some(2),
+            Language::Italian => Some(3),
+            Language::Dutch   => Some(4),
+        }
+    }
+}
+
+// Generated strings are English only, so copy them to every other language the game may be set to
+fn add_strg_languages(strg: &mut structs::Strg, languages: &[Language])
+{
+    let tables = strg.string_tables.as_mut_vec();
+    for language in languages {
+        let lang = language.strg_lang();
+        if tables.iter().any(|table| table.lang == lang) {
+            continue;
+        }
+        let mut table = tables[0].clone();
+        table.lang = lang;
+        tables.push(table);
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FrigateMode
+{
+    #[default]
+    Full,
+    Skip,
+    // Start the new save just before the Parasite Queen instead of at the frigate's entrance.
+    // Only the spawn point moves; the frigate's rooms are left as they are.
+    Short,
+}
+
 pub struct ParsedConfig
 {
-    pub input_iso: memmap::Mmap,
-    pub output_iso: File,
+    // Only used by patch_iso, patch_iso_bytes takes the input ISO and returns the output instead
+    pub input_iso: Option<memmap::Mmap>,
+    // The format of input_iso. When it isn't given, it is detected from the file's magic.
+    pub input_iso_format: Option<IsoFormat>,
+    pub output_iso: Option<File>,
     pub layout_string: String,
+    // Set by patch_iso when the input ISO was already patched by randomprime (it contains
+    // randomprime.txt). Door randomization and the per-room door options can then be stacked on
+    // top of it, while everything that was already applied (pickups, FMVs, the DOL, patches.rel
+    // and the save file starting items) is left alone. Stacking onto an ISO that was already door
+    // randomized (it contains mpdr.txt) is not supported.
     pub is_item_randomized: Option<bool>,
 
     pub pickup_layout: Vec<u8>,
+    // Takes precedence over pickup_layout when set, for callers of the Rust API
+    pub pickup_layout_typed: Option<Vec<PickupType>>,
     pub elevator_layout: Vec<u8>,
     pub elevator_layout_override: Vec<String>,
+    // Shuffle the elevator destinations using the seed, unless elevator_layout_override is given
+    pub randomize_elevators: bool,
     pub missile_lock_override: Vec<bool>,
     pub superheated_rooms: Vec<String>,
     pub deheated_rooms: Vec<String>,
@@ -4385,12 +6384,30 @@ pub struct ParsedConfig
     pub liquid_volumes: Vec<LiquidVolume>,
     pub aether_transforms: Vec<AetherTransform>,
     pub additional_items: Vec<AdditionalItem>,
+    pub door_openers: Vec<DoorOpener>,
+    // Scan text shown for a pickup instead of its name, keyed by the pickup's index in the layout
+    pub pickup_scan_overrides: HashMap<usize, String>,
+    // 1 in n missiles are shiny, 1024 if unset
+    pub shiny_missile_odds: Option<u32>,
+    // Locations that are always Nothing, as "World:Room Name" or "World:Room Name:index" for a
+    // single location
+    pub excluded_pickups: HashSet<String>,
     pub new_save_spawn_room: String,
     pub frigate_done_spawn_room: String,
     pub item_seed: u64,
     pub seed: u64,
     pub door_weights: Weights,
+    // Reject door weights that don't add up to exactly 100 instead of scaling them
+    pub strict_door_weights: bool,
     pub excluded_doors: [HashMap<String,Vec<String>>;7],
+    pub custom_door_types: Vec<CustomDoorDef>,
+    // Assets read at runtime that are available to pickups and doors, replacing any asset with the
+    // same id and type
+    pub extra_assets: Vec<(u32, FourCC, Vec<u8>)>,
+    pub protected_assets: HashSet<(u32, FourCC)>,
+    // AABBs (min xyz, max xyz) of pickup models that aren't in pickup_meta's table, keyed by CMDL,
+    // for pickup models added through extra_assets
+    pub custom_pickup_aabbs: HashMap<u32, [f32; 6]>,
     pub patch_map: bool,
     pub patch_power_conduits: bool,
     pub remove_missile_locks: bool,
@@ -4401,26 +6418,64 @@ pub struct ParsedConfig
     pub remove_hall_of_the_elders_forcefield: bool,
 
     pub iso_format: IsoFormat,
-    pub skip_frigate: bool,
+    pub frigate_mode: FrigateMode,
     pub skip_hudmenus: bool,
+    pub mute_item_fanfare: bool,
     pub keep_fmvs: bool,
     pub obfuscate_items: bool,
+    pub obfuscate_scans: bool,
     pub nonvaria_heat_damage: bool,
+    pub heat_damage_per_sec: Option<f32>,
     pub staggered_suit_damage: bool,
+    pub suit_damage_reduction: [f32; 4],
     pub auto_enabled_elevators: bool,
     pub powerbomb_lockpick: bool,
     pub quiet: bool,
     pub tiny_elvetator_samus: bool,
+    pub fast_elevators: bool,
+    pub boss_item_independence: bool,
+    pub fix_elite_research: bool,
+    pub fix_mines_security_station: bool,
+    // Skip patches.rel, rel_config.bin and the REL loader hook in the DOL, for debugging. Features
+    // implemented in the REL (e.g. quickplay) won't work.
+    pub disable_rel_patches: bool,
+    pub skip_ridley_shorelines_cinematic: bool,
+    pub skip_known_cutscenes: bool,
+    pub reveal_full_map: bool,
+    pub phazon_suit_scan_follows_item: bool,
+    pub scan_known_destructibles: bool,
+    pub skip_artifact_temple_intro: bool,
+    pub print_disc_info: bool,
+    // Apply every patch but don't write the output, to check a layout and config
+    pub validate_only: bool,
+    // Languages generated strings and the banner text are written for
+    pub target_languages: Vec<Language>,
 
     pub skip_impact_crater: bool,
     pub enable_vault_ledge_door: bool,
     pub artifact_hint_behavior: ArtifactHintBehavior,
     pub patch_vertical_to_blue: bool,
+    pub min_colored_doors_per_area: u8,
+    // Walk the rooms from the start and warn about doors that keep the player out of rooms
+    pub check_door_logic: bool,
+    // Turn randomly colored doors blue where the walk from the start gets stuck at them, until
+    // every room the walk can get to through them is reached. Rooms are taken to be traversable
+    // inside, so this doesn't guarantee the seed can be finished.
+    pub remove_unopenable_doors: bool,
+    // Show the seed under the main menu message on the file select screen
+    pub show_seed_ingame: bool,
+    pub show_starting_items: bool,
+    pub force_scan_visor: bool,
+    pub warp_to_start: bool,
+    pub gravity_chamber_item_accessible: bool,
 
     pub flaahgra_music_files: Option<[nod_wrapper::FileWrapper; 2]>,
+    // Audio/*.dsp files to replace, by file name, with the DSP file to replace each with. These are
+    // applied after flaahgra_music_files, so they win if both replace the same file.
+    pub replace_audio: HashMap<String, PathBuf>,
 
-    pub new_save_starting_items: u64,
-    pub frigate_done_starting_items: u64,
+    pub new_save_starting_items: StartingItems,
+    pub frigate_done_starting_items: StartingItems,
 
     pub comment: String,
     pub main_menu_message: String,
@@ -4433,8 +6488,16 @@ pub struct ParsedConfig
     pub bnr_game_name_full: Option<String>,
     pub bnr_developer_full: Option<String>,
     pub bnr_description: Option<String>,
+    // Name the game after the seed when bnr_game_name_full isn't set
+    pub bnr_seed_name: bool,
 
     pub pal_override: bool,
+    pub print_writer_regions: bool,
+
+    // Where the JSON spoiler log is written, if anywhere
+    pub spoiler_log: Option<File>,
+    // Where the position of every pickup location in the input ISO is written as JSON, if anywhere
+    pub pickup_positions_file: Option<File>,
 }
 
 
@@ -4460,18 +6523,155 @@ impl fmt::Display for Version
     }
 }
 
-pub fn patch_iso<T>(mut config: ParsedConfig, mut pn: T) -> Result<(), String>
+pub fn patch_iso<T>(mut config: ParsedConfig, pn: T) -> Result<(), String>
+    where T: structs::ProgressNotifier
+{
+    let input_iso = config.input_iso.take().ok_or("No input ISO was given")?;
+    // Nothing is written when only validating, so no output is needed
+    if config.validate_only {
+        return patch_iso_to(&input_iso[..], Cursor::new(Vec::new()), config, pn);
+    }
+    let output_iso = config.output_iso.take().ok_or("No output ISO was given")?;
+    if config.iso_format == IsoFormat::Iso {
+        output_iso.set_len(structs::GC_DISC_LENGTH as u64)
+            .map_err(|e| format!("Failed to resize output file: {}", e))?;
+    }
+    patch_iso_to(&input_iso[..], output_iso, config, pn)
+}
+
+// Patches an ISO held in memory and returns the output image in config.iso_format, for when there
+// is no filesystem to map the input from or write the output to. input_iso and output_iso in the
+// config are ignored. Nothing is written when validate_only is set, so the output is empty.
+pub fn patch_iso_bytes<T>(input_iso: &[u8], config: ParsedConfig, pn: T) -> Result<Vec<u8>, String>
+    where T: structs::ProgressNotifier
+{
+    let pad_to_disc_length = config.iso_format == IsoFormat::Iso && !config.validate_only;
+    let mut output = Cursor::new(Vec::new());
+    patch_iso_to(input_iso, &mut output, config, pn)?;
+    let mut output = output.into_inner();
+    // The end of the disc is skipped over rather than written
+    if pad_to_disc_length {
+        output.resize(structs::GC_DISC_LENGTH, 0);
+    }
+    Ok(output)
+}
+
+// Patches a set of seeds that share everything but the seed, e.g. for races. Each is written to
+// output_dir as "seed_<seed>" with the extension for iso_format, along with
+// "seed_<seed>_spoiler.json" if the base config has a spoiler log. The pickup layout is shared, so
+// item_seed is too. The input ISO is decoded once and read afresh for each seed, as patching
+// modifies the disc. Returns the output path or the error for each seed, in order. When
+// validate_only is set no files are created, and the paths are where the seeds would have gone.
+pub fn patch_iso_batch<T>(mut base_config: ParsedConfig, seeds: &[u64], output_dir: &Path, mut pn: T)
+    -> Result<Vec<Result<PathBuf, String>>, String>
     where T: structs::ProgressNotifier
 {
+    let input_iso = base_config.input_iso.take().ok_or("No input ISO was given")?;
+    let input_iso = decode_input_iso(&input_iso[..], base_config.input_iso_format)?;
+    let write_spoiler_logs = base_config.spoiler_log.take().is_some();
+    base_config.output_iso = None;
+
+    let mut results = Vec::with_capacity(seeds.len());
+    for &seed in seeds {
+        let mut patch_seed = || -> Result<PathBuf, String> {
+            base_config.seed = seed;
+            let (output_path, output, spoiler_log) = create_batch_outputs(
+                output_dir,
+                seed,
+                base_config.iso_format,
+                write_spoiler_logs,
+                base_config.validate_only,
+            )?;
+            base_config.spoiler_log = spoiler_log;
+            match output {
+                Some(output) => patch_decoded_iso_to(&input_iso, output, &mut base_config, &mut pn)?,
+                None => patch_decoded_iso_to(&input_iso, Cursor::new(Vec::new()), &mut base_config, &mut pn)?,
+            }
+            Ok(output_path)
+        };
+        results.push(patch_seed());
+    }
+    Ok(results)
+}
+
+// Creates the output file, and the spoiler log if one is wanted, for one seed of a batch. Nothing
+// is created when only validating.
+fn create_batch_outputs(
+    output_dir: &Path,
+    seed: u64,
+    iso_format: IsoFormat,
+    spoiler_log: bool,
+    validate_only: bool,
+) -> Result<(PathBuf, Option<File>, Option<File>), String>
+{
+    let extension = match iso_format {
+        IsoFormat::Iso => "iso",
+        IsoFormat::Gcz => "gcz",
+        IsoFormat::Ciso => "ciso",
+        IsoFormat::Rvz => "rvz",
+    };
+    let output_path = output_dir.join(format!("seed_{}.{}", seed, extension));
+    if validate_only {
+        return Ok((output_path, None, None));
+    }
+
+    let spoiler_log = if spoiler_log {
+        let spoiler_path = output_dir.join(format!("seed_{}_spoiler.json", seed));
+        Some(File::create(&spoiler_path)
+            .map_err(|e| format!("Failed to create {}: {}", spoiler_path.display(), e))?)
+    } else {
+        None
+    };
+
+    let output = File::create(&output_path)
+        .map_err(|e| format!("Failed to create {}: {}", output_path.display(), e))?;
+    if iso_format == IsoFormat::Iso {
+        output.set_len(structs::GC_DISC_LENGTH as u64)
+            .map_err(|e| format!("Failed to resize output file: {}", e))?;
+    }
+    Ok((output_path, Some(output), spoiler_log))
+}
+
+fn patch_iso_to<W, T>(input_iso: &[u8], output: W, mut config: ParsedConfig, mut pn: T)
+    -> Result<(), String>
+    where W: Write + Seek,
+          T: structs::ProgressNotifier
+{
+    let input_iso = decode_input_iso(input_iso, config.input_iso_format)?;
+    patch_decoded_iso_to(&input_iso, output, &mut config, &mut pn)
+}
+
+fn patch_decoded_iso_to<W, T>(input_iso: &[u8], output: W, config: &mut ParsedConfig, pn: &mut T)
+    -> Result<(), String>
+    where W: Write + Seek,
+          T: structs::ProgressNotifier
+{
+    if let Some(pickup_layout_typed) = config.pickup_layout_typed.take() {
+        let location_count: usize = pickup_meta::PICKUP_LOCATIONS.iter()
+            .flat_map(|(_, rooms)| rooms.iter())
+            .map(|room_info| room_info.pickup_locations.len())
+            .sum();
+        if pickup_layout_typed.len() != location_count {
+            Err(format!(
+                "The pickup layout has {} pickups, but there are {} pickup locations",
+                pickup_layout_typed.len(),
+                location_count,
+            ))?;
+        }
+        config.pickup_layout = pickup_layout_typed.iter().map(|pt| pt.idx() as u8).collect();
+    }
+
     let mut ct = Vec::new();
     writeln!(ct, "Created by randomprime version {}", env!("CARGO_PKG_VERSION")).unwrap();
     writeln!(ct).unwrap();
     writeln!(ct, "Options used:").unwrap();
     writeln!(ct, "configuration string: {}", config.layout_string).unwrap();
-    writeln!(ct, "skip frigate: {}", config.skip_frigate).unwrap();
+    writeln!(ct, "frigate mode: {:?}", config.frigate_mode).unwrap();
     writeln!(ct, "keep fmvs: {}", config.keep_fmvs).unwrap();
     writeln!(ct, "nonmodal hudmemos: {}", config.skip_hudmenus).unwrap();
     writeln!(ct, "obfuscated items: {}", config.obfuscate_items).unwrap();
+    writeln!(ct, "obfuscated scans: {}", config.obfuscate_scans).unwrap();
+    writeln!(ct, "min colored doors per area: {}", config.min_colored_doors_per_area).unwrap();
 
     let mut dt = Vec::new();
     writeln!(dt, "{}",config.comment).unwrap();
@@ -4481,7 +6681,21 @@ pub fn patch_iso<T>(mut config: ParsedConfig, mut pn: T) -> Result<(), String>
     writeln!(dt, "door weights: {:?}",config.door_weights).unwrap();
     writeln!(dt, "excluded_doors: {:?}",config.excluded_doors).unwrap();
 
-    let mut reader = Reader::new(&config.input_iso[..]);
+    if config.randomize_elevators && config.elevator_layout_override.is_empty() {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let layout = randomize_elevator_layout(&mut rng);
+        writeln!(dt).unwrap();
+        writeln!(dt, "Elevators:").unwrap();
+        for (elv, &dest) in ELEVATORS.iter().zip(&layout) {
+            let dest = &ELEVATORS[dest as usize];
+            // None of the elevators lead to the frigate, but keep the check done for overrides
+            assert!(dest.mlvl != World::FrigateOrpheon.mlvl() || config.frigate_mode != FrigateMode::Skip);
+            writeln!(dt, "{} -> {}", elv.name.replace('\0', " "), dest.name.replace('\0', " ")).unwrap();
+        }
+        config.elevator_layout[..ELEVATORS.len()].copy_from_slice(&layout);
+    }
+
+    let mut reader = Reader::new(input_iso);
 
     let mut gc_disc: structs::GcDisc = reader.read(());
 
@@ -4492,6 +6706,18 @@ pub fn patch_iso<T>(mut config: ParsedConfig, mut pn: T) -> Result<(), String>
         (b"GM8P01", 0, 0) => Version::Pal,
         _ => Err("The input ISO doesn't appear to be NTSC-US or PAL Metroid Prime.".to_string())?
     };
+    // The positions are the same for every seed of a batch, so they're only written once
+    if let Some(file) = config.pickup_positions_file.take() {
+        serde_json::to_writer_pretty(file, &dump_pickup_positions(&gc_disc)?)
+            .map_err(|e| format!("Error writing pickup positions: {}", e))?;
+    }
+
+    // Keep the log of the item randomized ISO when stacking, so it isn't lost
+    let previous_ct = gc_disc.find_file("randomprime.txt")
+        .and_then(|entry| match entry.file() {
+            Some(structs::FstEntryFile::Unknown(reader)) => Some(reader.to_vec()),
+            _ => None,
+        });
     config.is_item_randomized = Some(gc_disc.find_file("randomprime.txt").is_some());
     if config.is_item_randomized.unwrap_or(false) {
         pn.notify_stacking_warning();
@@ -4505,13 +6731,30 @@ pub fn patch_iso<T>(mut config: ParsedConfig, mut pn: T) -> Result<(), String>
         Err("The NTSC 0-01 and PAL versions of Metroid Prime are not current supported.")?;
     }
 
-    build_and_run_patches(&mut gc_disc, &config, version)?;
+    if config.validate_only {
+        return build_and_run_patches(&mut gc_disc, config, version, &mut ct, &mut SpoilerLog::default());
+    }
+
+    let mut spoiler_log = SpoilerLog::default();
+    build_and_run_patches(&mut gc_disc, config, version, &mut ct, &mut spoiler_log)?;
 
-    gc_disc.add_file("randomprime.txt", structs::FstEntryFile::Unknown(Reader::new(&ct)))?;
+    if let Some(mut previous_ct) = previous_ct {
+        writeln!(previous_ct).unwrap();
+        previous_ct.extend_from_slice(&ct);
+        ct = previous_ct;
+    }
+    if let Some(entry) = gc_disc.find_file_mut("randomprime.txt") {
+        // Replace the existing file instead of adding a second entry with the same name
+        *entry.file_mut().unwrap() = structs::FstEntryFile::Unknown(Reader::new(&ct));
+    } else {
+        gc_disc.add_file("randomprime.txt", structs::FstEntryFile::Unknown(Reader::new(&ct)))?;
+    }
     gc_disc.add_file("mpdr.txt",structs::FstEntryFile::Unknown(Reader::new(&dt)))?;
 
 
-    if !config.is_item_randomized.unwrap_or(false) && version != Version::Ntsc0_01 && version != Version::Pal {
+    if !config.is_item_randomized.unwrap_or(false) && !config.disable_rel_patches &&
+        version != Version::Ntsc0_01 && version != Version::Pal
+    {
         let patches_rel_bytes = match version {
             Version::Ntsc0_00 => generated::PATCHES_100_REL,
             Version::Ntsc0_01 => unreachable!(),
@@ -4524,107 +6767,324 @@ pub fn patch_iso<T>(mut config: ParsedConfig, mut pn: T) -> Result<(), String>
         )?;
     }
 
+    if config.print_disc_info {
+        println!("{}", disc_info_json(&gc_disc));
+    }
+
     match config.iso_format {
         IsoFormat::Iso => {
-            let mut file = config.output_iso;
-            file.set_len(structs::GC_DISC_LENGTH as u64)
-                .map_err(|e| format!("Failed to resize output file: {}", e))?;
-            gc_disc.write(&mut file, &mut pn)
+            let mut output = output;
+            gc_disc.write(&mut output, pn)
                 .map_err(|e| format!("Error writing output file: {}", e))?;
             pn.notify_flushing_to_disk();
         },
         IsoFormat::Gcz => {
-            let mut gcz_writer = GczWriter::new(config.output_iso, structs::GC_DISC_LENGTH as u64)
+            let mut gcz_writer = GczWriter::new(output, structs::GC_DISC_LENGTH as u64)
                 .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
-            gc_disc.write(&mut *gcz_writer, &mut pn)
+            if config.print_writer_regions {
+                gcz_writer.record_regions();
+            }
+            gc_disc.write(&mut *gcz_writer, pn)
                 .map_err(|e| format!("Error writing output file: {}", e))?;
             pn.notify_flushing_to_disk();
+            gcz_writer.finish()
+                .map_err(|e| format!("Error writing output file: {}", e))?;
+            print_writer_regions(gcz_writer.regions());
         },
         IsoFormat::Ciso => {
-            let mut ciso_writer = CisoWriter::new(config.output_iso)
+            let mut ciso_writer = CisoWriter::new(output)
+                .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+            if config.print_writer_regions {
+                ciso_writer.record_regions();
+            }
+            gc_disc.write(&mut ciso_writer, pn)
+                .map_err(|e| format!("Error writing output file: {}", e))?;
+            pn.notify_flushing_to_disk();
+            ciso_writer.finish()
+                .map_err(|e| format!("Error writing output file: {}", e))?;
+            print_writer_regions(ciso_writer.regions());
+        },
+        IsoFormat::Rvz => {
+            let mut rvz_writer = RvzWriter::new(output, structs::GC_DISC_LENGTH as u64)
                 .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
-            gc_disc.write(&mut ciso_writer, &mut pn)
+            if config.print_writer_regions {
+                rvz_writer.record_regions();
+            }
+            gc_disc.write(&mut rvz_writer, pn)
                 .map_err(|e| format!("Error writing output file: {}", e))?;
             pn.notify_flushing_to_disk();
+            rvz_writer.finish()
+                .map_err(|e| format!("Error writing output file: {}", e))?;
+            print_writer_regions(rvz_writer.regions());
         }
     };
+
+    if let Some(file) = config.spoiler_log.take() {
+        serde_json::to_writer_pretty(file, &spoiler_log)
+            .map_err(|e| format!("Error writing spoiler log: {}", e))?;
+    }
     Ok(())
 }
 
-fn spawn_room_from_string(room_string: String) -> SpawnRoom {
+// A machine readable record of what was randomized, for trackers and race verification tools.
+// Rooms are named "World:Room", the same way rooms are given in the config.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpoilerLog
+{
+    pickups: BTreeMap<String, Vec<&'static str>>,
+    elevators: BTreeMap<String, String>,
+    doors: BTreeMap<String, BTreeMap<usize, String>>,
+    starting_items: BTreeMap<&'static str, u64>,
+}
+
+fn spoiler_room_name(pak_name: &str, room_name: &str) -> String
+{
+    format!("{}:{}", World::from_pak(pak_name).unwrap().as_string(), room_name)
+}
+
+// The world position of every pickup location, in PICKUP_LOCATIONS order, for trackers and
+// auto-splitters. Rooms are named "World:Room" like in the spoiler log.
+pub fn dump_pickup_positions(gc_disc: &structs::GcDisc) -> Result<Vec<(String, [f32; 3])>, String>
+{
+    let mut positions = vec![];
+    for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("The disc has no {}", pak_name))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+
+        for room_info in rooms.iter() {
+            if room_info.pickup_locations.is_empty() {
+                continue;
+            }
+            let res = pak.resources.iter()
+                .find(|res| res.file_id == room_info.room_id && res.fourcc() == b"MREA".into())
+                .ok_or_else(|| format!("{} has no room 0x{:08X}", pak_name, room_info.room_id))?;
+            let mrea = res.kind.as_mrea()
+                .ok_or_else(|| format!("Room 0x{:08X} isn't an MREA", room_info.room_id))?;
+            let mut scly = mrea.scly_section().into_owned();
+            let layers = scly.layers.as_mut_vec();
+
+            for pickup_location in room_info.pickup_locations.iter() {
+                let pickup = find_pickup_location_object(layers, pickup_location.location, room_info.room_id, "pickup")?;
+                let position = pickup.property_data.as_pickup()
+                    .ok_or_else(|| format!("Object 0x{:X} in room 0x{:08X} isn't a pickup", pickup.instance_id, room_info.room_id))?
+                    .position;
+                positions.push((spoiler_room_name(pak_name, room_info.name), [position[0], position[1], position[2]]));
+            }
+        }
+    }
+    Ok(positions)
+}
+
+// Files randomprime may add to the root of the disc
+const RANDOMIZER_FILES: &[&str] = &["randomprime.txt", "mpdr.txt", "patches.rel", "rel_config.bin"];
+
+// A machine readable summary of the disc header and the files randomprime added, for scripts
+pub fn disc_info_json(gc_disc: &structs::GcDisc) -> String
+{
+    let header = &gc_disc.header;
+    let game_name_len = header.game_name.iter().position(|&b| b == 0).unwrap_or(header.game_name.len());
+    let added_files: Vec<&str> = RANDOMIZER_FILES.iter()
+        .copied()
+        .filter(|name| gc_disc.find_file(name).is_some())
+        .collect();
+    serde_json::json!({
+        "gameId": String::from_utf8_lossy(&header.game_identifier()),
+        "gameName": String::from_utf8_lossy(&header.game_name[..game_name_len]),
+        "discId": header.disc_id,
+        "version": header.version,
+        "addedFiles": added_files,
+    }).to_string()
+}
+
+fn print_writer_regions(regions: Option<&[WrittenRegion]>)
+{
+    for region in regions.unwrap_or(&[]) {
+        println!("region offset: 0x{:X} length: 0x{:X} compressed length: 0x{:X}",
+                 region.offset, region.length, region.compressed_length);
+    }
+}
+
+// Case insensitive Levenshtein distance, used to suggest a room name when one is misspelled
+fn edit_distance(a: &str, b: &str) -> usize
+{
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn spawn_room_from_string(room_string: String) -> Result<SpawnRoom, String> {
     if room_string.to_lowercase() == "credits" {
-        return Elevator::end_game_elevator().to_spawn_room();
+        return Ok(Elevator::end_game_elevator().to_spawn_room());
     }
 
     let vec: Vec<&str> = room_string.split(":").collect();
-    assert!(vec.len() == 2);
+    if vec.len() != 2 {
+        Err(format!("Room '{}' should be written as \"World:Room Name\"", room_string))?
+    }
     let world_name = vec[0];
     let room_name = vec[1];
 
-    for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() { // for each pak
-        let world = World::from_pak(pak_name).unwrap();
-
-        if !world.as_string().to_lowercase().starts_with(&world_name.to_lowercase()) {
+    let mut closest: Option<(usize, &str)> = None;
+    for room in pickup_meta::all_rooms() {
+        if !room.world_name.to_lowercase().starts_with(&world_name.to_lowercase()) {
             continue;
         }
 
-        let mut idx: u32 = 0;
-        for room_info in rooms.iter() { // for each room in the pak
-            if room_info.name.to_lowercase() == room_name.to_lowercase() {
-
-                /*
-                println!("\n'{}' interpreted as:", room_string);
-                println!("'{}'", room_info.name);
-                println!("pak name - {:?}",pak_name);
-                println!("mlvl - {:X}",world.mlvl());
-                println!("mrea - {:X}",room_info.room_id);
-                println!("mrea_idx - {}",idx);
-                */
-
-                return SpawnRoom {
-                    pak_name,
-                    mlvl: world.mlvl(),
-                    mrea: room_info.room_id,
-                    mrea_idx: idx,
-                };
-            }
-            idx = idx + 1;
+        if room.room_name.to_lowercase() == room_name.to_lowercase() {
+            return Ok(room.to_spawn_room());
+        }
+
+        let distance = edit_distance(room_name, room.room_name);
+        if closest.map(|(d, _)| distance < d).unwrap_or(true) {
+            closest = Some((distance, room.room_name));
         }
     }
 
-    println!("Error - Could not find room '{}'", room_string);
-    assert!(false);
-    return SpawnRoom::landing_site_spawn_room();
+    match closest {
+        Some((_, closest_name)) => Err(format!(
+            "Could not find room '{}', did you mean '{}:{}'?", room_string, world_name, closest_name
+        )),
+        None => {
+            let worlds: Vec<String> = pickup_meta::PICKUP_LOCATIONS.iter()
+                .map(|(pak_name, _)| World::from_pak(pak_name).unwrap().as_string())
+                .collect();
+            Err(format!(
+                "Could not find room '{}', '{}' doesn't start the name of a world. The worlds are: {}",
+                room_string, world_name, worlds.join(", ")
+            ))
+        },
+    }
 }
 
-fn room_strg_id_from_mrea_id(mrea_id: u32) -> (u32, u32)
+fn room_strg_id_from_mrea_id(mrea_id: u32) -> Result<(u32, u32), String>
 {
-    for _ in pickup_meta::PICKUP_LOCATIONS.iter().map(|(name, _)| name) {
-        let mut idx = 0;
-        for (_, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
-            for room_info in rooms.iter() {
-                if room_info.room_id == mrea_id {
-                    return (idx ,room_info.name_id);
-                }
+    let mut idx = 0;
+    for (_, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+        for room_info in rooms.iter() {
+            if room_info.room_id == mrea_id {
+                return Ok((idx ,room_info.name_id));
             }
-            idx = idx + 1;
         }
+        idx = idx + 1;
     }
 
-    assert!(false);
-    (0, 0)
+    Err(format!("Room 0x{:X} isn't in any world", mrea_id))
 }
 
-fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, version: Version)
-    -> Result<(), String>
+// Skipping the frigate also skips its scan visor tutorial, so without the visor in the starting
+// items the player couldn't scan anything. When the visor is one of the shuffled pickups, finding
+// it is left to the layout.
+fn new_save_starting_items(config: &ParsedConfig) -> StartingItems
 {
-    let pickup_layout: Vec<_> = config.pickup_layout.iter()
-        .map(|i| PickupType::from_idx(*i as usize).unwrap())
-        .collect();
+    let mut starting_items = config.new_save_starting_items;
+    let scan_visor_shuffled = config.pickup_layout.contains(&(PickupType::ScanVisor.idx() as u8));
+    if config.force_scan_visor || (config.frigate_mode == FrigateMode::Skip && !scan_visor_shuffled) {
+        starting_items.scan_visor = true;
+    }
+    starting_items
+}
+
+// The frigate can't be finished without the Scan Visor for the consoles that unlock its doors or
+// the Morph Ball for its tunnels. When the post frigate starting items replace everything once it is
+// over, these can be handed out for the frigate alone without changing what the rest of the seed
+// starts with.
+fn frigate_starting_items(starting_items: StartingItems) -> StartingItems
+{
+    StartingItems {
+        scan_visor: true,
+        morph_ball: true,
+        ..starting_items
+    }
+}
+
+// A DSP ADPCM file starts with a 0x60 byte header giving the sample count, nibble count and
+// sample rate, followed by the nibbles
+fn check_dsp(dsp: &[u8]) -> Result<(), String>
+{
+    if dsp.len() < 0x60 {
+        Err("is too short to be a DSP file".to_string())?
+    }
+    let read_u32 = |offset: usize| u32::from_be_bytes([dsp[offset], dsp[offset + 1], dsp[offset + 2], dsp[offset + 3]]);
+    let nibble_count = read_u32(4) as usize;
+    let sample_rate = read_u32(8);
+    if sample_rate == 0 {
+        Err("has a sample rate of 0".to_string())?
+    }
+    if dsp.len() - 0x60 < nibble_count.div_ceil(2) {
+        Err(format!("is truncated, its header has {} nibbles", nibble_count))?
+    }
+    Ok(())
+}
+
+// The full disc path and contents of each replacement audio file
+fn load_replacement_audio(
+    gc_disc: &structs::GcDisc,
+    replace_audio: &HashMap<String, PathBuf>,
+) -> Result<Vec<(String, Vec<u8>)>, String>
+{
+    let mut replacements = Vec::new();
+    for (name, path) in replace_audio.iter() {
+        let file_name = format!("Audio/{}", name.trim_start_matches("Audio/"));
+        if !file_name.to_lowercase().ends_with(".dsp") {
+            Err(format!("Only Audio/*.dsp files can be replaced, not '{}'", name))?
+        }
+        if gc_disc.find_file(&file_name).is_none() {
+            Err(format!("Can't replace '{}', there is no such file on the disc", file_name))?
+        }
+        let dsp = fs::read(path)
+            .map_err(|e| format!("Failed to read the replacement for '{}' from {}: {}", file_name, path.display(), e))?;
+        check_dsp(&dsp)
+            .map_err(|e| format!("The replacement for '{}' ({}) {}", file_name, path.display(), e))?;
+        replacements.push((file_name, dsp));
+    }
+    Ok(replacements)
+}
+
+fn build_and_run_patches(
+    gc_disc: &mut structs::GcDisc,
+    config: &ParsedConfig,
+    version: Version,
+    ct: &mut Vec<u8>,
+    spoiler_log: &mut SpoilerLog,
+) -> Result<(), String>
+{
+    config.door_weights.validate(config.strict_door_weights)?;
+    config.new_save_starting_items.validate("new_save_starting_items")?;
+    config.frigate_done_starting_items.validate("frigate_done_starting_items")?;
+
+    let pickup_layout = config.pickup_layout.iter()
+        .map(|i| PickupType::from_idx(*i as usize)
+            .ok_or_else(|| format!("Pickup index {} in the layout is out of range", i)))
+        .collect::<Result<Vec<_>, String>>()?;
+    // Excluded locations are Nothing for the logic, the hints and the spoiler log as well
+    let mut pickup_layout = pickup_layout;
+    for idx in excluded_pickup_indices(&config.excluded_pickups)? {
+        if let Some(pickup_type) = pickup_layout.get_mut(idx) {
+            *pickup_type = PickupType::Nothing;
+        }
+    }
     let pickup_layout = &pickup_layout[..];
 
     let mut elevator_layout: Vec<_> = config.elevator_layout[..ELEVATORS.len()].iter()
-        .map(|i| ELEVATORS[*i as usize])
+        .map(|i| ELEVATORS.get(*i as usize).cloned()
+            .ok_or_else(|| format!("Elevator index {} in the layout is out of range", i)))
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
         .map(|elv| if config.skip_impact_crater && elv.name == "Crater Entry Point" {
                 Elevator::end_game_elevator()
             } else {
@@ -4640,13 +7100,15 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             continue;    
         }
 
-        let spawn_room = spawn_room_from_string(elv.to_string());
+        let spawn_room = spawn_room_from_string(elv.to_string())?;
         
-        assert!(!(spawn_room.mlvl == World::FrigateOrpheon.mlvl() && config.skip_frigate)); // panic if a elevator destination takes you to the removed frigate level
+        if spawn_room.mlvl == World::FrigateOrpheon.mlvl() && config.frigate_mode == FrigateMode::Skip {
+            Err(format!("Elevator destination '{}' is on the frigate, which is skipped", elv))?
+        }
         elevator_layout[idx].mlvl = spawn_room.mlvl;
         elevator_layout[idx].mrea = spawn_room.mrea; 
 
-        let (mrea_idx, _) = room_strg_id_from_mrea_id(spawn_room.mrea);
+        let (mrea_idx, _) = room_strg_id_from_mrea_id(spawn_room.mrea)?;
         elevator_layout[idx].mrea_idx = mrea_idx;
         idx = idx + 1;
     }
@@ -4654,51 +7116,90 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
     // The room the player spawns in after starting a new save
     let new_save_spawn_room = {
         if config.new_save_spawn_room.to_string() == "" { // if unspecified
-            if config.skip_frigate {
-                SpawnRoom::from_room_idx(config.elevator_layout[20] as usize) // go to elevator specified in layout string
-            } else {
-                SpawnRoom::frigate_spawn_room() // spawn on frigate
+            match config.frigate_mode {
+                FrigateMode::Skip => SpawnRoom::from_room_idx(config.elevator_layout[20] as usize), // go to elevator specified in layout string
+                FrigateMode::Full => SpawnRoom::frigate_spawn_room(), // spawn on frigate
+                FrigateMode::Short => SpawnRoom::frigate_escape_spawn_room(), // spawn just before the parasite queen
             }
         } else {
-            spawn_room_from_string(config.new_save_spawn_room.to_string()) // use the specified room name
+            spawn_room_from_string(config.new_save_spawn_room.to_string())? // use the specified room name
         }
     };
-    assert!(new_save_spawn_room.mlvl != World::FrigateOrpheon.mlvl() || !config.skip_frigate); // panic if the games starts in the removed frigate level
+    if new_save_spawn_room.mlvl == World::FrigateOrpheon.mlvl() && config.frigate_mode == FrigateMode::Skip {
+        Err("The new save spawn room is on the frigate, which is skipped")?
+    }
     // println!("new_save_spawn_room - 0x{:X}", new_save_spawn_room.mrea);
 
     // The room the player spawns in after finishing the frigate level
     let frigate_done_spawn_room = {
-        if config.skip_frigate {
-            spawn_room_from_string("Tallon:Waterfall Cavern".to_string()) // this is to avoid double patching the landing site item
+        if config.frigate_mode == FrigateMode::Skip {
+            spawn_room_from_string("Tallon:Waterfall Cavern".to_string())? // this is to avoid double patching the landing site item
         } else if config.frigate_done_spawn_room.to_string() == "" { // if unspecified
             SpawnRoom::from_room_idx(config.elevator_layout[20] as usize) // go to elevator specified in layout string
         } else {
-            spawn_room_from_string(config.frigate_done_spawn_room.to_string()) // use the specified room name
+            spawn_room_from_string(config.frigate_done_spawn_room.to_string())? // use the specified room name
         }
     };
-    assert!(frigate_done_spawn_room.mlvl != World::FrigateOrpheon.mlvl()); // panic if the frigate level gets you stuck in a loop
+    if frigate_done_spawn_room.mlvl == World::FrigateOrpheon.mlvl() {
+        // Finishing the frigate would send the player back to it
+        Err("The frigate done spawn room can't be on the frigate")?
+    }
     // println!("frigate_done_spawn_room - 0x{:X}", frigate_done_spawn_room.mrea);
+
+    // Starting on the frigate grants the Morph Ball and Scan Visor until the frigate is done, so
+    // logic, the starting items scan and the spoiler log all see them too
+    let new_save_starting_items = if config.frigate_mode != FrigateMode::Skip &&
+        new_save_spawn_room.mlvl == World::FrigateOrpheon.mlvl()
+    {
+        frigate_starting_items(new_save_starting_items(config))
+    } else {
+        new_save_starting_items(config)
+    };
      
-    let mut rng = StdRng::seed_from_u64(config.seed);
-    let artifact_totem_strings = build_artifact_temple_totem_scan_strings(pickup_layout, &mut rng);
-    let mut pickup_resources = collect_pickup_resources(gc_disc);
-    let door_resources = collect_door_resources(gc_disc);
-    let liquid_resources = collect_liquid_resources(gc_disc);
+    let SeedRolls { artifact_totem_strings, select_game_fmv_suffix, placed_layout } =
+        roll_seed(config.seed, pickup_layout, config.shiny_missile_odds);
+    let mut pickup_resources = collect_pickup_resources(gc_disc, &config.extra_assets)?;
+    let mut door_resources = collect_door_resources(gc_disc, &config.extra_assets)?;
+    let custom_doors = add_custom_door_types(&mut door_resources, &config.custom_door_types)?;
+    let liquid_resources = collect_liquid_resources(gc_disc)?;
     if config.skip_hudmenus {
         add_skip_hudmemos_strgs(&mut pickup_resources);
     }
+    if config.obfuscate_items && config.obfuscate_scans {
+        add_obfuscated_hudmemo_strg(&mut pickup_resources);
+    }
+    if config.show_starting_items {
+        add_starting_items_scan(&mut pickup_resources, new_save_starting_items.to_u64());
+    }
+    if config.scan_known_destructibles {
+        add_destructible_scans(&mut pickup_resources);
+    }
+    add_pickup_scan_overrides(&mut pickup_resources, &config.pickup_scan_overrides, pickup_layout.len())?;
+    for res in pickup_resources.values_mut() {
+        if let structs::ResourceKind::Strg(strg) = &mut res.kind {
+            add_strg_languages(strg, &config.target_languages);
+        }
+    }
+
+    // Read before anything is patched so a bad file is reported straight away
+    let replacement_audio = load_replacement_audio(gc_disc, &config.replace_audio)?;
 
     // XXX These values need to out live the patcher
-    let select_game_fmv_suffix = ["A", "B", "C"].choose(&mut rng).unwrap();
     let n = format!("Video/02_start_fileselect_{}.thp", select_game_fmv_suffix);
-    let start_file_select_fmv = gc_disc.find_file(&n).unwrap().file().unwrap().clone();
+    let start_file_select_fmv = gc_disc.find_file(&n).and_then(|f| f.file())
+        .ok_or_else(|| format!("The disc has no {}", n))?.clone();
     let n = format!("Video/04_fileselect_playgame_{}.thp", select_game_fmv_suffix);
-    let file_select_play_game_fmv = gc_disc.find_file(&n).unwrap().file().unwrap().clone();
+    let file_select_play_game_fmv = gc_disc.find_file(&n).and_then(|f| f.file())
+        .ok_or_else(|| format!("The disc has no {}", n))?.clone();
 
 
     let pickup_resources = &pickup_resources;
     let door_resources = &door_resources;
+    let custom_doors = &custom_doors;
     let liquid_resources = &liquid_resources;
+    let main_menu_message = main_menu_message_with_seed(config);
+    let main_menu_message = &main_menu_message;
+
     let mut patcher = PrimePatcher::new();
     if !config.is_item_randomized.unwrap_or(false) && !config.keep_fmvs {
         patcher.add_file_patch(b"opening.bnr", |file| patch_bnr(file, config));
@@ -4741,6 +7242,13 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             }
         }
 
+        for (file_name, dsp) in replacement_audio.iter() {
+            patcher.add_file_patch(file_name.as_bytes(), move |file| {
+                *file = structs::FstEntryFile::ExternalFile(Box::new(dsp.clone()));
+                Ok(())
+            });
+        }
+
         // Replace the FMVs that play when you select a file so each ISO always plays the only one.
         const SELECT_GAMES_FMVS: &[&[u8]] = &[
             b"Video/02_start_fileselect_A.thp",
@@ -4788,7 +7296,7 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
 
     // Make superheated rooms normal temperature
     for room_name in config.deheated_rooms.iter() {
-        let room = spawn_room_from_string(room_name.to_string());
+        let room = spawn_room_from_string(room_name.to_string())?;
 
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
@@ -4798,17 +7306,42 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
 
     // Make rooms superheated
     for room_name in config.superheated_rooms.iter() {
-        let room = spawn_room_from_string(room_name.to_string());
+        let room = spawn_room_from_string(room_name.to_string())?;
 
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
-            move |_ps, area| patch_superheated_room(_ps, area),
+            move |_ps, area| patch_superheated_room(_ps, area, config.heat_damage_per_sec.unwrap_or(10.0)),
         );
     }
 
+    if let Some(heat_damage_per_sec) = config.heat_damage_per_sec {
+        if !heat_damage_per_sec.is_finite() || heat_damage_per_sec < 0.0 {
+            Err(format!("Heat damage per second must be a non-negative number, got {}", heat_damage_per_sec))?
+        }
+        for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+            for room_info in rooms.iter() {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id),
+                    move |_ps, area| patch_heat_damage_per_sec(_ps, area, heat_damage_per_sec),
+                );
+            }
+        }
+    }
+
+    if config.reveal_full_map {
+        for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+            for room_info in rooms.iter().filter(|room_info| room_info.mapa_id != 0) {
+                patcher.add_resource_patch(
+                    (&[pak_name.as_bytes()], room_info.mapa_id, b"MAPA".into()),
+                    patch_reveal_map,
+                );
+            }
+        }
+    }
+
     // Drain rooms of liquids
     for room_name in config.drain_liquid_rooms.iter() {
-        let room = spawn_room_from_string(room_name.to_string());
+        let room = spawn_room_from_string(room_name.to_string())?;
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
             move |_ps, area| patch_remove_water(_ps, area),
@@ -4817,7 +7350,7 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
 
     // Place liquids
     for liquid_volume in config.liquid_volumes.iter() {
-        let room = spawn_room_from_string(liquid_volume.room.to_string());
+        let room = spawn_room_from_string(liquid_volume.room.to_string())?;
 
         let water_type = {
             let liquid_type = liquid_volume.liquid_type.to_lowercase();
@@ -4828,9 +7361,7 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             } else if liquid_type == "lava" || liquid_type == "magma" {
                 WaterType::Lava
             } else {
-                println!("Error - Unknown Liquid Type '{}'", liquid_type);
-                assert!(false);
-                WaterType::Normal
+                Err(format!("Unknown liquid type '{}'", liquid_volume.liquid_type))?
             }
         };
 
@@ -4843,7 +7374,7 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
     // Place bounding box liquids //
     for room_name in config.underwater_rooms.iter()
     {
-        let room = spawn_room_from_string(room_name.to_string());
+        let room = spawn_room_from_string(room_name.to_string())?;
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
             move |_ps, area| patch_full_underwater(_ps, area, liquid_resources),
@@ -4853,7 +7384,7 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
     // Re-size bounding box //
     for aether_transform in config.aether_transforms.iter()
     {
-        let room = spawn_room_from_string(aether_transform.room.to_string());
+        let room = spawn_room_from_string(aether_transform.room.to_string())?;
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
             move |_ps, area| patch_transform_bounding_box(_ps, area, aether_transform.offset, aether_transform.scale),
@@ -4861,13 +7392,15 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
     }
     
     // Patch pickups and doors
-    let mut layout_iterator = pickup_layout.iter();
-    let mut door_rng = StdRng::seed_from_u64(config.seed);
+    let mut layout_iterator = placed_layout.iter().enumerate();
+    let mut all_room_doors = Vec::new();
+    let starting_room_ids = [new_save_spawn_room.mrea, frigate_done_spawn_room.mrea];
+    let opened_docks = door_opener_docks(&config.door_openers)?;
     for (name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() { // for each .pak
         let world = World::from_pak(name).unwrap();
         let level = world as usize;
 
-        if level == 0 && config.skip_frigate {continue;} // If we're skipping the frigate, there's nothing to patch
+        if level == 0 && config.frigate_mode == FrigateMode::Skip {continue;} // If we're skipping the frigate, there's nothing to patch
 
         for room_info in rooms.iter() { // for each room in the pak
             // patch the item locations
@@ -4882,23 +7415,18 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
                     Ok(())
                 });
                 let iter = room_info.pickup_locations.iter().zip(&mut layout_iterator);
-                for (&pickup_location, &pickup_type) in iter {
-                    // 1 in 1024 chance of a missile being shiny means a player is likely to see a
-                    // shiny missile every 40ish games (assuming most players collect about half of the
-                    // missiles)
-                    let pickup_type = if pickup_type == PickupType::Missile && rng.gen_ratio(1, 1024) {
-                        PickupType::ShinyMissile
-                    } else {
-                        pickup_type
-                    };
+                for (&pickup_location, (pickup_idx, &pickup_type)) in iter {
+                    spoiler_log.pickups.entry(spoiler_room_name(name, room_info.name))
+                        .or_default()
+                        .push(pickup_type.name());
                     patcher.add_scly_patch(
                         (name.as_bytes(), room_info.room_id),
                         move |ps, area| modify_pickups_in_mrea(
                                 ps,
                                 area,
+                                pickup_idx,
                                 pickup_type,
                                 pickup_location,
-                                0xFFFFFFFF,
                                 pickup_resources,
                                 config
                             )
@@ -4907,77 +7435,247 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             }
 
             // patch the door locations
+            let mut room_doors = Vec::new();
             let iter = room_info.door_locations.iter();
             for &door_location in iter // for each door location in the room
             {
                 if door_location.dock_number.is_none() { continue; }
                 let door_index = door_location.dock_number.unwrap() as usize;
                 
-                // println!("excluded_doors[{}][{}][{}]", level, room_info.name.to_string(), door_index);
-                let door_specification = &config.excluded_doors[level][room_info.name][door_index];
-
-                let is_vertical_door =  (room_info.room_id == 0x11BD63B7 && door_index == 0) || // Tower Chamber
-                                        (room_info.room_id == 0x0D72F1F7 && door_index == 1) || // Tower of Light
-                                        (room_info.room_id == 0xFB54A0CB && door_index == 4) || // Hall of the Elders 
-                                        (room_info.room_id == 0xE1981EFC && door_index == 0) || // Elder Chamber
-                                        (room_info.room_id == 0x43E4CC25 && door_index == 1) || // Research Lab Hydra
-                                        (room_info.room_id == 0x37BBB33C && door_index == 1) || // Observatory Access
-                                        (room_info.room_id == 0xD8E905DD && door_index == 1) || // Research Core Access
-                                        (room_info.room_id == 0x21B4BFF6 && door_index == 1) || // Research Lab Aether
-                                        (room_info.room_id == 0x3F375ECC && door_index == 2) || // Omega Research
-                                        (room_info.room_id == 0xF517A1EA && door_index == 1) || // Dynamo Access (Careful of Chozo room w/ same name)
-                                        (room_info.room_id == 0x8A97BB54 && door_index == 1) || // Elite Research
-                                        (room_info.room_id == 0xA20201D4                   ) || // Security Access B (both doors)
-                                        (room_info.room_id == 0x956F1552 && door_index == 1) || // Mine Security Station
-                                        (room_info.room_id == 0xC50AF17A && door_index == 2) || // Elite Control
-                                        (room_info.room_id == 0x90709AAC && door_index == 1);   // Ventilation Shaft
-
-                let mut door_type = calculate_door_type(name,&mut door_rng,&config.door_weights); // randomly pick a door color using weights
-                
-                if door_specification != "random" && door_specification != "default" {
-                    door_type = DoorType::from_string(door_specification.to_string()).unwrap();
+                let door_specification = door_specification(config, level, room_info.name, door_index)?;
+
+                let is_vertical_door = is_vertical_door(room_info.room_id, door_index);
+
+                let mut door_type = random_door_type(name, config.seed, room_info.room_id, door_index as u32, &config.door_weights); // randomly pick a door color using weights
+                let custom_door = custom_doors.get(&door_specification.to_lowercase());
+                let mut blast_shield = BlastShieldType::from_door_specification(door_specification);
+
+                if let Some(custom_door) = custom_door {
+                    if is_vertical_door && custom_door.vertical_cmdl.is_none() {
+                        Err(format!("Custom door type '{}' has no vertical model, so it can't be used on vertical door {} in {}",
+                            door_specification, door_index, room_info.name))?
+                    }
+                    door_type = DoorType::Blue; // patch_custom_door swaps in the user's assets afterwards
+                } else if blast_shield.is_some() {
+                    // The shield decides what opens the door, so the door behind it is left blue
+                    door_type = DoorType::Blue;
+                } else if door_specification != "random" && door_specification != "default" {
+                    door_type = DoorType::from_string(door_specification.to_string())
+                        .ok_or_else(|| unknown_door_specification(door_specification, room_info.name, door_index))?;
                 }
                 
                 if is_vertical_door {
-                    if config.patch_vertical_to_blue {
-                        door_type = DoorType::VerticalBlue;
+                    // Blast shields are only placed in front of horizontal doors
+                    if let Some(shield_type) = blast_shield.take() {
+                        writeln!(ct, "{} door {} is vertical, ignoring {:?} blast shield", room_info.name, door_index, shield_type).unwrap();
                     }
-                    else {
-                        door_type = door_type.to_vertical();
+                    door_type = vertical_door_type(door_type, config.patch_vertical_to_blue);
+                    if !door_type.is_vertical() {
+                        Err(format!("{:?} doors have no vertical model, so they can't be used on vertical door {} in {}",
+                            door_type, door_index, room_info.name))?
                     }
                 }
 
                 if (door_specification != "default") || (is_vertical_door && config.patch_vertical_to_blue)
                 {
-                    patcher.add_scly_patch(
-                        (name.as_bytes(), room_info.room_id),
-                        move |_ps, area| patch_door(_ps, area,door_location,door_type, BlastShieldType::Missile, door_resources,config.powerbomb_lockpick)
-                    );
-                    
-                    if config.patch_map && room_info.mapa_id != 0 {
-                        patcher.add_resource_patch(
-                            (&[name.as_bytes()], room_info.mapa_id,b"MAPA".into()),
-                            move |res| patch_map_door_icon(res,door_location,door_type)
-                        );
+                    // Only doors left to the weights may be recolored below
+                    let is_random = door_specification == "random";
+                    room_doors.push((door_location, door_index, door_type, custom_door, blast_shield, is_random));
+                }
+            }
+
+            // Make sure the room isn't left with (almost) only blue doors. Every side of a door is
+            // recolored together, so a door counts once no matter how many sides it has.
+            let min_colored_doors = config.min_colored_doors_per_area as usize;
+            if min_colored_doors > 0 {
+                let mut door_indices: Vec<_> = room_doors.iter().map(|door| door.1).collect();
+                door_indices.sort();
+                door_indices.dedup();
+                let mut colored: HashSet<usize> = room_doors.iter()
+                    .filter(|door| door.2 != DoorType::Blue || door.3.is_some() || door.4.is_some())
+                    .map(|door| door.1)
+                    .collect();
+                for &door_index in door_indices.iter() {
+                    if colored.len() >= min_colored_doors {
+                        break;
+                    }
+                    let recolorable = room_doors.iter()
+                        .filter(|door| door.1 == door_index)
+                        .all(|door| door.5 && !door.2.is_vertical());
+                    if !recolorable || colored.contains(&door_index) {
+                        continue;
+                    }
+                    // Continue the door's own stream past the roll that left it blue
+                    let mut door_rng = seeded_door_rng(config.seed, room_info.room_id, door_index as u32);
+                    calculate_door_type(name, &mut door_rng, &config.door_weights);
+                    let door_type = match calculate_colored_door_type(name, &mut door_rng, &config.door_weights) {
+                        Some(door_type) => door_type,
+                        None => break,
+                    };
+                    for door in room_doors.iter_mut().filter(|door| door.1 == door_index) {
+                        door.2 = door_type;
                     }
+                    writeln!(ct, "min colored doors: {} door {} recolored to {:?}", room_info.name, door_index, door_type).unwrap();
+                    colored.insert(door_index);
                 }
             }
+
+            all_room_doors.push((*name, level, room_info, room_doors));
+        }
+    }
+
+    let mut patched_door_types: HashMap<_, _> = all_room_doors.iter()
+        .flat_map(|(_, _, room_info, room_doors)| room_doors.iter().map(move |door| (room_info.room_id, door)))
+        .map(|(room_id, door)| (
+            (room_id, door.1 as u32),
+            logic_door_type(&opened_docks, room_id, door.1, door.2, door.3.is_some(), door.4),
+        ))
+        .collect();
+
+    // The door logic walks the doors as the unpatched game has them with the patched ones laid
+    // over them. Reading every room takes a while, so it's only done when asked for.
+    let door_logic = if config.check_door_logic || config.remove_unopenable_doors {
+        let mut room_pickups = logic::room_pickups(pickup_layout);
+        if config.frigate_mode == FrigateMode::Skip {
+            let frigate_rooms = pickup_meta::PICKUP_LOCATIONS[0].1;
+            room_pickups.retain(|room_id, _| frigate_rooms.iter().all(|room_info| room_info.room_id != *room_id));
+        }
+        let starting_items: HashSet<_> = PickupType::iter()
+            .filter(|&pickup_type| starting_items_contain(new_save_starting_items.to_u64(), pickup_type))
+            .collect();
+        Some((vanilla_door_assignments(gc_disc)?, room_pickups, starting_items))
+    } else {
+        None
+    };
+
+    if config.remove_unopenable_doors {
+        let (vanilla_doors, room_pickups, starting_items) = door_logic.as_ref().unwrap();
+        let mut door_assignments = with_patched_doors(vanilla_doors, &patched_door_types);
+        let recolorable_docks: HashSet<_> = all_room_doors.iter()
+            .flat_map(|(_, _, room_info, room_doors)| room_doors.iter()
+                .filter(|door| door.5)
+                .map(move |door| (room_info.room_id, door.1 as u32)))
+            .collect();
+        let opened_doors = open_unopenable_doors(
+            room_pickups,
+            &mut door_assignments,
+            &recolorable_docks,
+            &elevator_layout,
+            &starting_room_ids,
+            starting_items,
+        );
+        for (door, reason) in opened_doors {
+            let (_, _, room_info, room_doors) = all_room_doors.iter_mut()
+                .find(|(_, _, room_info, _)| room_info.room_id == door.room_id)
+                .unwrap();
+            for room_door in room_doors.iter_mut().filter(|room_door| room_door.1 == door.dock_number as usize && room_door.5) {
+                let blue = if room_door.2.is_vertical() { DoorType::VerticalBlue } else { DoorType::Blue };
+                writeln!(ct, "unopenable doors: {} door {} changed from {:?} to blue because {}", room_info.name, room_door.1, room_door.2, reason).unwrap();
+                room_door.2 = blue;
+                patched_door_types.insert((door.room_id, door.dock_number), Some(blue));
+            }
+        }
+    }
+
+    for (name, level, room_info, room_doors) in all_room_doors {
+        for (door_location, door_index, door_type, custom_door, blast_shield, _) in room_doors {
+            let spoiler_door_type = if custom_door.is_some() {
+                door_specification(config, level, room_info.name, door_index)?.clone()
+            } else if let Some(shield_type) = blast_shield {
+                format!("{:?}", shield_type)
+            } else {
+                format!("{:?}", door_type)
+            };
+            spoiler_log.doors.entry(spoiler_room_name(name, room_info.name))
+                .or_default()
+                .insert(door_index, spoiler_door_type);
+
+            patcher.add_scly_patch(
+                (name.as_bytes(), room_info.room_id),
+                move |_ps, area| patch_door(area,door_location,door_index,door_type,door_resources,config.powerbomb_lockpick)
+            );
+
+            if let Some(shield_type) = blast_shield {
+                patcher.add_scly_patch(
+                    (name.as_bytes(), room_info.room_id),
+                    move |ps, area| patch_blast_shield(ps, area, door_location, shield_type, door_resources)
+                );
+            }
+
+            if let Some(custom_door) = custom_door {
+                patcher.add_scly_patch(
+                    (name.as_bytes(), room_info.room_id),
+                    move |_ps, area| patch_custom_door(area, door_location, door_index, custom_door, door_type.is_vertical(), door_resources, config.powerbomb_lockpick)
+                );
+            }
+
+            if config.patch_map && room_info.mapa_id != 0 {
+                let map_object_type = if custom_door.is_some() {
+                    structs::MapaObjectType::DoorShield as u32
+                } else {
+                    door_type.map_object_type()
+                };
+                patcher.add_resource_patch(
+                    (&[name.as_bytes()], room_info.mapa_id,b"MAPA".into()),
+                    move |res| patch_map_door_icon(res,door_location,map_object_type)
+                );
+            }
+        }
+    }
+
+    if config.check_door_logic {
+        let (vanilla_doors, room_pickups, starting_items) = door_logic.as_ref().unwrap();
+        let door_assignments = with_patched_doors(vanilla_doors, &patched_door_types);
+        let warnings = check_door_logic(room_pickups, &door_assignments, &elevator_layout, &starting_room_ids, starting_items);
+        for warning in warnings {
+            writeln!(ct, "Warning - {}", warning).unwrap();
         }
     }
 
     // add additional items //
     for item in config.additional_items.iter()
     {
-        let room = spawn_room_from_string(item.room.to_string());
+        let room = spawn_room_from_string(item.room.to_string())?;
         patcher.add_scly_patch(
             (room.pak_name.as_bytes(), room.mrea),
             move |_ps, area| patch_add_item(_ps, area, PickupType::from_string(item.item_type.to_string()), item.position, pickup_resources, config),
         );
     }
 
+    // connect door openers //
+    for opener in config.door_openers.iter()
+    {
+        let room = spawn_room_from_string(opener.room.to_string())?;
+        let door_location = pickup_meta::PICKUP_LOCATIONS.iter()
+            .flat_map(|(_, rooms)| rooms.iter())
+            .find(|room_info| room_info.room_id == room.mrea)
+            .and_then(|room_info| room_info.door_locations.iter()
+                .find(|door_location| door_location.dock_number == Some(opener.dock)))
+            .copied()
+            .ok_or_else(|| format!("Room '{}' has no door {}", opener.room, opener.dock))?;
+        patcher.add_scly_patch(
+            (room.pak_name.as_bytes(), room.mrea),
+            move |_ps, area| patch_door_opener(area, door_location, opener.object_id, opener.state),
+        );
+    }
+
+    if config.warp_to_start {
+        for (pak_name, rooms) in pickup_meta::PICKUP_LOCATIONS.iter() {
+            for room_info in rooms.iter() {
+                if !room_info.name.starts_with("Save Station") || room_info.room_id == new_save_spawn_room.mrea {
+                    continue;
+                }
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id),
+                    move |ps, area| patch_save_station_warp_to_start(ps, area, new_save_spawn_room, version)
+                );
+            }
+        }
+    }
+
     if !config.is_item_randomized.unwrap_or(false) {
         let rel_config;
-        if config.skip_frigate {
+        if config.frigate_mode == FrigateMode::Skip {
             patcher.add_file_patch(
                 b"default.dol",
                 move |file| patch_dol(
@@ -4986,11 +7684,16 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
                     version,
                     config.nonvaria_heat_damage,
                     config.staggered_suit_damage,
+                    config.suit_damage_reduction,
+                    !config.disable_rel_patches,
                 )
             );
             patcher.add_file_patch(b"Metroid1.pak", empty_frigate_pak);
             rel_config = create_rel_config_file(new_save_spawn_room, config.quickplay);
         } else {
+            // Both the full and short frigate end at the Exterior Docking Hangar, so they share the
+            // teleporter patch. The short frigate only differs in where a new save spawns (see
+            // new_save_spawn_room above); the escape sequence itself is left untouched.
             patcher.add_file_patch(
                 b"default.dol",
                 |file| patch_dol(
@@ -4999,6 +7702,8 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
                     version,
                     config.nonvaria_heat_damage,
                     config.staggered_suit_damage,
+                    config.suit_damage_reduction,
+                    !config.disable_rel_patches,
                 )
             );
             patcher.add_scly_patch(
@@ -5008,10 +7713,12 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             rel_config = create_rel_config_file(new_save_spawn_room, config.quickplay);
         }
 
-        gc_disc.add_file(
-            "rel_config.bin",
-            structs::FstEntryFile::ExternalFile(Box::new(rel_config)),
-        )?;
+        if !config.disable_rel_patches {
+            gc_disc.add_file(
+                "rel_config.bin",
+                structs::FstEntryFile::ExternalFile(Box::new(rel_config)),
+            )?;
+        }
 
         // Patch the landing site to avoid loosing all items with custscene trigger //
         patcher.add_scly_patch(
@@ -5019,14 +7726,21 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             patch_landing_site_cutscene_triggers
         );
         
-        // New Save Room Starting Items //
+        // New Save Room Starting Items, applied to whichever room the new save spawns in //
         patcher.add_scly_patch(
             (new_save_spawn_room.pak_name.as_bytes(), new_save_spawn_room.mrea),
-            move |_ps, area| patch_starting_pickups(area, config.new_save_starting_items, false)
+            move |_ps, area| patch_starting_pickups(area, new_save_starting_items, false)
         );
 
+        if config.show_starting_items {
+            patcher.add_scly_patch(
+                (new_save_spawn_room.pak_name.as_bytes(), new_save_spawn_room.mrea),
+                move |ps, area| patch_starting_items_scan(ps, area, pickup_resources)
+            );
+        }
+
         // Post Frigate Starting Items //
-        if !config.skip_frigate && frigate_done_spawn_room.mrea != new_save_spawn_room.mrea { // but only if it won't override an existing patch
+        if config.frigate_mode != FrigateMode::Skip && frigate_done_spawn_room.mrea != new_save_spawn_room.mrea { // but only if it won't override an existing patch
             patcher.add_scly_patch(
                 (frigate_done_spawn_room.pak_name.as_bytes(), frigate_done_spawn_room.mrea),
                 move |_ps, area| patch_starting_pickups(area, config.frigate_done_starting_items, false)
@@ -5056,11 +7770,11 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
 
         patcher.add_resource_patch(
             resource_info!("STRG_Main.STRG").into(),// 0x0552a456
-            |res| patch_main_strg(res, &config.main_menu_message)
+            move |res| patch_main_strg(res, main_menu_message)
         );
         patcher.add_resource_patch(
             resource_info!("FRME_NewFileSelect.FRME").into(),
-            patch_main_menu
+            move |res| patch_main_menu(res, main_menu_message)
         );
 
         patcher.add_resource_patch(
@@ -5068,10 +7782,12 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             |res| patch_credits(res, &pickup_layout)
         );
 
-        patcher.add_resource_patch(
-            resource_info!("!MinesWorld_Master.SAVW").into(),
-            patch_mines_savw_for_phazon_suit_scan
-        );
+        for world in phazon_suit_scan_worlds(pickup_layout, config.phazon_suit_scan_follows_item) {
+            patcher.add_resource_patch(
+                world_savw(world).into(),
+                patch_savw_for_phazon_suit_scan
+            );
+        }
         patcher.add_scly_patch(
             resource_info!("07_stonehenge.MREA").into(),
             |ps, area| fix_artifact_of_truth_requirements(ps, area, &pickup_layout)
@@ -5080,6 +7796,18 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             resource_info!("07_stonehenge.MREA").into(),
             |ps, area| patch_artifact_hint_availability(ps, area, config.artifact_hint_behavior)
         );
+        if let ArtifactHintBehavior::NoHints = config.artifact_hint_behavior {
+            patcher.add_resource_patch(
+                resource_info!("HINT_Hints_1.HINT").into(),
+                patch_remove_hints
+            );
+        }
+        if config.skip_artifact_temple_intro {
+            patcher.add_scly_patch(
+                resource_info!("07_stonehenge.MREA").into(),
+                patch_artifact_temple_skip_intro
+            );
+        }
 
         patcher.add_resource_patch(
             resource_info!("TXTR_SaveBanner.TXTR").into(),
@@ -5111,9 +7839,38 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             );
         }
 
-        make_elevators_patch(&mut patcher, &elevator_layout, &config.elevator_layout_override, config.auto_enabled_elevators, config.tiny_elvetator_samus);
+        for (idx, (elv, dest)) in ELEVATORS.iter().zip(&elevator_layout).enumerate() {
+            // Overridden elevators keep the name of the elevator they replaced
+            let dest_name = config.elevator_layout_override.get(idx)
+                .cloned()
+                .unwrap_or_else(|| dest.name.replace('\0', " "));
+            spoiler_log.elevators.insert(elv.name.replace('\0', " "), dest_name);
+        }
+        for pickup_type in PickupType::iter() {
+            let count = starting_item_count(new_save_starting_items.to_u64(), pickup_type);
+            if count > 0 {
+                spoiler_log.starting_items.insert(pickup_type.name(), count);
+            }
+        }
+
+        make_elevators_patch(&mut patcher, &elevator_layout, &config.elevator_layout_override, config.auto_enabled_elevators, config.tiny_elvetator_samus, config.fast_elevators, &config.target_languages);
+
+        if config.fix_elite_research {
+            make_elite_research_fight_prereq_patches(&mut patcher);
+        }
+
+        if config.boss_item_independence {
+            make_boss_item_independence_patches(&mut patcher);
+        }
 
-        make_elite_research_fight_prereq_patches(&mut patcher);
+        if config.scan_known_destructibles {
+            for (idx, destructible) in DESTRUCTIBLE_SCANS.iter().enumerate() {
+                patcher.add_scly_patch(
+                    destructible.room.into(),
+                    move |ps, area| patch_destructible_scan(ps, area, idx, pickup_resources)
+                );
+            }
+        }
 
         patcher.add_scly_patch(
             resource_info!("22_Flaahgra.MREA").into(),
@@ -5127,10 +7884,17 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             resource_info!("00j_over_hall.MREA").into(),
             patch_temple_security_station_cutscene_trigger
         );
-        patcher.add_scly_patch(
-            resource_info!("01_ice_plaza.MREA").into(),
-            patch_ridley_phendrana_shorelines_cinematic
-        );
+        if config.skip_known_cutscenes {
+            for room in KNOWN_CUTSCENE_ROOMS.iter() {
+                patcher.add_scly_patch((*room).into(), skip_room_cutscenes);
+            }
+        }
+        if config.skip_ridley_shorelines_cinematic {
+            patcher.add_scly_patch(
+                resource_info!("01_ice_plaza.MREA").into(),
+                patch_ridley_phendrana_shorelines_cinematic
+            );
+        }
         patcher.add_scly_patch(
             resource_info!("08b_under_intro_ventshaft.MREA").into(),
             patch_main_ventilation_shaft_section_b_door
@@ -5146,14 +7910,24 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
             resource_info!("11_ice_observatory.MREA").into(),
             patch_observatory_2nd_pass_solvablility
         );
-        patcher.add_scly_patch(
-            resource_info!("02_mines_shotemup.MREA").into(),
-            patch_mines_security_station_soft_lock
-        );
+        if config.fix_mines_security_station {
+            patcher.add_scly_patch(
+                resource_info!("02_mines_shotemup.MREA").into(),
+                patch_mines_security_station_soft_lock
+            );
+        }
         patcher.add_scly_patch(
             resource_info!("18_ice_gravity_chamber.MREA").into(),
             patch_gravity_chamber_stalactite_grapple_point
         );
+        if config.gravity_chamber_item_accessible &&
+            !starting_items_contain(new_save_starting_items.to_u64(), PickupType::GrappleBeam)
+        {
+            patcher.add_scly_patch(
+                resource_info!("18_ice_gravity_chamber.MREA").into(),
+                patch_gravity_chamber_ledge_item
+            );
+        }
 
         if version == Version::Ntsc0_02 {
             patcher.add_scly_patch(
@@ -5199,11 +7973,16 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
 
     if config.enable_vault_ledge_door {
 
-        let door_specification = &config.excluded_doors[World::ChozoRuins as usize]["Main Plaza"][4];
+        let door_specification = door_specification(config, World::ChozoRuins as usize, "Main Plaza", 4)?;
         let door_type = match door_specification.as_str() {
-            "random"  => calculate_door_type("Metroid2.pak",&mut rng,&config.door_weights),
+            // Same roll as the door would get if it were randomized with the rest of the room
+            "random"  => random_door_type("Metroid2.pak", config.seed, 0xD5CDB809, 4, &config.door_weights),
             "default" => DoorType::Blue,
-            _         => DoorType::from_string(door_specification.to_string()).unwrap(),
+            // The two-way door is rebuilt from scratch, so a blast shield becomes the matching color
+            _         => BlastShieldType::from_door_specification(door_specification)
+                .map(|shield_type| shield_type.door_type())
+                .or_else(|| DoorType::from_string(door_specification.to_string()))
+                .ok_or_else(|| unknown_door_specification(door_specification, "Main Plaza", 4))?,
         };
 
         {
@@ -5221,6 +8000,938 @@ fn build_and_run_patches(gc_disc: &mut structs::GcDisc, config: &ParsedConfig, v
         }
     }
 
-    patcher.run(gc_disc)?;
+    patcher.protect_assets(config.protected_assets.iter().cloned());
+    patcher.add_custom_pickup_aabbs(config.custom_pickup_aabbs.iter().map(|(&cmdl, &aabb)| (cmdl, aabb)));
+    for warning in patcher.run(gc_disc)? {
+        writeln!(ct, "Warning - {}", warning).unwrap();
+    }
     Ok(())
 }
+
+#[test]
+fn test_artifact_temple_layer_flags()
+{
+    let truth_req_layer_id = 24;
+    let all_layers_mask = ARTIFACT_TEMPLE_LAYERS.iter()
+        .fold(0u64, |mask, &(_, layer)| mask | 1 << layer.unwrap_or(truth_req_layer_id));
+
+    // Every combination of placed artifacts, with each possible artifact at the temple
+    for placed in 0u32..(1 << 12) {
+        let pickup_kinds: Vec<u32> = (0..12).filter(|i| placed & (1 << i) != 0)
+            .map(|i| i + 29)
+            .collect();
+        for at_pickup_kind in (29..41).chain(Some(0)) {
+            let flags = artifact_temple_layer_flags(
+                !all_layers_mask,
+                truth_req_layer_id,
+                &pickup_kinds,
+                at_pickup_kind,
+            );
+            // Unrelated layers are untouched
+            assert_eq!(flags & !all_layers_mask, !all_layers_mask);
+
+            let required: Vec<(u32, u32)> = ARTIFACT_TEMPLE_LAYERS.iter()
+                .map(|&(kind, layer)| (kind, layer.unwrap_or(truth_req_layer_id)))
+                .filter(|&(kind, _)| pickup_kinds.contains(&kind) && kind != at_pickup_kind)
+                .collect();
+            for &(kind, layer) in ARTIFACT_TEMPLE_LAYERS.iter() {
+                let layer = layer.unwrap_or(truth_req_layer_id);
+                let active = flags & (1 << layer) != 0;
+                assert_eq!(active, !required.contains(&(kind, layer)));
+            }
+
+            // Collecting the required artifacts one by one, the Ridley gate (every artifact
+            // layer active) only opens once the last one is collected
+            let mut collected_flags = flags;
+            for &(_, layer) in required.iter() {
+                assert_ne!(collected_flags & all_layers_mask, all_layers_mask);
+                collected_flags |= 1 << layer;
+            }
+            assert_eq!(collected_flags & all_layers_mask, all_layers_mask);
+        }
+    }
+}
+
+#[test]
+fn test_parse_output_spec()
+{
+    let spec = |s| parse_output_spec(s).unwrap();
+    assert_eq!(spec("gcz:/out/prime.gcz"), (IsoFormat::Gcz, PathBuf::from("/out/prime.gcz")));
+    assert_eq!(spec("CISO:prime.iso"), (IsoFormat::Ciso, PathBuf::from("prime.iso")));
+    assert_eq!(spec("iso:prime.gcz"), (IsoFormat::Iso, PathBuf::from("prime.gcz")));
+    assert_eq!(spec("prime.ciso"), (IsoFormat::Ciso, PathBuf::from("prime.ciso")));
+    assert_eq!(spec("rvz:prime"), (IsoFormat::Rvz, PathBuf::from("prime")));
+    assert_eq!(spec("prime.rvz"), (IsoFormat::Rvz, PathBuf::from("prime.rvz")));
+    assert_eq!(spec("C:\\prime.GCZ"), (IsoFormat::Gcz, PathBuf::from("C:\\prime.GCZ")));
+    assert_eq!(spec("prime"), (IsoFormat::Iso, PathBuf::from("prime")));
+    assert!(parse_output_spec("").is_err());
+    assert!(parse_output_spec("gcz:").is_err());
+    assert!(parse_output_spec("wbfs:prime.wbfs").is_err());
+    assert!(parse_output_spec("gcé:prime").is_ok());
+    assert_eq!(serde_json::from_str::<IsoFormat>("\"rvz\"").unwrap(), IsoFormat::Rvz);
+}
+
+#[test]
+fn test_rng_golden_values()
+{
+    // These pin down how the seed is consumed, so that a change to the order or number of RNG
+    // calls (which would change every seed's output) is caught. Only update them on purpose.
+    let weights = test_weights();
+    let mut door_rng = StdRng::seed_from_u64(1234);
+    let mut doors = vec![];
+    for _ in 0..12 {
+        doors.push(calculate_door_type("Metroid2.pak", &mut door_rng, &weights));
+    }
+    for _ in 0..4 {
+        doors.push(calculate_colored_door_type("Metroid2.pak", &mut door_rng, &weights).unwrap());
+    }
+    use DoorType::*;
+    assert_eq!(doors, vec![
+        Blue, Blue, White, Red, Red, Purple, White, Purple, Purple, Red, Blue, Blue,
+        White, Red, White, Red,
+    ]);
+
+    // A fixed layout with every pickup type in it, rolled the way build_and_run_patches rolls it
+    let pickup_layout: Vec<_> = (0..100).map(|i| PickupType::from_idx(i % 36).unwrap()).collect();
+    let rolls = roll_seed(1234, &pickup_layout, None);
+    let totems: Vec<(&str, &str)> = rolls.artifact_totem_strings.iter()
+        .map(|s| {
+            let room = s.split("#43CD80;").nth(1).unwrap().split("&pop;").next().unwrap();
+            (&s[..12], room)
+        })
+        .collect();
+    assert_eq!(totems, vec![
+        ("To be honest", "Watery Hall"),
+        ("Some say it ", "Watery Hall"),
+        ("Hear the wor", "Dynamo"),
+        ("I kind of ju", "Dynamo"),
+        ("So a buddy o", "Burn Dome"),
+        ("Okay, so thi", "Burn Dome"),
+        ("Uhh umm... W", "Furnace"),
+        ("Hey man, so ", "Furnace"),
+        ("I uhhh... wa", "Hall of the Elders"),
+        ("So, uhhh, I ", "Crossway"),
+        ("I'm not sure", "Elder Chamber"),
+        ("I mean, mayb", "Antechamber"),
+    ]);
+    assert_eq!(rolls.select_game_fmv_suffix, "B");
+
+    // A layout of only missiles, with better odds, so that some of them come out shiny
+    let missiles = vec![PickupType::Missile; 100];
+    let rolls = roll_seed(1234, &missiles, Some(16));
+    let shiny_missiles: Vec<usize> = rolls.placed_layout.iter().enumerate()
+        .filter(|(_, &pickup_type)| pickup_type == PickupType::ShinyMissile)
+        .map(|(idx, _)| idx)
+        .collect();
+    assert_eq!(shiny_missiles, vec![16, 45, 49, 79, 97, 98]);
+    assert_eq!(roll_seed(1234, &missiles, Some(0)).placed_layout, missiles);
+}
+
+#[test]
+fn test_spawn_room_from_string_errors()
+{
+    assert_eq!(spawn_room_from_string("tallon:landing site".to_string()).unwrap().mrea, 0xB2701146);
+    let err = spawn_room_from_string("tallon:landing sight".to_string()).unwrap_err();
+    assert!(err.contains("did you mean 'tallon:Landing Site'"), "{}", err);
+    let err = spawn_room_from_string("talon:landing site".to_string()).unwrap_err();
+    assert!(err.contains("Tallon Overworld"), "{}", err);
+    assert!(spawn_room_from_string("landing site".to_string()).is_err());
+}
+
+#[test]
+fn test_check_door_logic()
+{
+    let landing_site = SpawnRoom::landing_site_spawn_room().mrea;
+    let room_pickups: HashMap<_, _> = [
+        (0x1, vec![PickupType::IceBeam]),
+        (0x2, vec![PickupType::WaveBeam]),
+    ].iter().cloned().collect();
+    let doors = [
+        DoorAssignment { room_id: landing_site, dock_number: 0, door_type: Some(DoorType::Blue), destination_room_id: 0x1 },
+        DoorAssignment { room_id: 0x1, dock_number: 1, door_type: Some(DoorType::Purple), destination_room_id: 0x2 },
+    ];
+    let warnings = check_door_logic(&room_pickups, &doors, &[], &[landing_site], &HashSet::new());
+    assert_eq!(warnings, vec![
+        "Room 0x00000001 door 1 is Purple, but Wave Beam isn't reachable before it".to_string(),
+        "Room 0x00000002 can't be reached, so its pickups can't be collected".to_string(),
+    ]);
+}
+
+#[test]
+fn test_open_unopenable_doors()
+{
+    let room_pickups: HashMap<_, _> = [
+        (0x2, vec![PickupType::WaveBeam]),
+        (0x4, vec![PickupType::IceBeam]),
+        (0x5, vec![PickupType::Missile]),
+    ].iter().cloned().collect();
+    let door = |room_id, dock_number, door_type, destination_room_id| DoorAssignment {
+        room_id, dock_number, door_type: Some(door_type), destination_room_id,
+    };
+    let mut doors = [
+        door(0x1, 0, DoorType::Purple, 0x2),
+        door(0x2, 0, DoorType::White, 0x3),
+        door(0x1, 1, DoorType::Blue, 0x4),
+        door(0x3, 0, DoorType::Solid, 0x5),
+    ];
+    let recolorable_docks: HashSet<_> = [(0x1, 0), (0x2, 0)].iter().cloned().collect();
+
+    let opened_doors = open_unopenable_doors(&room_pickups, &mut doors, &recolorable_docks, &[], &[0x1], &HashSet::new());
+    assert_eq!(opened_doors, vec![
+        (door(0x1, 0, DoorType::Purple, 0x2), "Wave Beam isn't reachable before it".to_string()),
+    ]);
+    assert_eq!(doors[0].door_type, Some(DoorType::Blue));
+    // The Ice Beam is behind a blue door, and the solid door wasn't randomly colored
+    assert_eq!(doors[1].door_type, Some(DoorType::White));
+    assert_eq!(doors[3].door_type, Some(DoorType::Solid));
+}
+
+#[test]
+fn test_add_pickup_scan_overrides()
+{
+    let mut pickup_resources = HashMap::new();
+    let mut overrides = HashMap::new();
+    overrides.insert(3, "A hint".to_string());
+    overrides.insert(7, "Another hint\0".to_string());
+    add_pickup_scan_overrides(&mut pickup_resources, &overrides, 100).unwrap();
+    assert_eq!(pickup_resources.len(), 4);
+    let (scan, strg) = pickup_scan_override_ids(3);
+    assert!(pickup_resources.contains_key(&(scan, b"SCAN".into())));
+    assert!(pickup_resources.contains_key(&(strg, b"STRG".into())));
+
+    overrides.insert(100, "Out of range".to_string());
+    assert!(add_pickup_scan_overrides(&mut HashMap::new(), &overrides, 100).is_err());
+}
+
+#[test]
+fn test_randomize_elevator_layout()
+{
+    for seed in 0..20 {
+        let layout = randomize_elevator_layout(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(layout.len(), ELEVATORS.len());
+
+        let mut sorted = layout.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..ELEVATORS.len() as u8).collect::<Vec<_>>());
+
+        for (i, (elv, &dest)) in ELEVATORS.iter().zip(&layout).enumerate() {
+            if elv.name == "Artifact Temple" || elv.name == "Crater Entry Point" {
+                assert_eq!(dest, elv.default_dest);
+            } else {
+                assert_ne!(dest as usize, i);
+            }
+            // Every elevator leads back to the one that leads to it
+            assert_eq!(layout[dest as usize] as usize, i);
+        }
+    }
+}
+
+#[test]
+fn test_spoiler_log_json()
+{
+    let mut spoiler_log = SpoilerLog::default();
+    let room = spoiler_room_name("Metroid4.pak", "Landing Site");
+    spoiler_log.pickups.insert(room.clone(), vec![PickupType::Missile.name()]);
+    spoiler_log.starting_items.insert(PickupType::EnergyTank.name(), 2);
+
+    let json: serde_json::Value = serde_json::to_value(&spoiler_log).unwrap();
+    assert_eq!(room, "Tallon Overworld:Landing Site");
+    assert_eq!(json["pickups"]["Tallon Overworld:Landing Site"][0], "Missile");
+    assert_eq!(json["startingItems"]["Energy Tank"], 2);
+    assert!(json["doors"].as_object().unwrap().is_empty());
+}
+
+#[test]
+fn test_artifact_totem_scan_strings_with_duplicates()
+{
+    let mut pickup_layout = vec![PickupType::Missile; 100];
+    pickup_layout[0] = PickupType::ArtifactOfTruth;
+    pickup_layout[50] = PickupType::ArtifactOfTruth;
+    let totem_strings = build_artifact_temple_totem_scan_strings(&pickup_layout, &mut StdRng::seed_from_u64(0));
+
+    let rooms: Vec<&str> = pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|i| i.1.iter())
+        .flat_map(|l| iter::repeat_n(l.name, l.pickup_locations.len()))
+        .collect();
+    let truth = &totem_strings[PickupType::ArtifactOfTruth.idx() - PickupType::ArtifactOfLifegiver.idx()];
+    assert!(truth.contains(&format!("#43CD80;{}&pop; or the &push;&main-color=#43CD80;{}&pop;", rooms[0], rooms[50])));
+
+    let strength = &totem_strings[PickupType::ArtifactOfStrength.idx() - PickupType::ArtifactOfLifegiver.idx()];
+    assert!(strength.starts_with("Artifact not present"));
+}
+
+#[test]
+fn test_starting_items_round_trip()
+{
+    // scan visor, 5 missiles, 2 energy tanks, morph ball and flamethrower
+    let bits = 1 | (5 << 1) | (2 << 9) | (1 << 21) | (1 << 35);
+    let items = StartingItems::from_u64(bits);
+    assert!(items.scan_visor && items.morph_ball && items.flamethrower);
+    assert!(!items.bombs);
+    assert_eq!((items.missiles, items.energy_tanks, items.power_bombs), (5, 2, 0));
+    assert_eq!(items.to_u64(), bits);
+
+    let legacy: StartingItemsConfig = serde_json::from_str(&bits.to_string()).unwrap();
+    let named: StartingItemsConfig = serde_json::from_str(
+        r#"{"scanVisor": true, "missiles": 5, "energyTanks": 2, "morphBall": true, "flamethrower": true}"#
+    ).unwrap();
+    assert_eq!(StartingItems::from(legacy), items);
+    assert_eq!(StartingItems::from(named), items);
+
+    assert!(items.validate("starting_items").is_ok());
+    let items = StartingItems { energy_tanks: 16, ..items };
+    assert_eq!(items.validate("starting_items"), Err("starting_items gives 16 energyTanks, at most 15 are supported".to_string()));
+}
+
+#[test]
+fn test_door_rng_independent_of_room_order()
+{
+    let weights = test_weights();
+
+    // Every door the patcher rolls a color for, in the order it visits them
+    let doors: Vec<_> = pickup_meta::PICKUP_LOCATIONS.iter()
+        .flat_map(|(pak_name, rooms)| rooms.iter().map(move |room_info| (*pak_name, room_info)))
+        .flat_map(|(pak_name, room_info)| room_info.door_locations.iter()
+            .filter_map(move |door_location| door_location.dock_number)
+            .map(move |dock_number| (pak_name, room_info.room_id, dock_number)))
+        .collect();
+    assert!(!doors.is_empty());
+
+    let forward: Vec<_> = doors.iter()
+        .map(|&(pak_name, room_id, dock_number)| random_door_type(pak_name, 42, room_id, dock_number, &weights))
+        .collect();
+    let mut backward: Vec<_> = doors.iter().rev()
+        .map(|&(pak_name, room_id, dock_number)| random_door_type(pak_name, 42, room_id, dock_number, &weights))
+        .collect();
+    backward.reverse();
+    assert_eq!(forward, backward);
+
+    // Pinned so a change to the derivation, which would reshuffle every existing seed, is noticed
+    let pinned = [
+        (0x3E6B2BB7, 0, DoorType::Blue),
+        (0xB7F1952A, 1, DoorType::Purple),
+        (0xB7F1952A, 0, DoorType::White),
+        (0xD5CDB809, 1, DoorType::Blue),
+        (0xD5CDB809, 3, DoorType::Purple),
+        (0xD5CDB809, 0, DoorType::Blue),
+    ];
+    for (room_id, dock_number, door_type) in pinned {
+        let i = doors.iter().position(|&door| door == ("Metroid2.pak", room_id, dock_number)).unwrap();
+        assert_eq!(forward[i], door_type);
+    }
+
+    let other_seed: Vec<_> = doors.iter()
+        .map(|&(pak_name, room_id, dock_number)| random_door_type(pak_name, 43, room_id, dock_number, &weights))
+        .collect();
+    assert_ne!(forward, other_seed);
+}
+
+#[test]
+fn test_excluded_pickups()
+{
+    assert_eq!(parse_excluded_pickup("Tallon:Landing Site"), Ok(("tallon".to_string(), "landing site".to_string(), None)));
+    assert_eq!(parse_excluded_pickup("Chozo:Main Plaza:3"), Ok(("chozo".to_string(), "main plaza".to_string(), Some(3))));
+    assert!(parse_excluded_pickup("Main Plaza").is_err());
+
+    let indices = |excluded: &[&str]| excluded_pickup_indices(&excluded.iter().map(|s| s.to_string()).collect());
+    let main_plaza = indices(&["chozo ruins:main plaza"]).unwrap();
+    assert_eq!(main_plaza.len(), 4);
+    let one_location = indices(&["CHOZO:Main Plaza:1"]).unwrap();
+    assert_eq!(one_location.len(), 1);
+    assert!(one_location.is_subset(&main_plaza));
+    assert_eq!(indices(&["Tallon:Landing Site", "Chozo:Main Plaza:1"]).unwrap().len(), 2);
+    assert!(indices(&["Chozo:Main Plaza:4"]).is_err());
+    assert!(indices(&["Chozo:Not A Room"]).is_err());
+    assert!(indices(&["Tallon:Main Plaza"]).is_err());
+}
+
+#[test]
+fn test_forwarded_camera_connections()
+{
+    let conn = |state, message, target_object_id| structs::Connection { state, message, target_object_id };
+    use structs::{ConnectionState as S, ConnectionMsg as M};
+
+    // Camera 1 fades (10), hides Samus (11) and chains into camera 2, which opens a door (20)
+    // when it finishes. Camera 2 points back at 1, which mustn't loop forever. Camera 1 also
+    // turns a trigger (40) off while it plays, which only the finish turning it back on survives.
+    let mut cameras = HashMap::new();
+    cameras.insert(1, vec![
+        conn(S::ACTIVE, M::ACTIVATE, 10),
+        conn(S::ACTIVE, M::ACTIVATE, 11),
+        conn(S::INACTIVE, M::ACTIVATE, 2),
+        conn(S::ACTIVE, M::INCREMENT, 30),
+        conn(S::ACTIVE, M::DEACTIVATE, 40),
+        conn(S::INACTIVE, M::ACTIVATE, 40),
+    ]);
+    cameras.insert(2, vec![
+        conn(S::INACTIVE, M::OPEN, 20),
+        conn(S::INACTIVE, M::ACTIVATE, 1),
+        conn(S::ZERO, M::OPEN, 21),
+    ]);
+    let cinematic_only: HashSet<u32> = [10, 11].iter().cloned().collect();
+
+    let forwarded = forwarded_camera_connections(&cameras, &cinematic_only, 1, &mut HashSet::new());
+    let forwarded: Vec<_> = forwarded.iter().map(|c| (c.message, c.target_object_id)).collect();
+    assert_eq!(forwarded, vec![(M::INCREMENT, 30), (M::OPEN, 20), (M::ACTIVATE, 40)]);
+}
+
+#[test]
+fn test_main_menu_message_lines()
+{
+    assert_eq!(main_menu_message_lines(""), 0);
+    assert_eq!(main_menu_message_lines("randomprime v1.0"), 1);
+    assert_eq!(main_menu_message_lines("randomprime v1.0\nSeed: 12345"), 2);
+    assert_eq!(main_menu_message_lines(&"x".repeat(MAIN_MENU_MESSAGE_LINE_CHARS + 1)), 2);
+    assert_eq!(main_menu_message_lines("a\n\nb"), 3);
+}
+
+#[test]
+fn test_truncate_encoded_str()
+{
+    assert_eq!(truncate_encoded_str("Metroid Prime", 64), "Metroid Prime");
+    assert_eq!(truncate_encoded_str("Metroid Prime", 9), "Metroid");
+    assert_eq!(truncate_encoded_str("Metroid Prime", 1), "");
+}
+
+// A config with every patch turned off, for tests that call patch functions directly
+#[cfg(test)]
+fn test_config() -> ParsedConfig
+{
+    ParsedConfig {
+        input_iso: None,
+        input_iso_format: None,
+        output_iso: None,
+        layout_string: String::new(),
+        is_item_randomized: None,
+        pickup_layout: vec![],
+        pickup_layout_typed: None,
+        elevator_layout: vec![],
+        elevator_layout_override: vec![],
+        randomize_elevators: false,
+        missile_lock_override: vec![],
+        superheated_rooms: vec![],
+        deheated_rooms: vec![],
+        drain_liquid_rooms: vec![],
+        underwater_rooms: vec![],
+        liquid_volumes: vec![],
+        aether_transforms: vec![],
+        additional_items: vec![],
+        door_openers: vec![],
+        pickup_scan_overrides: HashMap::new(),
+        shiny_missile_odds: None,
+        excluded_pickups: HashSet::new(),
+        new_save_spawn_room: String::new(),
+        frigate_done_spawn_room: String::new(),
+        item_seed: 0,
+        seed: 0,
+        door_weights: test_weights(),
+        strict_door_weights: false,
+        excluded_doors: Default::default(),
+        custom_door_types: vec![],
+        extra_assets: vec![],
+        protected_assets: HashSet::new(),
+        custom_pickup_aabbs: HashMap::new(),
+        patch_map: false,
+        patch_power_conduits: false,
+        remove_missile_locks: false,
+        remove_frigidite_lock: false,
+        remove_mine_security_station_locks: false,
+        lower_mines_backwards: false,
+        biohazard_containment_alt_spawn: false,
+        remove_hall_of_the_elders_forcefield: false,
+        iso_format: IsoFormat::Iso,
+        frigate_mode: FrigateMode::Full,
+        skip_hudmenus: false,
+        mute_item_fanfare: false,
+        keep_fmvs: false,
+        obfuscate_items: false,
+        obfuscate_scans: false,
+        nonvaria_heat_damage: false,
+        heat_damage_per_sec: None,
+        staggered_suit_damage: false,
+        suit_damage_reduction: [0.0; 4],
+        auto_enabled_elevators: false,
+        powerbomb_lockpick: false,
+        quiet: true,
+        tiny_elvetator_samus: false,
+        fast_elevators: false,
+        boss_item_independence: false,
+        fix_elite_research: false,
+        fix_mines_security_station: false,
+        disable_rel_patches: false,
+        skip_ridley_shorelines_cinematic: false,
+        skip_known_cutscenes: false,
+        reveal_full_map: false,
+        phazon_suit_scan_follows_item: false,
+        scan_known_destructibles: false,
+        skip_artifact_temple_intro: false,
+        print_disc_info: false,
+        validate_only: false,
+        target_languages: vec![Language::English],
+        skip_impact_crater: false,
+        enable_vault_ledge_door: false,
+        artifact_hint_behavior: ArtifactHintBehavior::Default,
+        patch_vertical_to_blue: false,
+        min_colored_doors_per_area: 0,
+        check_door_logic: false,
+        remove_unopenable_doors: false,
+        show_seed_ingame: false,
+        show_starting_items: false,
+        force_scan_visor: false,
+        warp_to_start: false,
+        gravity_chamber_item_accessible: false,
+        flaahgra_music_files: None,
+        replace_audio: HashMap::new(),
+        new_save_starting_items: StartingItems::default(),
+        frigate_done_starting_items: StartingItems::default(),
+        comment: String::new(),
+        main_menu_message: String::new(),
+        quickplay: false,
+        bnr_game_name: None,
+        bnr_developer: None,
+        bnr_game_name_full: None,
+        bnr_developer_full: None,
+        bnr_description: None,
+        bnr_seed_name: false,
+        pal_override: false,
+        print_writer_regions: false,
+        spoiler_log: None,
+        pickup_positions_file: None,
+    }
+}
+
+// Door weights that give every color the same chance in every world
+#[cfg(test)]
+fn test_weights() -> Weights
+{
+    Weights {
+        tallon_overworld: [25, 25, 25, 25],
+        chozo_ruins: [25, 25, 25, 25],
+        magmoor_caverns: [25, 25, 25, 25],
+        phendrana_drifts: [25, 25, 25, 25],
+        phazon_mines: [25, 25, 25, 25],
+    }
+}
+
+// The state PrimePatcher::run starts its patches with
+#[cfg(test)]
+fn test_patcher_state() -> PatcherState
+{
+    PatcherState {
+        fresh_instance_id_range: FIRST_FRESH_INSTANCE_ID..,
+        custom_pickup_aabbs: HashMap::new(),
+        warnings: Vec::new(),
+    }
+}
+
+// A world with a single area (mrea_id) that has one layer, named "Default", and no dependencies
+#[cfg(test)]
+fn test_mlvl_bytes(mrea_id: u32) -> Vec<u8>
+{
+    let mut bytes = vec![];
+    let push_u32 = |bytes: &mut Vec<u8>, v: u32| bytes.extend_from_slice(&v.to_be_bytes());
+
+    for &v in &[0xDEAFBABE, 0x11, 0xFFFFFFFF, 0xFFFFFFFF, 0xFFFFFFFF] {
+        push_u32(&mut bytes, v);
+    }
+    push_u32(&mut bytes, 0); // memory relay connections
+    push_u32(&mut bytes, 1); // areas
+    push_u32(&mut bytes, 1);
+
+    push_u32(&mut bytes, 0xFFFFFFFF); // area name
+    bytes.extend(iter::repeat_n(0, 4 * (12 + 6))); // transform and bounding box
+    push_u32(&mut bytes, mrea_id);
+    push_u32(&mut bytes, 0); // internal id
+    push_u32(&mut bytes, 0); // attached areas
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, 0); // dependencies
+    push_u32(&mut bytes, 2); // dependency offsets, one per layer plus one for the area itself
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, 0); // docks
+
+    push_u32(&mut bytes, 0xFFFFFFFF); // world map
+    bytes.push(0);
+    push_u32(&mut bytes, 0); // script instances
+    push_u32(&mut bytes, 0); // audio groups
+    bytes.push(0);
+
+    push_u32(&mut bytes, 1); // layer flags
+    push_u32(&mut bytes, 1);
+    bytes.extend_from_slice(&1u64.to_be_bytes());
+
+    push_u32(&mut bytes, 1); // layer names
+    bytes.extend_from_slice(b"Default\0");
+    push_u32(&mut bytes, 1);
+    push_u32(&mut bytes, 0);
+
+    let len = bytes.len();
+    bytes.extend(reader_writer::pad_bytes(32, len).iter());
+    bytes
+}
+
+// Run f on an area built in memory from test_mlvl_bytes and a MREA holding objects on its
+// only layer, without needing a disc
+#[cfg(test)]
+fn with_test_area<F>(mrea_id: u32, objects: Vec<structs::SclyObject<'static>>, f: F)
+    where F: for<'mlvl, 'cursor, 'list> FnOnce(&mut mlvl_wrapper::MlvlArea<'static, 'mlvl, 'cursor, 'list>)
+{
+    // Leaked so the area's lifetime matches the 'static objects and resources tests pass in
+    let mlvl_bytes: &'static [u8] = Box::leak(test_mlvl_bytes(mrea_id).into_boxed_slice());
+    let mlvl: structs::Mlvl = Reader::new(mlvl_bytes).read(());
+
+    let mrea = structs::Mrea {
+        area_transform: [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+        world_model_count: 0,
+        world_geometry_section_idx: 0,
+        scly_section_idx: 0,
+        collision_section_idx: 0,
+        unknown_section_idx: 0,
+        lights_section_idx: 0,
+        visibility_tree_section_idx: 0,
+        path_section_idx: 0,
+        area_octree_section_idx: 0,
+        sections: vec![structs::MreaSection::Scly(structs::Scly {
+            unknown: 1,
+            layers: vec![structs::SclyLayer {
+                unknown: 0,
+                objects: objects.into(),
+            }].into(),
+        })].into(),
+    };
+    let mut resources: reader_writer::DiffList<structs::ResourceSource> = iter::once(
+        pickup_meta::build_resource(mrea_id, structs::ResourceKind::Mrea(mrea))
+    ).collect();
+
+    let mut cursor = resources.cursor();
+    let mut mlvl_editor = mlvl_wrapper::MlvlEditor::new(mlvl);
+    f(&mut mlvl_editor.get_area(&mut cursor));
+}
+
+#[test]
+fn test_modify_pickups_in_mrea()
+{
+    let pickup_location = pickup_meta::PickupLocation {
+        location: pickup_meta::ScriptObjectLocation { layer: 0, instance_id: 1 },
+        attainment_audio: pickup_meta::ScriptObjectLocation { layer: 0, instance_id: 2 },
+        hudmemo: pickup_meta::ScriptObjectLocation { layer: 0, instance_id: 3 },
+        post_pickup_relay_connections: &[],
+    };
+    let objects = || vec![
+        structs::SclyObject {
+            instance_id: 1,
+            connections: vec![].into(),
+            property_data: structs::SclyProperty::Pickup(PickupType::Missile.pickup_data().clone()),
+        },
+        structs::SclyObject {
+            instance_id: 2,
+            connections: vec![].into(),
+            property_data: structs::SclyProperty::StreamedAudio(structs::StreamedAudio {
+                name: b"attainment audio\0".as_cstr(),
+                active: 1,
+                audio_file_name: b"/audio/itm_x_short_02.dsp\0".as_cstr(),
+                no_stop_on_deactivate: 0,
+                fade_in_time: 0.0,
+                fade_out_time: 0.0,
+                volume: 127,
+                oneshot: 1,
+                is_music: 1,
+            }),
+        },
+        structs::SclyObject {
+            instance_id: 3,
+            connections: vec![].into(),
+            property_data: structs::SclyProperty::HudMemo(structs::HudMemo {
+                name: b"hudmemo\0".as_cstr(),
+                first_message_timer: 3.0,
+                unknown: 1,
+                memo_type: 1,
+                strg: 0xFFFFFFFF,
+                active: 1,
+            }),
+        },
+    ];
+    let config = test_config();
+
+    for &(pickup_type, is_artifact) in &[(PickupType::VariaSuit, false), (PickupType::ArtifactOfWild, true)] {
+        let maybe_obfuscated = MaybeObfuscatedPickup::Unobfuscated(pickup_type);
+        let mut pickup_resources = HashMap::new();
+        let deps = maybe_obfuscated.dependencies().iter().cloned()
+            .chain(iter::once((maybe_obfuscated.hudmemo_strg(), b"STRG".into())));
+        for (file_id, fourcc) in deps {
+            let res = pickup_meta::build_resource(file_id, structs::ResourceKind::External(vec![], fourcc));
+            pickup_resources.insert((file_id, fourcc), res);
+        }
+
+        with_test_area(0x1234, objects(), |area| {
+            let mut ps = test_patcher_state();
+            modify_pickups_in_mrea(&mut ps, area, 0, pickup_type, pickup_location, &pickup_resources,
+                                   &config).unwrap();
+
+            assert_eq!(area.layer_flags.layer_count, 2);
+            assert_eq!(area.layer_names().len(), 2);
+
+            let layers = area.mrea().scly_section().layers.iter()
+                .map(|layer| layer.into_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(layers.len(), 2);
+            let new_objects: Vec<_> = layers[1].objects.iter().map(|obj| obj.into_owned()).collect();
+
+            let relay_name = b"Randomizer Post Pickup Relay\0".as_cstr();
+            assert!(new_objects.iter().any(|obj| {
+                obj.property_data.as_relay().map(|relay| relay.name == relay_name).unwrap_or(false)
+            }));
+            let layer_change_count = new_objects.iter()
+                .filter(|obj| obj.property_data.is_special_function())
+                .count();
+            assert_eq!(layer_change_count, if is_artifact { 1 } else { 0 });
+
+            let pickup = layers[0].objects.iter()
+                .find(|obj| obj.instance_id == 1)
+                .unwrap()
+                .property_data.as_pickup().unwrap().into_owned();
+            assert_eq!(pickup.kind, pickup_type.pickup_data().kind);
+        });
+    }
+}
+
+#[test]
+fn test_unknown_door_specification()
+{
+    let msg = unknown_door_specification("purpel", "Main Plaza", 4);
+    assert!(msg.starts_with("Unknown door type 'purpel' for door 4 in Main Plaza."));
+    assert!(msg.contains("purple, "));
+    assert!(msg.ends_with(DoorType::all_names().last().unwrap()));
+}
+
+#[test]
+fn test_calculate_door_type_relative_weights()
+{
+    let mut weights = test_config().door_weights;
+    weights.chozo_ruins = [0, 0, 0, 3];
+    weights.phendrana_drifts = [200, 0, 200, 0];
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        assert_eq!(calculate_door_type("Metroid2.pak", &mut rng, &weights), DoorType::Red);
+        let door_type = calculate_door_type("Metroid3.pak", &mut rng, &weights);
+        assert!(door_type == DoorType::Blue || door_type == DoorType::White);
+    }
+}
+
+#[test]
+fn test_frigate_starting_items()
+{
+    let starting_items = StartingItems { missiles: 5, ..StartingItems::default() };
+    let frigate_items = frigate_starting_items(starting_items);
+    assert!(frigate_items.scan_visor && frigate_items.morph_ball);
+    assert_eq!(frigate_items.missiles, 5);
+    assert_eq!(frigate_starting_items(frigate_items), frigate_items);
+}
+
+#[test]
+fn test_new_save_scan_visor()
+{
+    let mut config = test_config();
+    config.frigate_mode = FrigateMode::Skip;
+    config.pickup_layout = vec![PickupType::Missile.idx() as u8];
+    assert!(new_save_starting_items(&config).scan_visor);
+
+    // A shuffled Scan Visor is left to the layout
+    config.pickup_layout.push(PickupType::ScanVisor.idx() as u8);
+    assert!(!new_save_starting_items(&config).scan_visor);
+    config.force_scan_visor = true;
+    assert!(new_save_starting_items(&config).scan_visor);
+}
+
+#[test]
+fn test_add_user_assets()
+{
+    let txtr: FourCC = b"TXTR".into();
+    let mut found = HashMap::new();
+    found.insert((0x88ED4593, txtr), pickup_meta::build_resource(
+        0x88ED4593,
+        structs::ResourceKind::External(vec![0; 32], txtr),
+    ));
+    let mut looking_for: HashSet<_> = [(0x88ED4593, txtr), (0xDEAD0001, txtr)].iter().cloned().collect();
+    add_user_assets(&mut found, &mut looking_for, &[
+        (0x88ED4593, txtr, vec![1; 40]),
+        (0xDEAD0001, txtr, vec![2; 8]),
+    ]);
+    assert!(looking_for.is_empty());
+    match &found[&(0x88ED4593, txtr)].kind {
+        structs::ResourceKind::External(bytes, _) => assert_eq!(&bytes[..], &[&[1; 40][..], &[0; 24][..]].concat()[..]),
+        _ => panic!(),
+    }
+    assert_eq!(found.len(), 2);
+}
+
+#[test]
+fn test_check_dsp()
+{
+    let mut dsp = vec![0; 0x60 + 8];
+    dsp[4..8].copy_from_slice(&16u32.to_be_bytes());
+    dsp[8..12].copy_from_slice(&32000u32.to_be_bytes());
+    assert_eq!(check_dsp(&dsp), Ok(()));
+    assert_eq!(check_dsp(&dsp[..0x60 + 7]), Err("is truncated, its header has 16 nibbles".to_string()));
+    assert_eq!(check_dsp(&dsp[..0x20]), Err("is too short to be a DSP file".to_string()));
+    dsp[8..12].copy_from_slice(&0u32.to_be_bytes());
+    assert_eq!(check_dsp(&dsp), Err("has a sample rate of 0".to_string()));
+}
+
+#[test]
+fn test_vertical_door_type()
+{
+    assert!(is_vertical_door(0x11BD63B7, 0));
+    assert!(!is_vertical_door(0x11BD63B7, 1));
+    assert!(is_vertical_door(0xA20201D4, 1));
+    // The vault ledge door in Main Plaza
+    assert!(!is_vertical_door(0xD5CDB809, 4));
+
+    assert_eq!(vertical_door_type(DoorType::Purple, true), DoorType::VerticalBlue);
+    assert_eq!(vertical_door_type(DoorType::Purple, false), DoorType::VerticalPurple);
+    assert_eq!(vertical_door_type(DoorType::VerticalBlue, false), DoorType::VerticalBlue);
+}
+
+#[test]
+fn test_boss_vulnerability_skips_doors()
+{
+    let trigger = |instance_id| structs::SclyObject {
+        instance_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::DamageableTrigger(structs::DamageableTrigger {
+            name: b"trigger\0".as_cstr(),
+            position: [0.0, 0.0, 0.0].into(),
+            scale: [1.0, 1.0, 1.0].into(),
+            health_info: structs::structs::HealthInfo {
+                health: 1.0,
+                knockback_resistance: 1.0,
+            },
+            damage_vulnerability: DoorType::Missile.vulnerability(),
+            unknown0: 0,
+            pattern_txtr0: 0xFFFFFFFF,
+            pattern_txtr1: 0xFFFFFFFF,
+            color_txtr: 0xFFFFFFFF,
+            lock_on: 0,
+            active: 1,
+            visor_params: structs::structs::VisorParameters {
+                unknown0: 0,
+                target_passthrough: 0,
+                unknown2: 15,
+            },
+        }),
+    };
+    let weak_point_id = 0x00240000;
+    let door_force_id = 2359350; // One of Hive Totem's doors
+    let shield_id = FIRST_FRESH_INSTANCE_ID;
+    let hive_totem = BOSS_VULNERABILITY_FIXES.iter().find(|fix| fix.room_id == 0xC8309DF6).unwrap();
+
+    let objects = vec![trigger(weak_point_id), trigger(door_force_id), trigger(shield_id)];
+    with_test_area(hive_totem.room_id, objects, |area| {
+        let mut ps = test_patcher_state();
+        patch_boss_vulnerability(&mut ps, area, hive_totem).unwrap();
+
+        let layers = area.mrea().scly_section().layers.iter()
+            .map(|layer| layer.into_owned())
+            .collect::<Vec<_>>();
+        let power_vulnerability = |instance_id| layers[0].objects.iter()
+            .find(|obj| obj.instance_id == instance_id)
+            .unwrap()
+            .property_data.as_damageable_trigger().unwrap()
+            .damage_vulnerability.power;
+        assert_eq!(power_vulnerability(weak_point_id), 1); // Normal
+        assert_eq!(power_vulnerability(door_force_id), 2); // Reflect
+        assert_eq!(power_vulnerability(shield_id), 2); // Reflect
+    });
+}
+
+#[test]
+fn test_create_batch_outputs()
+{
+    // Its own directory per run, so concurrent test runs don't remove each other's files
+    let output_dir = std::env::temp_dir()
+        .join(format!("randomprime_test_create_batch_outputs_{}", std::process::id()));
+    std::fs::create_dir_all(&output_dir).unwrap();
+    let output_path = output_dir.join("seed_7.gcz");
+    let spoiler_path = output_dir.join("seed_7_spoiler.json");
+    let _ = std::fs::remove_file(&output_path);
+    let _ = std::fs::remove_file(&spoiler_path);
+
+    // Validating a batch must not leave any files behind
+    let (path, output, spoiler_log) = create_batch_outputs(&output_dir, 7, IsoFormat::Gcz, true, true)
+        .unwrap();
+    assert_eq!(path, output_path);
+    assert!(output.is_none() && spoiler_log.is_none());
+    assert!(!output_path.exists() && !spoiler_path.exists());
+
+    let (path, output, spoiler_log) = create_batch_outputs(&output_dir, 7, IsoFormat::Gcz, true, false)
+        .unwrap();
+    assert_eq!(path, output_path);
+    assert!(output.is_some() && spoiler_log.is_some());
+    assert!(output_path.exists() && spoiler_path.exists());
+
+    std::fs::remove_dir_all(&output_dir).unwrap();
+}
+
+#[test]
+fn test_main_menu_message_with_seed()
+{
+    let mut config = test_config();
+    config.seed = 12345;
+    config.item_seed = 0xABCD;
+    config.main_menu_message = "randomprime v1.0".to_string();
+    assert_eq!(main_menu_message_with_seed(&config), "randomprime v1.0");
+
+    config.show_seed_ingame = true;
+    assert_eq!(main_menu_message_with_seed(&config), "randomprime v1.0\nSeed 12345 - 000000000000ABCD");
+
+    config.main_menu_message = String::new();
+    assert_eq!(main_menu_message_with_seed(&config), "Seed 12345 - 000000000000ABCD");
+}
+
+#[test]
+fn test_check_custom_door_cmdl()
+{
+    // One material set holding one texture id, after the header and the padded size table
+    let mut cmdl_bytes = vec![0xDE, 0xAD, 0xBA, 0xBE, 0, 0, 0, 2];
+    cmdl_bytes.resize(0x24, 0);
+    cmdl_bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 8]);
+    cmdl_bytes.resize(0x40, 0);
+    cmdl_bytes.extend_from_slice(&[0, 0, 0, 1, 0xDE, 0xAF, 0, 0]);
+    assert!(check_custom_door_cmdl("test", &cmdl_bytes).is_ok());
+    let mut cmdl: structs::Cmdl = Reader::new(&cmdl_bytes).read(());
+    assert_eq!(cmdl.material_sets.as_mut_vec()[0].texture_ids.as_mut_vec()[0], 0xDEAF0000);
+
+    assert!(check_custom_door_cmdl("test", &cmdl_bytes[..0x44]).is_err());
+
+    // More texture ids than the material set has room for
+    cmdl_bytes[0x43] = 2;
+    assert!(check_custom_door_cmdl("test", &cmdl_bytes).is_err());
+
+    // More sections than the size table has room for
+    cmdl_bytes[0x43] = 1;
+    cmdl_bytes[0x27] = 0xFF;
+    assert!(check_custom_door_cmdl("test", &cmdl_bytes).is_err());
+}
+
+#[test]
+fn test_update_pickup_custom_aabb()
+{
+    let mut original = PickupType::Missile.pickup_data().clone();
+    original.cmdl = 0xDEAF1234;
+    let mut pickup = structs::SclyObject {
+        instance_id: 1,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::Pickup(original),
+    };
+    let pickup_type = MaybeObfuscatedPickup::Unobfuscated(PickupType::VariaSuit);
+
+    let mut custom_aabbs = HashMap::new();
+    assert_eq!(
+        update_pickup(&mut pickup.clone(), pickup_type, 0xFFFFFFFF, &custom_aabbs),
+        Err("No AABB for pickup CMDL 0xDEAF1234, add one to custom_pickup_aabbs".to_string()),
+    );
+
+    custom_aabbs.insert(0xDEAF1234, [-1.0, -1.0, -1.0, 1.0, 1.0, 1.0]);
+    assert!(update_pickup(&mut pickup, pickup_type, 0xFFFFFFFF, &custom_aabbs).is_ok());
+    assert_eq!(pickup.property_data.as_pickup().unwrap().cmdl, PickupType::VariaSuit.pickup_data().cmdl);
+}
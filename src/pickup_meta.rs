@@ -1,9 +1,15 @@
-use std::mem;
+use std::{
+    collections::HashMap,
+    mem,
+    sync::OnceLock,
+};
 
 use reader_writer::{FourCC, Reader};
 use structs::{Connection, ConnectionMsg, ConnectionState, Pickup, Resource, ResourceKind};
 
-use crate::{custom_asset_ids,door_meta::DoorLocation};
+use serde::Serialize;
+
+use crate::{custom_asset_ids, door_meta::{DoorLocation, World}, elevators::SpawnRoom, ResourceData};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum PickupType
@@ -46,6 +52,7 @@ pub enum PickupType
     Nothing,
     ScanVisor,
     ShinyMissile,
+    MissileLauncher,
 }
 
 impl PickupType
@@ -91,6 +98,7 @@ impl PickupType
             PickupType::Nothing =>             "Nothing",
             PickupType::ScanVisor =>           "Scan Visor",
             PickupType::ShinyMissile =>        "Shiny Missile",
+            PickupType::MissileLauncher =>     "Missile Launcher",
         }
     }
 
@@ -135,6 +143,7 @@ impl PickupType
             PickupType::Nothing =>             35,
             PickupType::ScanVisor =>           36,
             PickupType::ShinyMissile =>        37,
+            PickupType::MissileLauncher =>     38,
         }
     }
 
@@ -178,6 +187,7 @@ impl PickupType
             34 => Some(PickupType::ArtifactOfStrength),
             35 => Some(PickupType::Nothing),
             36 => Some(PickupType::ScanVisor),
+            38 => Some(PickupType::MissileLauncher),
             _ => None,
         }
     }
@@ -201,6 +211,17 @@ impl PickupType
         }
     }
 
+    // The pickup this counts as for door and completion logic. The Missile Launcher and the Shiny
+    // Missile both just give missiles like an expansion does.
+    pub fn logic_type(&self) -> PickupType
+    {
+        match self {
+            PickupType::MissileLauncher => PickupType::Missile,
+            PickupType::ShinyMissile =>    PickupType::Missile,
+            _ => *self,
+        }
+    }
+
     pub fn skip_hudmemos_strg(&self) -> u32
     {
         (custom_asset_ids::SKIP_HUDMEMO_STRG_START..custom_asset_ids::SKIP_HUDMEMO_STRG_END)
@@ -254,9 +275,16 @@ impl PickupType
             PickupType::Nothing,
             PickupType::ScanVisor,
             PickupType::ShinyMissile,
+            PickupType::MissileLauncher,
         ].iter().map(|i| *i)
     }
 
+    // The names accepted by from_string
+    pub fn all_names() -> Vec<&'static str>
+    {
+        PickupType::iter().map(|pt| pt.name()).collect()
+    }
+
     pub fn from_string(string: String) -> Self {
         for i in PickupType::iter() {
             if i.name().to_string().to_lowercase() == string.to_lowercase() {
@@ -281,14 +309,10 @@ impl PickupTable
             .collect())
     }
 
-    fn get<'a>() -> &'a PickupTable
+    fn get() -> &'static PickupTable
     {
-        static mut CACHED: Option<PickupTable> = None;
-        if unsafe { CACHED.is_none() } {
-            let pmt = PickupTable::new();
-            unsafe { CACHED = Some(pmt) };
-        }
-        unsafe { CACHED.as_ref().unwrap() }
+        static CACHED: OnceLock<PickupTable> = OnceLock::new();
+        CACHED.get_or_init(PickupTable::new)
     }
 }
 
@@ -301,9 +325,14 @@ impl std::ops::Index<PickupType> for PickupTable
     }
 }
 
-/// Lookup a pre-computed AABB for a pickup's CMDL
-pub fn aabb_for_pickup_cmdl(cmdl_id: u32) -> Option<[f32; 6]>
+/// Lookup a pre-computed AABB for a pickup's CMDL. `custom_aabbs` holds the AABBs of models that
+/// aren't in PICKUP_CMDL_AABBS (see ParsedConfig::custom_pickup_aabbs) and takes precedence over it.
+pub fn aabb_for_pickup_cmdl(cmdl_id: u32, custom_aabbs: &HashMap<u32, [f32; 6]>) -> Option<[f32; 6]>
 {
+    if let Some(aabb) = custom_aabbs.get(&cmdl_id) {
+        return Some(*aabb);
+    }
+
     // The aabb array is sorted, so we can binary search.
     if let Ok(idx) = PICKUP_CMDL_AABBS.binary_search_by_key(&cmdl_id, |&(k, _)| k) {
         // The arrays contents are stored as u32s to reduce percision loss from
@@ -360,6 +389,14 @@ const EXTRA_ASSETS_DOORS: &[(u32, [u8; 4], &[u8])] = &[
     (custom_asset_ids::WAVEBUSTER_DOOR_TXTR,      *b"TXTR", include_bytes!("../extra_assets/holorim_wavebuster.txtr")),
     (custom_asset_ids::ICESPREADER_DOOR_TXTR,     *b"TXTR", include_bytes!("../extra_assets/holorim_icespreader.txtr")),
     (custom_asset_ids::FLAMETHROWER_DOOR_TXTR,    *b"TXTR", include_bytes!("../extra_assets/holorim_flamethrower.txtr")),
+    (custom_asset_ids::WAVEBUSTER_DOOR_FORCEFIELD_TXTR,   *b"TXTR", include_bytes!("../extra_assets/forcefield_wavebuster.txtr")),
+    (custom_asset_ids::ICESPREADER_DOOR_FORCEFIELD_TXTR,  *b"TXTR", include_bytes!("../extra_assets/forcefield_icespreader.txtr")),
+    (custom_asset_ids::FLAMETHROWER_DOOR_FORCEFIELD_TXTR, *b"TXTR", include_bytes!("../extra_assets/forcefield_flamethrower.txtr")),
+    (custom_asset_ids::THERMAL_DOOR_TXTR,                 *b"TXTR", include_bytes!("../extra_assets/holorim_thermal.txtr")),
+    (custom_asset_ids::THERMAL_DOOR_FORCEFIELD_TXTR,      *b"TXTR", include_bytes!("../extra_assets/forcefield_thermal.txtr")),
+    (custom_asset_ids::XRAY_DOOR_FORCEFIELD_TXTR,         *b"TXTR", include_bytes!("../extra_assets/forcefield_xray.txtr")),
+    (custom_asset_ids::BOOST_DOOR_TXTR,                   *b"TXTR", include_bytes!("../extra_assets/holorim_boost.txtr")),
+    (custom_asset_ids::BOOST_DOOR_FORCEFIELD_TXTR,        *b"TXTR", include_bytes!("../extra_assets/forcefield_boost.txtr")),
 ];
 
 pub fn extra_assets_doors<'r>() -> Vec<Resource<'r>>
@@ -389,6 +426,19 @@ pub fn build_resource<'r>(file_id: u32, kind: ResourceKind<'r>) -> Resource<'r>
         original_offset: 0,
     }
 }
+
+// Like build_resource, but stores the data compressed the same way the game's paks do
+pub fn build_compressed_resource<'r>(file_id: u32, data: &[u8], fourcc: FourCC) -> Resource<'r>
+{
+    let mut compressed = ResourceData::compress(data);
+    let len = compressed.len();
+    compressed.extend(reader_writer::pad_bytes(32, len).iter());
+
+    let mut res = build_resource(file_id, ResourceKind::External(compressed, fourcc));
+    res.compressed = true;
+    res
+}
+
 pub fn extra_assets<'r>() -> Vec<Resource<'r>>
 {
     EXTRA_ASSETS.iter().map(|&(file_id, ref fourcc, bytes)| {
@@ -416,3 +466,116 @@ pub struct ObjectsToRemove
 }
 
 include!("pickup_meta.rs.in");
+
+// A room that can be named in the config as "World:Room", for tools that let the user pick one
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomDescriptor
+{
+    pub world_name: String,
+    pub room_name: &'static str,
+    pub room_id: u32,
+    pub pak_name: &'static str,
+    pub mlvl: u32,
+    // The room's index in its world's MLVL
+    pub mrea_idx: u32,
+}
+
+impl RoomDescriptor
+{
+    pub fn config_name(&self) -> String
+    {
+        format!("{}:{}", self.world_name, self.room_name)
+    }
+
+    pub fn to_spawn_room(&self) -> SpawnRoom
+    {
+        SpawnRoom {
+            pak_name: self.pak_name,
+            mlvl: self.mlvl,
+            mrea: self.room_id,
+            mrea_idx: self.mrea_idx,
+        }
+    }
+}
+
+// Every room in PICKUP_LOCATIONS order
+pub fn all_rooms() -> Vec<RoomDescriptor>
+{
+    PICKUP_LOCATIONS.iter()
+        .flat_map(|(pak_name, rooms)| {
+            let world = World::from_pak(pak_name).unwrap();
+            rooms.iter().enumerate().map(move |(idx, room_info)| RoomDescriptor {
+                world_name: world.as_string(),
+                room_name: room_info.name,
+                room_id: room_info.room_id,
+                pak_name,
+                mlvl: world.mlvl(),
+                mrea_idx: idx as u32,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_custom_aabbs()
+{
+    // Custom pickup models shipped with randomprime are already in the static table
+    let mut custom_aabbs = HashMap::new();
+    for &cmdl in &[custom_asset_ids::PHAZON_SUIT_CMDL, custom_asset_ids::NOTHING_CMDL,
+                   custom_asset_ids::SHINY_MISSILE_CMDL] {
+        assert!(aabb_for_pickup_cmdl(cmdl, &custom_aabbs).is_some());
+    }
+
+    let aabb = [-1.0, -1.0, 0.0, 1.0, 1.0, 2.0];
+    assert_eq!(aabb_for_pickup_cmdl(0xDEADBEEF, &custom_aabbs), None);
+    custom_aabbs.insert(0xDEADBEEF, aabb);
+    assert_eq!(aabb_for_pickup_cmdl(0xDEADBEEF, &custom_aabbs), Some(aabb));
+}
+
+
+#[test]
+fn test_missile_launcher()
+{
+    let launcher = PickupType::MissileLauncher;
+    assert_eq!(PickupType::from_idx(launcher.idx()), Some(launcher));
+    assert_eq!(PickupType::from_string("missile launcher".to_string()), launcher);
+    assert_eq!(launcher.logic_type(), PickupType::Missile);
+    assert_eq!(launcher.pickup_data().kind, PickupType::Missile.pickup_data().kind);
+    assert_eq!(launcher.dependencies(), PickupType::Missile.dependencies());
+    assert!(PickupType::iter().enumerate().all(|(i, pt)| pt.idx() == i));
+}
+
+#[test]
+fn test_pickup_data_from_multiple_threads()
+{
+    let threads: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| PickupType::iter().map(|pt| pt.pickup_data().kind).collect::<Vec<_>>()))
+        .collect();
+    let kinds: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert!(kinds.iter().all(|k| *k == kinds[0]));
+}
+
+#[test]
+fn test_all_rooms()
+{
+    let rooms = all_rooms();
+    let room_count: usize = PICKUP_LOCATIONS.iter().map(|(_, rooms)| rooms.len()).sum();
+    assert_eq!(rooms.len(), room_count);
+    let landing_site = rooms.iter().find(|room| room.config_name() == "Tallon Overworld:Landing Site").unwrap();
+    assert_eq!(landing_site.pak_name, "Metroid4.pak");
+    assert_eq!(landing_site.mlvl, World::TallonOverworld.mlvl());
+    let (_, tallon_rooms) = PICKUP_LOCATIONS.iter().find(|(pak_name, _)| *pak_name == "Metroid4.pak").unwrap();
+    assert_eq!(tallon_rooms[landing_site.mrea_idx as usize].room_id, landing_site.room_id);
+}
+
+#[test]
+fn test_pickup_type_all_names()
+{
+    let names = PickupType::all_names();
+    assert_eq!(names.len(), PickupType::iter().count());
+    // No two pickup types share a name, so from_string gets back the type each name came from
+    for (pickup_type, name) in PickupType::iter().zip(names.iter()) {
+        assert_eq!(PickupType::from_string(name.to_uppercase()), pickup_type);
+    }
+}
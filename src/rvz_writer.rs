@@ -0,0 +1,331 @@
+use reader_writer::byteorder::{BigEndian, WriteBytesExt};
+use sha1::{Digest, Sha1};
+use structs;
+
+use std::{
+    cmp::min,
+    io::{self, Seek, Write},
+};
+
+use crate::gcz_writer::{WrittenRegion, ZEROES};
+
+// Format description
+// https://github.com/dolphin-emu/dolphin/blob/master/docs/WiaAndRvz.md
+//
+// Only the "none" compression method is written. Most of the space savings of RVZ on a
+// GameCube disc come from the unused space, which is stored as empty groups.
+
+const RVZ_MAGIC: &[u8; 4] = b"RVZ\x01";
+const RVZ_VERSION: u32 = 0x01000000;
+const RVZ_VERSION_COMPATIBLE: u32 = 0x00030000;
+
+const FILE_HEAD_SIZE: usize = 0x48;
+const DISC_SIZE: usize = 0xDC;
+const RAW_DATA_ENTRY_SIZE: usize = 0x18;
+const GROUP_ENTRY_SIZE: usize = 0xC;
+const PART_ENTRY_SIZE: u32 = 0x30;
+
+const DISC_TYPE_GAMECUBE: u32 = 1;
+const COMPRESSION_NONE: u32 = 0;
+
+// The disc header is stored in the disc struct, so the raw data starts after it
+const DISC_HEADER_SIZE: usize = 0x80;
+
+// constants are fixed to one integer type...
+macro_rules! chunk_size {
+    () => { 2 * 1024 * 1024 }
+}
+
+pub struct RvzWriter<W: Write + Seek>
+{
+    expected_uncompressed_size: u64,
+    total_bytes_written: u64,
+    groups: Vec<(u32, u32)>,// (data offset / 4, data size)
+
+    disc_header: [u8; DISC_HEADER_SIZE],
+    input_buf_used: usize,
+    input_buf: Vec<u8>,
+
+    file: W,
+
+    regions: Option<Vec<WrittenRegion>>,
+    finished: bool,
+}
+
+impl<W: Write + Seek> RvzWriter<W>
+{
+    pub fn new(mut file: W, uncompressed_size: u64) -> io::Result<RvzWriter<W>>
+    {
+        file.seek(io::SeekFrom::Start(0))?;
+
+        let mut header_bytes = RvzWriter::<W>::header_size(uncompressed_size);
+        while header_bytes > 0 {
+            let l = min(ZEROES.len(), header_bytes);
+            file.write_all(&ZEROES[..l])?;
+            header_bytes -= l;
+        }
+
+        Ok(RvzWriter {
+            expected_uncompressed_size: uncompressed_size,
+            total_bytes_written: 0,
+            groups: Vec::with_capacity(RvzWriter::<W>::num_groups(uncompressed_size)),
+
+            disc_header: [0u8; DISC_HEADER_SIZE],
+            input_buf_used: 0,
+            input_buf: vec![0u8; chunk_size!()],
+
+            file,
+
+            regions: None,
+            finished: false,
+        })
+    }
+
+    fn num_groups(uncompressed_size: u64) -> usize
+    {
+        uncompressed_size.div_ceil(chunk_size!()) as usize
+    }
+
+    fn header_size(uncompressed_size: u64) -> usize
+    {
+        FILE_HEAD_SIZE + DISC_SIZE + RAW_DATA_ENTRY_SIZE
+            + GROUP_ENTRY_SIZE * RvzWriter::<W>::num_groups(uncompressed_size)
+    }
+
+    // Start keeping track of every group written, see regions()
+    pub fn record_regions(&mut self)
+    {
+        self.regions = Some(Vec::new());
+    }
+
+    // The groups written so far, if record_regions was called. Empty groups have a compressed
+    // length of 0.
+    pub fn regions(&self) -> Option<&[WrittenRegion]>
+    {
+        self.regions.as_ref().map(|regions| &regions[..])
+    }
+
+    fn push_group(&mut self, length: usize, data_size: usize)
+    {
+        let data_offset = if data_size == 0 {
+            0
+        } else {
+            RvzWriter::<W>::header_size(self.expected_uncompressed_size) as u64 + self.total_bytes_written
+        };
+        if let Some(regions) = self.regions.as_mut() {
+            regions.push(WrittenRegion {
+                offset: self.groups.len() as u64 * chunk_size!(),
+                length: length as u64,
+                compressed_length: data_size as u64,
+            });
+        }
+        self.groups.push(((data_offset >> 2) as u32, data_size as u32));
+        self.total_bytes_written += data_size as u64;
+    }
+
+    // Write out the buffered group, or record it as empty if it is all zeroes
+    fn write_group(&mut self) -> io::Result<()>
+    {
+        let length = self.input_buf_used;
+        if self.groups.is_empty() {
+            self.disc_header.copy_from_slice(&self.input_buf[..DISC_HEADER_SIZE]);
+        }
+        if self.input_buf[..length].iter().all(|&b| b == 0) {
+            self.push_group(length, 0);
+        } else {
+            self.file.write_all(&self.input_buf[..length])?;
+            self.push_group(length, length);
+        }
+        self.input_buf_used = 0;
+        Ok(())
+    }
+
+    // Write out the last group and the headers. This happens automatically on drop, but calling
+    // it directly allows errors to be handled and regions() to be inspected afterwards.
+    pub fn finish(&mut self) -> io::Result<()>
+    {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        // Anything not written is treated as unused space
+        let written = self.groups.len() as u64 * chunk_size!() + self.input_buf_used as u64;
+        if written < self.expected_uncompressed_size {
+            structs::WriteExt::skip_bytes(self, self.expected_uncompressed_size - written)?;
+        } else if written > self.expected_uncompressed_size {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Wrote {} bytes to an RVZ of {} bytes", written, self.expected_uncompressed_size)));
+        }
+        if self.input_buf_used != 0 {
+            self.write_group()?;
+        }
+
+        let header_size = RvzWriter::<W>::header_size(self.expected_uncompressed_size);
+        let raw_data_offset = (FILE_HEAD_SIZE + DISC_SIZE) as u64;
+        let group_offset = raw_data_offset + RAW_DATA_ENTRY_SIZE as u64;
+
+        let mut raw_data = Vec::with_capacity(RAW_DATA_ENTRY_SIZE);
+        raw_data.write_u64::<BigEndian>(DISC_HEADER_SIZE as u64)?;
+        raw_data.write_u64::<BigEndian>(self.expected_uncompressed_size - DISC_HEADER_SIZE as u64)?;
+        raw_data.write_u32::<BigEndian>(0)?;
+        raw_data.write_u32::<BigEndian>(self.groups.len() as u32)?;
+
+        let mut groups = Vec::with_capacity(GROUP_ENTRY_SIZE * self.groups.len());
+        for &(data_offset, data_size) in &self.groups {
+            groups.write_u32::<BigEndian>(data_offset)?;
+            groups.write_u32::<BigEndian>(data_size)?;
+            groups.write_u32::<BigEndian>(0)?;// not packed
+        }
+
+        let mut disc = Vec::with_capacity(DISC_SIZE);
+        disc.write_u32::<BigEndian>(DISC_TYPE_GAMECUBE)?;
+        disc.write_u32::<BigEndian>(COMPRESSION_NONE)?;
+        disc.write_i32::<BigEndian>(0)?;
+        disc.write_u32::<BigEndian>(chunk_size!())?;
+        disc.write_all(&self.disc_header)?;
+        disc.write_u32::<BigEndian>(0)?;// no partitions
+        disc.write_u32::<BigEndian>(PART_ENTRY_SIZE)?;
+        disc.write_u64::<BigEndian>(0)?;
+        disc.write_all(&sha1(&[]))?;
+        disc.write_u32::<BigEndian>(1)?;
+        disc.write_u64::<BigEndian>(raw_data_offset)?;
+        disc.write_u32::<BigEndian>(raw_data.len() as u32)?;
+        disc.write_u32::<BigEndian>(self.groups.len() as u32)?;
+        disc.write_u64::<BigEndian>(group_offset)?;
+        disc.write_u32::<BigEndian>(groups.len() as u32)?;
+        disc.write_all(&[0u8; 8])?;// no compressor data
+        assert_eq!(disc.len(), DISC_SIZE);
+
+        let mut file_head = Vec::with_capacity(FILE_HEAD_SIZE);
+        file_head.write_all(RVZ_MAGIC)?;
+        file_head.write_u32::<BigEndian>(RVZ_VERSION)?;
+        file_head.write_u32::<BigEndian>(RVZ_VERSION_COMPATIBLE)?;
+        file_head.write_u32::<BigEndian>(DISC_SIZE as u32)?;
+        file_head.write_all(&sha1(&disc))?;
+        file_head.write_u64::<BigEndian>(self.expected_uncompressed_size)?;
+        file_head.write_u64::<BigEndian>(header_size as u64 + self.total_bytes_written)?;
+        let file_head_hash = sha1(&file_head);
+        file_head.write_all(&file_head_hash)?;
+        assert_eq!(file_head.len(), FILE_HEAD_SIZE);
+
+        // Seek the file back to the start and write the headers
+        self.file.seek(io::SeekFrom::Start(0))?;
+        self.file.write_all(&file_head)?;
+        self.file.write_all(&disc)?;
+        self.file.write_all(&raw_data)?;
+        self.file.write_all(&groups)?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Write for RvzWriter<W>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        self.write_all(buf).map(|()| buf.len())
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()>
+    {
+        while !buf.is_empty() {
+            let l = min(chunk_size!() - self.input_buf_used, buf.len());
+            self.input_buf[self.input_buf_used..self.input_buf_used + l].copy_from_slice(&buf[..l]);
+            self.input_buf_used += l;
+            buf = &buf[l..];
+
+            if self.input_buf_used == chunk_size!() {
+                self.write_group()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.file.flush()
+    }
+}
+
+impl<W: Write + Seek> structs::WriteExt for RvzWriter<W>
+{
+    fn skip_bytes(&mut self, mut bytes: u64) -> io::Result<()>
+    {
+        // Finish the current group with zeroes
+        while self.input_buf_used != 0 && bytes > 0 {
+            let l = min((chunk_size!() - self.input_buf_used) as u64, min(bytes, ZEROES.len() as u64));
+            self.write_all(&ZEROES[..l as usize])?;
+            bytes -= l;
+        }
+
+        // Whole groups of zeroes take no space at all. The disc header always has to be
+        // buffered though, so it ends up in the disc struct.
+        while bytes >= chunk_size!() && !self.groups.is_empty() {
+            self.push_group(chunk_size!(), 0);
+            bytes -= chunk_size!();
+        }
+
+        // Write leftover zeroes
+        while bytes > 0 {
+            let l = min(bytes, ZEROES.len() as u64);
+            self.write_all(&ZEROES[..l as usize])?;
+            bytes -= l;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Drop for RvzWriter<W>
+{
+    fn drop(&mut self)
+    {
+        let res = self.finish();
+        // We really don't want to panic from a destructor, so just write a warning instead
+        if let Err(e) = res {
+            eprintln!("Error closing RvzWriter: {}", e);
+        };
+    }
+}
+
+// The headers are checked against their SHA-1 hashes when the file is opened
+fn sha1(data: &[u8]) -> [u8; 20]
+{
+    Sha1::digest(data).into()
+}
+
+#[test]
+fn test_sha1()
+{
+    let hex = |digest: [u8; 20]| digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+        hex(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+}
+
+#[test]
+fn test_rvz_writer_groups()
+{
+    use structs::WriteExt;
+
+    let size = 3 * chunk_size!() + 0x8000;
+    let mut file = io::Cursor::new(Vec::new());
+    {
+        let mut writer = RvzWriter::new(&mut file, size).unwrap();
+        writer.write_all(&[0xAB; 0x100]).unwrap();
+        writer.skip_bytes(2 * chunk_size!()).unwrap();
+        writer.write_all(&[0xCD; 0x10]).unwrap();
+        writer.finish().unwrap();
+    }
+    let bytes = file.into_inner();
+    let header_size = RvzWriter::<io::Cursor<Vec<u8>>>::header_size(size);
+
+    assert_eq!(&bytes[..4], RVZ_MAGIC);
+    assert_eq!(&bytes[FILE_HEAD_SIZE - 20..FILE_HEAD_SIZE], &sha1(&bytes[..FILE_HEAD_SIZE - 20])[..]);
+    assert_eq!(&bytes[0x24..0x2C], &size.to_be_bytes());
+    // Two groups of data, with the middle and trailing groups left empty
+    assert_eq!(bytes.len(), header_size + 2 * chunk_size!());
+    assert_eq!(&bytes[FILE_HEAD_SIZE + 0x10..FILE_HEAD_SIZE + 0x90], &[0xAB; 0x80][..]);
+}
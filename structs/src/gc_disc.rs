@@ -65,6 +65,51 @@ pub trait ProgressNotifier
     fn notify_writing_header(&mut self);
     fn notify_flushing_to_disk(&mut self);
     fn notify_stacking_warning(&mut self);
+
+    // Called repeatedly while a file is being written, so large paks can report
+    // smooth progress. Does nothing unless overridden.
+    fn notify_file_progress(&mut self, _file_name: &str, _bytes_written: u64, _total: u64)
+    {
+    }
+
+    // Called once the header is written and again after each file, with the bytes written so far
+    // out of the total passed to notify_total_bytes. Does nothing unless overridden.
+    fn notify_write_progress(&mut self, _bytes_written: u64, _total: u64)
+    {
+    }
+}
+
+// How many bytes are written between calls to notify_file_progress
+const FILE_PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+struct ProgressWriter<'a, W, N>
+{
+    writer: &'a mut W,
+    notifier: &'a mut N,
+    file_name: &'a str,
+    bytes_written: u64,
+    total: u64,
+}
+
+impl<'a, W, N> Write for ProgressWriter<'a, W, N>
+    where W: Write,
+          N: ProgressNotifier,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        let written = self.writer.write(buf)?;
+        let before = self.bytes_written / FILE_PROGRESS_INTERVAL;
+        self.bytes_written += written as u64;
+        if self.bytes_written / FILE_PROGRESS_INTERVAL != before {
+            self.notifier.notify_file_progress(self.file_name, self.bytes_written, self.total);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.writer.flush()
+    }
 }
 
 pub trait WriteExt
@@ -131,7 +176,9 @@ impl<'r> GcDisc<'r>
 
         let fst_end = (self.header.fst_offset + self.header.fst_length) as u64;
         writer.skip_bytes(files_offset as u64 - fst_end)?;
-        FstEntry::write_files(writer, notifier, &raw_fst)
+        let bytes_written = (total_size - file_system_size) as u64;
+        notifier.notify_write_progress(bytes_written, total_size as u64);
+        FstEntry::write_files(writer, notifier, &raw_fst, bytes_written, total_size as u64)
     }
 }
 
@@ -335,8 +382,13 @@ impl<'r> FstEntry<'r>
         state.entries
     }
 
-    fn write_files<W, N>(writer: &mut W, notifier: &mut N, fst_entries: &[WrappedFstEntry])
-        -> io::Result<()>
+    fn write_files<W, N>(
+        writer: &mut W,
+        notifier: &mut N,
+        fst_entries: &[WrappedFstEntry],
+        mut bytes_written: u64,
+        total_size: u64,
+    ) -> io::Result<()>
         where W: Write,
               N: ProgressNotifier,
     {
@@ -354,8 +406,20 @@ impl<'r> FstEntry<'r>
         for (e, zeroes) in entries_and_zeroes {
             if let Some(f) = e.file {
                 notifier.notify_writing_file(&e.name, e.raw_entry.length as usize);
-                f.write_to(writer)?;
+                let file_name = e.name.to_string_lossy();
+                let total = e.raw_entry.length as u64;
+                let mut progress_writer = ProgressWriter {
+                    writer: &mut *writer,
+                    notifier: &mut *notifier,
+                    file_name: &file_name,
+                    bytes_written: 0,
+                    total,
+                };
+                f.write_to(&mut progress_writer)?;
+                notifier.notify_file_progress(&file_name, total, total);
                 writer.write_all(&zero_bytes[0..zeroes as usize])?;
+                bytes_written += total;
+                notifier.notify_write_progress(bytes_written, total_size);
             }
         }
         Ok(())
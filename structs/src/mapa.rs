@@ -60,6 +60,16 @@ pub enum MapaObjectType {
     DoorPlasmaFloor2   = 15,
 }
 
+// When the area (or an object on its map) is drawn on the map screen
+#[derive(Debug, Clone)]
+pub enum MapaVisibilityMode {
+    Always             = 0,
+    MapStationOrVisit  = 1,
+    Visit              = 2,
+    Never              = 3,
+    MapStationOrVisit2 = 4,
+}
+
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Debug, Clone)]
 pub struct MapaObject
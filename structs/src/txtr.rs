@@ -15,7 +15,7 @@ pub struct Txtr<'r>
 
     // TODO Palettes...
 
-    #[auto_struct(init = (format_pixel_bytes(format, (height * width) as usize), ()))]
+    #[auto_struct(init = (format_pixel_bytes(format, height as usize * width as usize), ()))]
     pub pixel_data: LazyArray<'r, u8>,
     // TODO: Mipmaps
 